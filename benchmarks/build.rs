@@ -0,0 +1,6 @@
+fn main() {
+    // structures used by the tests for flat_message_codegen
+    let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("codegen_test.rs");
+    flat_message_codegen::generate_file("schemas/codegen_test.toml", out).unwrap();
+    println!("cargo:rerun-if-changed=schemas/codegen_test.toml");
+}
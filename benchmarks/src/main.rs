@@ -257,6 +257,42 @@ impl<'a, T: FlatMessage<'a>> FlatMessage<'a> for Wrapper<T> {
     {
         Ok(Wrapper(T::deserialize_from_unchecked(input)?))
     }
+
+    fn deserialize_from_slice(input: &'a [u8]) -> std::result::Result<Self, flat_message::Error>
+    where
+        Self: Sized,
+    {
+        unsafe { Self::deserialize_from_slice_unchecked(input) }
+    }
+
+    fn deserialize_from_with_options(
+        input: &'a Storage,
+        _: &flat_message::DeserializeOptions,
+    ) -> std::result::Result<Self, flat_message::Error>
+    where
+        Self: Sized,
+    {
+        unsafe { Self::deserialize_from_unchecked(input) }
+    }
+
+    fn deserialize_from_slice_with_options(
+        input: &'a [u8],
+        _: &flat_message::DeserializeOptions,
+    ) -> std::result::Result<Self, flat_message::Error>
+    where
+        Self: Sized,
+    {
+        unsafe { Self::deserialize_from_slice_unchecked(input) }
+    }
+
+    unsafe fn deserialize_from_slice_unchecked(
+        input: &'a [u8],
+    ) -> std::result::Result<Self, flat_message::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Wrapper(T::deserialize_from_slice_unchecked(input)?))
+    }
 }
 
 fn add_benches<'a, T: FlatMessageOwned + Clone + Serialize + DeserializeOwned + GetSize>(
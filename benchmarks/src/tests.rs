@@ -828,6 +828,3240 @@ fn check_max_size_for_serialization() {
     }
 }
 
+#[test]
+fn check_authentication() {
+    #[flat_message(checksum: true, validate_name: true)]
+    struct TestStruct<'a> {
+        value: u32,
+        name: &'a str,
+    }
+    let key = [7u8; 32];
+    let s = TestStruct {
+        value: 123456,
+        name: "John",
+        metadata: MetaDataBuilder::new().timestamp(1).unique_id(2).build(),
+    };
+    let mut plain = Storage::default();
+    let mut signed = Storage::default();
+    s.serialize_to(&mut plain, Config::default()).unwrap();
+    s.serialize_to(&mut signed, ConfigBuilder::new().authentication_key(key).build())
+        .unwrap();
+    // MAC is 32 bytes long
+    assert_eq!(signed.len(), plain.len() + 32);
+    assert!(Verifier::new(key).verify(&signed).is_ok());
+    assert_eq!(
+        Verifier::new([8u8; 32]).verify(&signed),
+        Err(flat_message::Error::AuthenticationFailed)
+    );
+    assert_eq!(
+        Verifier::new(key).verify(&plain),
+        Err(flat_message::Error::AuthenticationFailed)
+    );
+    // the MAC does not prevent normal deserialization
+    let d = TestStruct::deserialize_from(&signed).unwrap();
+    assert_eq!(d.value, 123456);
+    assert_eq!(d.name, "John");
+    assert_eq!(d.metadata.timestamp(), Some(1));
+    assert_eq!(d.metadata.unique_id(), Some(2));
+    let buf = FlatMessageBuffer::try_from(&signed).unwrap();
+    assert_eq!(buf.name(), Some(name!("TestStruct")));
+    check_field_value!(name!("value"), u32, 123456, buf);
+    let si = StructureInformation::try_from(&signed).unwrap();
+    assert_eq!(si.name(), Some(name!("TestStruct")));
+    assert_eq!(si.unique_id(), Some(2));
+}
+
+#[test]
+fn check_authentication_tampered_message() {
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        value: u32,
+    }
+    let key = [1u8; 32];
+    let mut storage = Storage::default();
+    TestStruct { value: 123456 }
+        .serialize_to(&mut storage, ConfigBuilder::new().authentication_key(key).build())
+        .unwrap();
+    assert!(Verifier::new(key).verify(&storage).is_ok());
+    // change the value of the field (first byte after the header)
+    let mut tampered = storage.as_slice().to_vec();
+    tampered[8] ^= 0xFF;
+    let tampered = Storage::from_buffer(&tampered);
+    assert_eq!(
+        Verifier::new(key).verify(&tampered),
+        Err(flat_message::Error::AuthenticationFailed)
+    );
+    // tampering with the MAC itself is also detected
+    let mut tampered = storage.as_slice().to_vec();
+    let len = tampered.len();
+    tampered[len - 1] ^= 0x01;
+    let tampered = Storage::from_buffer(&tampered);
+    assert_eq!(
+        Verifier::new(key).verify(&tampered),
+        Err(flat_message::Error::AuthenticationFailed)
+    );
+}
+
+#[test]
+fn check_authentication_on_deserialize() {
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        value: u32,
+    }
+    let key = [3u8; 32];
+    let config = ConfigBuilder::new().authentication_key(key).build();
+    let mut storage = Storage::default();
+    TestStruct { value: 123456 }.serialize_to(&mut storage, config).unwrap();
+    // the key from the configuration is used to check the MAC
+    let options = DeserializeOptions::from(config);
+    assert_eq!(options.authentication_key(), Some(&key));
+    let d = TestStruct::deserialize_from_with_options(&storage, &options).unwrap();
+    assert_eq!(d.value, 123456);
+
+    let mut tampered = storage.as_slice().to_vec();
+    tampered[8] ^= 0xFF;
+    let tampered = Storage::from_buffer(&tampered);
+    assert_eq!(
+        TestStruct::deserialize_from_with_options(&tampered, &options).err(),
+        Some(flat_message::Error::AuthenticationFailed)
+    );
+    // without a key the MAC is not checked
+    assert!(TestStruct::deserialize_from(&tampered).is_ok());
+    // a different key or a message without a MAC are rejected as well
+    let other = DeserializeOptionsBuilder::new().authentication_key([4u8; 32]).build();
+    assert_eq!(
+        TestStruct::deserialize_from_with_options(&storage, &other).err(),
+        Some(flat_message::Error::AuthenticationFailed)
+    );
+    let mut plain = Storage::default();
+    TestStruct { value: 123456 }.serialize_to(&mut plain, Config::default()).unwrap();
+    assert_eq!(
+        TestStruct::deserialize_from_with_options(&plain, &options).err(),
+        Some(flat_message::Error::AuthenticationFailed)
+    );
+}
+
+#[test]
+fn check_encryption() {
+    use std::collections::HashMap;
+    #[flat_message(checksum: true, validate_name: true)]
+    struct TestStruct<'a> {
+        value: u32,
+        name: &'a str,
+        list: Vec<u64>,
+    }
+    let s = TestStruct {
+        value: 123456,
+        name: "John",
+        list: vec![1, 2, 3],
+        metadata: MetaDataBuilder::new().timestamp(10).unique_id(20).build(),
+    };
+    let mut keys = HashMap::new();
+    keys.insert(5u32, [3u8; 32]);
+    let mut plain = Storage::default();
+    s.serialize_to(&mut plain, Config::default()).unwrap();
+    let mut storage = Storage::default();
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    encryption::encrypt(&mut storage, 5, &keys).unwrap();
+    assert_eq!(storage.len(), plain.len() + 32);
+    // metadata and name are still readable
+    let si = StructureInformation::try_from(&storage).unwrap();
+    assert_eq!(si.name(), Some(name!("TestStruct")));
+    assert_eq!(si.timestamp(), Some(10));
+    assert_eq!(si.unique_id(), Some(20));
+    // fields are not
+    assert_eq!(
+        TestStruct::deserialize_from(&storage).err(),
+        Some(flat_message::Error::EncryptedMessage)
+    );
+    assert!(FlatMessageBuffer::try_from(&storage).is_err());
+    assert_eq!(
+        encryption::encrypt(&mut storage, 5, &keys),
+        Err(flat_message::Error::EncryptedMessage)
+    );
+    encryption::decrypt(&mut storage, &keys).unwrap();
+    assert_eq!(storage, plain);
+    let d = TestStruct::deserialize_from(&storage).unwrap();
+    assert_eq!(d.value, 123456);
+    assert_eq!(d.name, "John");
+    assert_eq!(d.list, [1, 2, 3]);
+    assert_eq!(
+        encryption::decrypt(&mut storage, &keys),
+        Err(flat_message::Error::NotEncrypted)
+    );
+}
+
+#[test]
+fn check_encryption_invalid_key_or_tampering() {
+    use std::collections::HashMap;
+    #[flat_message]
+    struct TestStruct {
+        value: u32,
+    }
+    let mut keys = HashMap::new();
+    keys.insert(1u32, [1u8; 32]);
+    let mut storage = Storage::default();
+    TestStruct {
+        value: 123456,
+        metadata: MetaDataBuilder::new().unique_id(1).build(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(
+        encryption::encrypt(&mut storage, 2, &keys),
+        Err(flat_message::Error::UnknownEncryptionKey(2))
+    );
+    encryption::encrypt(&mut storage, 1, &keys).unwrap();
+    // same key id but a different key
+    let mut other_keys = HashMap::new();
+    other_keys.insert(1u32, [2u8; 32]);
+    let mut copy = Storage::from_buffer(storage.as_slice());
+    assert_eq!(
+        encryption::decrypt(&mut copy, &other_keys),
+        Err(flat_message::Error::DecryptionFailed)
+    );
+    // metadata is authenticated as well (unique id is stored after the hash table)
+    let mut tampered = storage.as_slice().to_vec();
+    let uid_offset = tampered.len() - 32 - 4 - 8;
+    tampered[uid_offset] ^= 0xFF;
+    let mut tampered = Storage::from_buffer(&tampered);
+    assert_eq!(
+        encryption::decrypt(&mut tampered, &keys),
+        Err(flat_message::Error::DecryptionFailed)
+    );
+    encryption::decrypt(&mut storage, &keys).unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().value, 123456);
+}
+
+#[test]
+fn check_encryption_with_authentication() {
+    use std::collections::HashMap;
+    #[flat_message(checksum: true)]
+    struct TestStruct {
+        value: String,
+    }
+    let mut keys = HashMap::new();
+    keys.insert(7u32, [9u8; 32]);
+    let mac_key = [4u8; 32];
+    let mut storage = Storage::default();
+    TestStruct {
+        value: "secret".to_string(),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(
+        &mut storage,
+        ConfigBuilder::new().authentication_key(mac_key).build(),
+    )
+    .unwrap();
+    encryption::encrypt(&mut storage, 7, &keys).unwrap();
+    // the MAC covers the plain message (it is checked after the message is decrypted)
+    assert_eq!(
+        Verifier::new(mac_key).verify(&storage),
+        Err(flat_message::Error::EncryptedMessage)
+    );
+    let options = DeserializeOptionsBuilder::new().authentication_key(mac_key).build();
+    assert_eq!(
+        TestStruct::deserialize_from_with_options(&storage, &options).err(),
+        Some(flat_message::Error::EncryptedMessage)
+    );
+    encryption::decrypt(&mut storage, &keys).unwrap();
+    assert!(Verifier::new(mac_key).verify(&storage).is_ok());
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().value, "secret");
+    let d = TestStruct::deserialize_from_with_options(&storage, &options).unwrap();
+    assert_eq!(d.value, "secret");
+}
+
+#[test]
+fn check_compression() {
+    #[flat_message(checksum: true)]
+    struct TestStruct {
+        text: String,
+        values: Vec<u32>,
+        value: u8,
+    }
+    let s = TestStruct {
+        text: "Hello, World ! ".repeat(100),
+        values: vec![1; 200],
+        value: 3,
+        metadata: MetaDataBuilder::new().timestamp(1).unique_id(2).build(),
+    };
+    let mut plain = Storage::default();
+    s.serialize_to(&mut plain, Config::default()).unwrap();
+    let mut storage = Storage::default();
+    let config = ConfigBuilder::new().compression(Compression::Lz4).build();
+    s.serialize_to(&mut storage, config).unwrap();
+    assert!(storage.len() < plain.len() / 4);
+    assert_eq!(&storage.as_slice()[..4], b"GTH\x11");
+    // metadata is still available
+    let si = StructureInformation::try_from(&storage).unwrap();
+    assert_eq!(si.name(), Some(name!("TestStruct")));
+    assert_eq!(si.timestamp(), Some(1));
+    assert_eq!(
+        TestStruct::deserialize_from(&storage).err(),
+        Some(flat_message::Error::CompressedMessage)
+    );
+    assert_eq!(
+        FlatMessageBuffer::try_from(&storage).err(),
+        Some(flat_message::Error::CompressedMessage)
+    );
+    compression::decompress(&mut storage, Config::default()).unwrap();
+    assert_eq!(storage, plain);
+    let d = TestStruct::deserialize_from(&storage).unwrap();
+    assert_eq!(d.text, s.text);
+    assert_eq!(d.values, s.values);
+    assert_eq!(d.value, 3);
+    // decompressing a message that is not compressed does nothing
+    compression::decompress(&mut storage, Config::default()).unwrap();
+    assert_eq!(storage, plain);
+}
+
+#[test]
+fn check_compression_transparent_deserialization() {
+    #[flat_message(metadata: false)]
+    struct TestStruct<'a> {
+        text: &'a str,
+        value: u32,
+    }
+    let text = "abc ".repeat(300);
+    let s = TestStruct { text: &text, value: 7 };
+    let key = [5u8; 32];
+    let config = ConfigBuilder::new()
+        .compression(Compression::Lz4)
+        .authentication_key(key)
+        .build();
+    let mut storage = Storage::default();
+    s.serialize_to(&mut storage, config).unwrap();
+    assert!(compression::is_compressed(storage.as_slice()));
+    let mut buffer = Storage::default();
+    let d = TestStruct::deserialize_from_storage(&storage, &mut buffer, config).unwrap();
+    assert_eq!(d.text, text);
+    assert_eq!(d.value, 7);
+    // the decompressed message is still checked (max size and MAC)
+    let mut buffer = Storage::default();
+    let small = ConfigBuilder::new().max_size(256).build();
+    assert!(matches!(
+        TestStruct::deserialize_from_storage(&storage, &mut buffer, small),
+        Err(flat_message::Error::ExceedMaxSize(_))
+    ));
+    let other_key = ConfigBuilder::new().authentication_key([6u8; 32]).build();
+    assert_eq!(
+        TestStruct::deserialize_from_storage(&storage, &mut buffer, other_key).err(),
+        Some(flat_message::Error::AuthenticationFailed)
+    );
+    // messages that are not compressed are deserialized in place
+    let mut plain = Storage::default();
+    s.serialize_to(&mut plain, Config::default()).unwrap();
+    let mut buffer = Storage::default();
+    let d = TestStruct::deserialize_from_storage(&plain, &mut buffer, Config::default()).unwrap();
+    assert_eq!(d.text, text);
+    assert_eq!(buffer.len(), 0);
+}
+
+#[test]
+fn check_compression_threshold() {
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        text: String,
+    }
+    let s = TestStruct {
+        text: "a".repeat(100),
+    };
+    let mut plain = Storage::default();
+    s.serialize_to(&mut plain, Config::default()).unwrap();
+    let mut storage = Storage::default();
+    // data region is smaller than the threshold
+    let config = ConfigBuilder::new()
+        .compression(Compression::Lz4)
+        .compression_threshold(1000)
+        .build();
+    s.serialize_to(&mut storage, config).unwrap();
+    assert_eq!(storage, plain);
+    let config = ConfigBuilder::new()
+        .compression(Compression::Lz4)
+        .compression_threshold(10)
+        .build();
+    s.serialize_to(&mut storage, config).unwrap();
+    assert!(storage.len() < plain.len());
+    // not enough room to decompress
+    assert_eq!(
+        compression::decompress(&mut storage, ConfigBuilder::new().max_size(64).build()),
+        Err(flat_message::Error::ExceedMaxSize((plain.len() as u32, 64)))
+    );
+    // corrupted compressed data
+    let mut corrupted = storage.as_slice().to_vec();
+    corrupted[8] = 0xFF;
+    let mut corrupted = Storage::from_buffer(&corrupted);
+    assert_eq!(
+        compression::decompress(&mut corrupted, Config::default()),
+        Err(flat_message::Error::DecompressionFailed)
+    );
+    compression::decompress(&mut storage, Config::default()).unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().text, s.text);
+}
+
+#[test]
+fn check_compression_with_authentication_and_encryption() {
+    use std::collections::HashMap;
+    #[flat_message(checksum: true)]
+    struct TestStruct {
+        text: String,
+    }
+    let mut keys = HashMap::new();
+    keys.insert(1u32, [5u8; 32]);
+    let mac_key = [6u8; 32];
+    let s = TestStruct {
+        text: "secret ".repeat(200),
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    let config = ConfigBuilder::new()
+        .compression(Compression::Lz4)
+        .authentication_key(mac_key)
+        .build();
+    s.serialize_to(&mut storage, config).unwrap();
+    encryption::encrypt(&mut storage, 1, &keys).unwrap();
+    assert_eq!(
+        compression::decompress(&mut storage, Config::default()),
+        Err(flat_message::Error::EncryptedMessage)
+    );
+    encryption::decrypt(&mut storage, &keys).unwrap();
+    assert_eq!(
+        Verifier::new(mac_key).verify(&storage),
+        Err(flat_message::Error::CompressedMessage)
+    );
+    compression::decompress(&mut storage, Config::default()).unwrap();
+    Verifier::new(mac_key).verify(&storage).unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().text, s.text);
+}
+
+#[test]
+fn check_compressed_fields() {
+    #[flat_message]
+    struct TestStruct {
+        #[flat_message(compress = "lz4")]
+        text: String,
+        #[flat_message(compress = "lz4")]
+        data: Vec<u8>,
+        #[flat_message(compress = "lz4")]
+        names: Vec<String>,
+        value: u32,
+        name: String,
+    }
+    let s = TestStruct {
+        text: "Hello, World ! ".repeat(100),
+        data: vec![7; 1000],
+        names: vec!["John".repeat(50), String::new(), "Ana".repeat(50)],
+        value: 123,
+        name: "Test".to_string(),
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    assert!(storage.len() < 500);
+    let d = TestStruct::deserialize_from(&storage).unwrap();
+    assert_eq!(d.text, s.text);
+    assert_eq!(d.data, s.data);
+    assert_eq!(d.names, s.names);
+    assert_eq!(d.value, 123);
+    assert_eq!(d.name, "Test");
+    let d = unsafe { TestStruct::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(d.text, s.text);
+    assert_eq!(d.names, s.names);
+    // random access
+    let buf = FlatMessageBuffer::try_from(&storage).unwrap();
+    check_field_value!(name!("value"), u32, 123, buf);
+    check_field_value!(name!("name"), &str, "Test", buf);
+    assert_eq!(buf.get_compressed::<String>(name!("text")), Some(s.text.clone()));
+    assert_eq!(buf.get_compressed::<Vec<u8>>(name!("data")), Some(s.data.clone()));
+    assert_eq!(buf.get_compressed::<Vec<String>>(name!("names")), Some(s.names.clone()));
+    // a compressed field is not a regular field
+    assert_eq!(buf.get::<&str>(name!("text")), None);
+    assert_eq!(buf.get_compressed::<String>(name!("name")), None);
+}
+
+#[test]
+fn check_compressed_fields_invalid_data() {
+    #[flat_message(metadata: false)]
+    struct Compressed {
+        #[flat_message(compress = "lz4")]
+        text: String,
+    }
+    #[flat_message(metadata: false)]
+    struct Plain {
+        text: String,
+    }
+    let s = Compressed {
+        text: "abc".repeat(100),
+    };
+    let mut storage = Storage::default();
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    assert!(Plain::deserialize_from(&storage).is_err());
+    // corrupt the sizes of the compressed field
+    let mut data = storage.as_slice().to_vec();
+    data[8] = 0xFF;
+    let storage = Storage::from_buffer(&data);
+    assert!(matches!(
+        Compressed::deserialize_from(&storage),
+        Err(flat_message::Error::FailToDeserialize(_))
+    ));
+    assert!(matches!(
+        unsafe { Compressed::deserialize_from_unchecked(&storage) },
+        Err(flat_message::Error::FailToDeserialize(_))
+    ));
+}
+
+#[test]
+fn check_frames() {
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        id: u32,
+        text: String,
+    }
+    let mut writer = FrameWriter::new(Vec::new(), Config::default());
+    for id in 0..10 {
+        let s = TestStruct {
+            id,
+            text: "x".repeat(id as usize * 100),
+        };
+        writer.write(&s).unwrap();
+    }
+    writer.flush().unwrap();
+    let stream = writer.into_inner();
+    let mut reader = FrameReader::new(std::io::Cursor::new(stream), Config::default());
+    for id in 0..10 {
+        let storage = reader.read().unwrap().unwrap();
+        let d = TestStruct::deserialize_from(storage).unwrap();
+        assert_eq!(d.id, id);
+        assert_eq!(d.text.len(), id as usize * 100);
+    }
+    assert_eq!(reader.read(), Ok(None));
+    assert_eq!(reader.skipped(), 0);
+}
+
+#[test]
+fn check_frames_resync_and_max_size() {
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        id: u32,
+        text: String,
+    }
+    let mut writer = FrameWriter::new(Vec::new(), Config::default());
+    writer.write(&TestStruct { id: 1, text: "a".repeat(10) }).unwrap();
+    let mut stream = writer.into_inner();
+    // garbage between frames
+    stream.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+    let mut writer = FrameWriter::new(stream, Config::default());
+    // a frame that is larger than the maximum size allowed by the reader
+    writer.write(&TestStruct { id: 2, text: "b".repeat(1000) }).unwrap();
+    writer.write(&TestStruct { id: 3, text: "c".repeat(10) }).unwrap();
+    let mut stream = writer.into_inner();
+    let max_size = 256;
+    let mut reader = FrameReader::new(
+        std::io::Cursor::new(stream.clone()),
+        ConfigBuilder::new().max_size(max_size).build(),
+    );
+    let d = TestStruct::deserialize_from(reader.read().unwrap().unwrap()).unwrap();
+    assert_eq!(d.id, 1);
+    let d = TestStruct::deserialize_from(reader.read().unwrap().unwrap()).unwrap();
+    assert_eq!(d.id, 3);
+    assert_eq!(reader.read(), Ok(None));
+    assert!(reader.skipped() > 1000);
+    // the writer also enforces the maximum size
+    let mut writer = FrameWriter::new(Vec::new(), ConfigBuilder::new().max_size(max_size).build());
+    assert!(matches!(
+        writer.write(&TestStruct { id: 4, text: "d".repeat(1000) }),
+        Err(flat_message::Error::ExceedMaxSize(_))
+    ));
+    assert!(writer.get_ref().is_empty());
+    // truncated stream
+    stream.truncate(stream.len() - 3);
+    let mut reader = FrameReader::new(std::io::Cursor::new(stream), Config::default());
+    reader.read().unwrap();
+    reader.read().unwrap();
+    assert_eq!(
+        reader.read(),
+        Err(flat_message::Error::Io(std::io::ErrorKind::UnexpectedEof))
+    );
+}
+
+#[tokio::test]
+async fn check_tokio_codec() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_util::codec::{FramedRead, FramedWrite};
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        id: u32,
+        text: String,
+    }
+    let (client, server) = tokio::io::duplex(64);
+    let writer = tokio::spawn(async move {
+        let mut sink = FramedWrite::new(client, FrameCodec::default());
+        for id in 0..10 {
+            let s = TestStruct {
+                id,
+                text: "x".repeat(id as usize * 50),
+            };
+            sink.send(&s).await.unwrap();
+        }
+    });
+    let mut stream = FramedRead::new(server, FrameCodec::default());
+    for id in 0..10 {
+        let storage = stream.next().await.unwrap().unwrap();
+        let d = TestStruct::deserialize_from(&storage).unwrap();
+        assert_eq!(d.id, id);
+        assert_eq!(d.text.len(), id as usize * 50);
+    }
+    assert!(stream.next().await.is_none());
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn check_tokio_codec_max_size_and_resync() {
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+    #[flat_message(metadata: false)]
+    struct TestStruct {
+        id: u32,
+        text: String,
+    }
+    let config = ConfigBuilder::new().max_size(256).build();
+    let (client, server) = tokio::io::duplex(64);
+    let writer = tokio::spawn(async move {
+        let mut sink = FramedWrite::new(client, FrameCodec::default());
+        sink.send(&TestStruct { id: 1, text: "a".repeat(10) }).await.unwrap();
+        sink.get_mut().write_all(&[1, 2, 3, 4, 5, 6, 7, 8, 9]).await.unwrap();
+        // larger than the maximum size allowed by the reader
+        sink.send(&TestStruct { id: 2, text: "b".repeat(1000) }).await.unwrap();
+        sink.send(&TestStruct { id: 3, text: "c".repeat(10) }).await.unwrap();
+    });
+    let mut stream = FramedRead::new(server, FrameCodec::new(config));
+    let storage = stream.next().await.unwrap().unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().id, 1);
+    let storage = stream.next().await.unwrap().unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().id, 3);
+    assert!(stream.next().await.is_none());
+    assert!(stream.decoder().skipped() > 1000);
+    writer.await.unwrap();
+    // the encoder also enforces the maximum size
+    let mut sink = FramedWrite::new(Vec::new(), FrameCodec::new(config));
+    assert!(matches!(
+        sink.send(&TestStruct { id: 4, text: "d".repeat(1000) }).await,
+        Err(flat_message::Error::ExceedMaxSize(_))
+    ));
+}
+
+#[test]
+fn check_message_log() {
+    #[flat_message]
+    struct TestStruct {
+        id: u32,
+        text: String,
+    }
+    let path = std::env::temp_dir().join(format!("flat_message_log_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("log.idx"));
+    {
+        let mut log = MessageLog::open(&path, Config::default()).unwrap();
+        assert!(log.is_empty());
+        for id in 1..=10 {
+            let s = TestStruct {
+                id,
+                text: format!("message {}", id),
+                metadata: MetaDataBuilder::new()
+                    .timestamp(id as u64 * 100)
+                    .unique_id(id as u64 + 1000)
+                    .build(),
+            };
+            assert_eq!(log.append(&s).unwrap(), id as usize - 1);
+        }
+        log.sync().unwrap();
+    }
+    let log = MessageLog::open(&path, Config::default()).unwrap();
+    assert_eq!(log.len(), 10);
+    let entry = *log.find_by_unique_id(1005).unwrap();
+    assert_eq!(entry.timestamp(), Some(500));
+    assert_eq!(entry.name(), Some(name!("TestStruct")));
+    let mut storage = Storage::default();
+    log.read(&entry, &mut storage).unwrap();
+    let d = TestStruct::deserialize_from(&storage).unwrap();
+    assert_eq!(d.id, 5);
+    assert_eq!(d.text, "message 5");
+    assert!(log.find_by_unique_id(1).is_none());
+    let ids: Vec<u64> = log
+        .range_by_timestamp(300..=600)
+        .map(|e| e.unique_id().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1003, 1004, 1005, 1006]);
+    for (idx, storage) in log.iter().enumerate() {
+        let storage = storage.unwrap();
+        assert_eq!(TestStruct::deserialize_from(&storage).unwrap().id, idx as u32 + 1);
+    }
+    // the log can be read from multiple threads (through a shared reference)
+    std::thread::scope(|scope| {
+        for thread in 0..4 {
+            let log = &log;
+            scope.spawn(move || {
+                let mut storage = Storage::default();
+                for step in 0..200 {
+                    let index = (step * 7 + thread * 3) % log.len();
+                    log.read(&log.entries()[index], &mut storage).unwrap();
+                    let d = TestStruct::deserialize_from(&storage).unwrap();
+                    assert_eq!(d.id, index as u32 + 1);
+                    assert_eq!(d.text, format!("message {}", index + 1));
+                }
+            });
+        }
+    });
+    drop(log);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("log.idx"));
+}
+
+#[test]
+fn check_message_log_recovery() {
+    #[flat_message(checksum: true)]
+    struct TestStruct {
+        id: u32,
+    }
+    let path = std::env::temp_dir().join(format!("flat_message_recovery_{}.log", std::process::id()));
+    let index_path = path.with_extension("log.idx");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&index_path);
+    let mut log = MessageLog::open(&path, Config::default()).unwrap();
+    for id in 1..=5 {
+        let s = TestStruct {
+            id,
+            metadata: MetaDataBuilder::new().unique_id(id as u64).build(),
+        };
+        log.append(&s).unwrap();
+    }
+    drop(log);
+    let valid_size = std::fs::metadata(&path).unwrap().len();
+    // the last two entries were not indexed and the last frame is torn
+    let mut data = std::fs::read(&path).unwrap();
+    let frame = data[data.len() - (valid_size as usize / 5)..].to_vec();
+    data.extend_from_slice(&frame[..frame.len() - 3]);
+    std::fs::write(&path, &data).unwrap();
+    let index = std::fs::read(&index_path).unwrap();
+    std::fs::write(&index_path, &index[..index.len() / 5 * 3 + 7]).unwrap();
+    let log = MessageLog::open(&path, Config::default()).unwrap();
+    assert_eq!(log.len(), 5);
+    assert_eq!(log.find_by_unique_id(5).unwrap().offset(), valid_size * 4 / 5);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_size);
+    assert_eq!(std::fs::metadata(&index_path).unwrap().len(), index.len() as u64);
+    drop(log);
+    // a complete frame with an invalid CRC32 is also removed
+    let mut data = std::fs::read(&path).unwrap();
+    let mut frame = data[data.len() - (valid_size as usize / 5)..].to_vec();
+    let len = frame.len();
+    frame[len - 1] ^= 0xFF;
+    data.extend_from_slice(&frame);
+    std::fs::write(&path, &data).unwrap();
+    let mut log = MessageLog::open(&path, Config::default()).unwrap();
+    assert_eq!(log.len(), 5);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_size);
+    let s = TestStruct {
+        id: 6,
+        metadata: MetaDataBuilder::new().unique_id(6).build(),
+    };
+    assert_eq!(log.append(&s).unwrap(), 5);
+    let mut storage = Storage::default();
+    let entry = *log.find_by_unique_id(6).unwrap();
+    log.read(&entry, &mut storage).unwrap();
+    assert_eq!(TestStruct::deserialize_from(&storage).unwrap().id, 6);
+    drop(log);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&index_path);
+}
+
+#[test]
+fn check_deserialize_from_slice() {
+    #[flat_message(metadata: false)]
+    struct TestStruct<'a> {
+        values: &'a [u64],
+        text: &'a str,
+    }
+    let s = TestStruct {
+        values: &[1, 2, 3],
+        text: "Hello",
+    };
+    let mut storage = Storage::default();
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    let d = TestStruct::deserialize_from_slice(storage.as_slice()).unwrap();
+    assert_eq!(d.values, &[1, 2, 3]);
+    assert_eq!(d.text, "Hello");
+    // the same message, but starting from an unaligned offset
+    let mut data = Storage::default();
+    data.resize_zero(storage.len() + 1);
+    data.as_mut_slice()[1..].copy_from_slice(storage.as_slice());
+    assert_eq!(
+        TestStruct::deserialize_from_slice(&data.as_slice()[1..]).err(),
+        Some(flat_message::Error::UnalignedBuffer)
+    );
+
+    // structures without lifetimes are deserialized from an aligned copy
+    #[flat_message(metadata: false)]
+    struct Owned {
+        values: Vec<u64>,
+        text: String,
+    }
+    let o = Owned::deserialize_from_slice(&data.as_slice()[1..]).unwrap();
+    assert_eq!(o.values, vec![1, 2, 3]);
+    assert_eq!(o.text, "Hello");
+    let o = unsafe { Owned::deserialize_from_slice_unchecked(&data.as_slice()[1..]).unwrap() };
+    assert_eq!(o.values, vec![1, 2, 3]);
+}
+
+#[test]
+fn check_flat_message_manual_implementation() {
+    #[flat_message(version: 2, metadata: false)]
+    struct Inner {
+        value: u32,
+    }
+    // only the original methods are implemented (the rest have default implementations)
+    struct Manual {
+        value: u32,
+    }
+    impl FlatMessage<'_> for Manual {
+        fn metadata(&self) -> &MetaData {
+            &MetaData::NONE
+        }
+        fn update_metada(&mut self, _: MetaData) {}
+        fn serialize_to<V: VecLike>(&self, output: &mut V, config: Config) -> Result<(), Error> {
+            Inner { value: self.value }.serialize_to(output, config)
+        }
+        fn deserialize_from(input: &Storage) -> Result<Self, Error> {
+            Inner::deserialize_from(input).map(|inner| Manual { value: inner.value })
+        }
+        unsafe fn deserialize_from_unchecked(input: &Storage) -> Result<Self, Error> {
+            Inner::deserialize_from_unchecked(input).map(|inner| Manual { value: inner.value })
+        }
+    }
+    let key = [1u8; 32];
+    let mut storage = Storage::default();
+    Manual { value: 5 }
+        .serialize_to(&mut storage, ConfigBuilder::new().authentication_key(key).build())
+        .unwrap();
+    // options are checked before calling deserialize_from
+    let options = DeserializeOptionsBuilder::new()
+        .compatible_versions(VersionSet::try_from("1").unwrap())
+        .build();
+    assert_eq!(
+        Manual::deserialize_from_with_options(&storage, &options).err(),
+        Some(Error::IncompatibleVersion(2))
+    );
+    let options = DeserializeOptionsBuilder::new()
+        .compatible_versions(VersionSet::try_from("2").unwrap())
+        .authentication_key(key)
+        .build();
+    assert_eq!(Manual::deserialize_from_with_options(&storage, &options).unwrap().value, 5);
+    let options = DeserializeOptionsBuilder::new().authentication_key([2u8; 32]).build();
+    assert_eq!(
+        Manual::deserialize_from_with_options(&storage, &options).err(),
+        Some(Error::AuthenticationFailed)
+    );
+    assert_eq!(
+        Manual::deserialize_from_slice(storage.as_slice()).err(),
+        Some(Error::SliceNotSupported)
+    );
+}
+
+#[test]
+fn check_archive() {
+    #[flat_message]
+    struct TestStruct<'a> {
+        id: u32,
+        values: &'a [u128],
+        small: &'a [u64],
+        text: &'a str,
+    }
+    let big = [1u128, 2, 3];
+    let small = [4u64, 5];
+    let mut writer = ArchiveWriter::new(Vec::new(), Config::default());
+    for id in 0..10 {
+        let text = "x".repeat(id as usize);
+        let s = TestStruct {
+            id,
+            values: &big[..(id as usize % 4)],
+            small: &small,
+            text: &text,
+            metadata: MetaData::default(),
+        };
+        writer.append(&s).unwrap();
+    }
+    let data = writer.finish().unwrap();
+    // any 16 bytes aligned buffer will do (a Storage object or a memory mapped file)
+    let storage = Storage::from_buffer(&data);
+    let archive = Archive::new(storage.as_slice()).unwrap();
+    assert_eq!(archive.len(), 10);
+    for id in 0..10 {
+        let d: TestStruct = archive.deserialize(id).unwrap();
+        assert_eq!(d.id, id as u32);
+        assert_eq!(d.values, &big[..(id % 4)]);
+        assert_eq!(d.small, &small);
+        assert_eq!(d.text.len(), id);
+        // no copy was made
+        assert!(storage.as_slice().as_ptr_range().contains(&(d.small.as_ptr() as *const u8)));
+    }
+    assert_eq!(archive.iter().count(), 10);
+    assert!(archive.get(10).is_none());
+    assert!(archive.deserialize::<TestStruct>(10).is_err());
+
+    // invalid archives
+    let mut corrupted = data.clone();
+    let len = corrupted.len();
+    corrupted[len - 12] = 11;
+    let corrupted = Storage::from_buffer(&corrupted);
+    assert_eq!(
+        Archive::new(corrupted.as_slice()).err(),
+        Some(flat_message::Error::InvalidArchive)
+    );
+    let mut unaligned = Storage::default();
+    unaligned.resize_zero(data.len() + 8);
+    unaligned.as_mut_slice()[8..].copy_from_slice(&data);
+    assert_eq!(
+        Archive::new(&unaligned.as_slice()[8..]).err(),
+        Some(flat_message::Error::UnalignedBuffer)
+    );
+
+    // memory mapped file
+    let path = std::env::temp_dir().join(format!("flat_message_archive_{}.bin", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+    let mmap = MmapArchive::open(&path).unwrap();
+    let archive = mmap.archive();
+    let d: TestStruct = archive.deserialize(7).unwrap();
+    assert_eq!(d.id, 7);
+    assert_eq!(d.values, &big[..3]);
+    drop(mmap);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn check_dispatcher() {
+    #[flat_message]
+    struct Login {
+        user: String,
+    }
+    #[flat_message]
+    struct Order {
+        id: u32,
+        amount: f64,
+    }
+    #[flat_message]
+    struct Logout {
+        user: String,
+    }
+    #[flat_message(store_name: false)]
+    struct Anonymous {
+        id: u32,
+    }
+    #[flat_message]
+    struct Borrowed<'a> {
+        values: &'a [u32],
+    }
+    assert_eq!(Login::NAME, Some(name!("Login")));
+    assert_eq!(Anonymous::NAME, None);
+
+    let mut logins = Vec::new();
+    let mut total = 0.0;
+    let mut unknown = 0;
+    {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher
+            .register(|m: Login| logins.push(m.user))
+            .register(|m: Order| total += m.amount)
+            .register_raw(name!("Borrowed"), |input| {
+                let m = Borrowed::deserialize_from(input)?;
+                assert_eq!(m.values, &[1, 2, 3]);
+                Ok(())
+            })
+            .fallback(|_| unknown += 1);
+        let mut storage = Storage::default();
+        let login = Login {
+            user: "John".to_string(),
+            metadata: MetaData::default(),
+        };
+        login.serialize_to(&mut storage, Config::default()).unwrap();
+        dispatcher.dispatch(&storage).unwrap();
+        for id in 0..3 {
+            let order = Order {
+                id,
+                amount: 10.5,
+                metadata: MetaData::default(),
+            };
+            order.serialize_to(&mut storage, Config::default()).unwrap();
+            dispatcher.dispatch(&storage).unwrap();
+        }
+        let borrowed = Borrowed {
+            values: &[1, 2, 3],
+            metadata: MetaData::default(),
+        };
+        borrowed.serialize_to(&mut storage, Config::default()).unwrap();
+        dispatcher.dispatch(&storage).unwrap();
+        let logout = Logout {
+            user: "John".to_string(),
+            metadata: MetaData::default(),
+        };
+        logout.serialize_to(&mut storage, Config::default()).unwrap();
+        dispatcher.dispatch(&storage).unwrap();
+        let anonymous = Anonymous {
+            id: 1,
+            metadata: MetaData::default(),
+        };
+        anonymous.serialize_to(&mut storage, Config::default()).unwrap();
+        dispatcher.dispatch(&storage).unwrap();
+    }
+    assert_eq!(logins, vec!["John".to_string()]);
+    assert_eq!(total, 31.5);
+    assert_eq!(unknown, 2);
+}
+
+#[test]
+fn check_dispatcher_errors() {
+    #[flat_message]
+    struct Ping {
+        id: u32,
+    }
+    #[flat_message]
+    struct Pong {
+        id: u32,
+    }
+    #[flat_message(store_name: false)]
+    struct Anonymous {
+        id: u32,
+    }
+    let mut dispatcher: Dispatcher<u32> = Dispatcher::new();
+    dispatcher.register(|m: Ping| m.id + 1);
+    let mut storage = Storage::default();
+    Ping {
+        id: 5,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(dispatcher.dispatch(&storage), Ok(6));
+    Pong {
+        id: 5,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(
+        dispatcher.dispatch(&storage),
+        Err(flat_message::Error::UnknownMessage(name!("Pong").value))
+    );
+    Anonymous {
+        id: 5,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(
+        dispatcher.dispatch(&storage),
+        Err(flat_message::Error::NameNotStored)
+    );
+    assert_eq!(
+        dispatcher.dispatch(&Storage::from_buffer(&[1, 2, 3])),
+        Err(flat_message::Error::InvalidHeaderLength(3))
+    );
+}
+
+#[test]
+fn check_union() {
+    #[flat_message]
+    struct Login<'a> {
+        user: &'a str,
+    }
+    #[flat_message]
+    struct Order {
+        id: u32,
+        amount: f64,
+    }
+    #[flat_message]
+    struct Logout {
+        id: u32,
+    }
+    #[derive(FlatMessageUnion)]
+    enum Msg<'a> {
+        Login(Login<'a>),
+        Order(Order),
+    }
+    let mut storage = Storage::default();
+    let msg = Msg::Login(Login {
+        user: "John",
+        metadata: MetaDataBuilder::new().timestamp(5).build(),
+    });
+    msg.serialize_to(&mut storage, Config::default()).unwrap();
+    assert_eq!(msg.metadata().timestamp(), Some(5));
+    match Msg::deserialize_from(&storage).unwrap() {
+        Msg::Login(login) => {
+            assert_eq!(login.user, "John");
+            assert_eq!(login.metadata.timestamp(), Some(5));
+        }
+        Msg::Order(_) => panic!("expecting a Login message"),
+    }
+    // serialized directly (not through the union)
+    let order = Order {
+        id: 3,
+        amount: 1.5,
+        metadata: MetaData::default(),
+    };
+    order.serialize_to(&mut storage, Config::default()).unwrap();
+    match unsafe { Msg::deserialize_from_unchecked(&storage).unwrap() } {
+        Msg::Order(order) => assert_eq!(order.id, 3),
+        Msg::Login(_) => panic!("expecting an Order message"),
+    }
+    let logout = Logout {
+        id: 3,
+        metadata: MetaData::default(),
+    };
+    logout.serialize_to(&mut storage, Config::default()).unwrap();
+    assert!(matches!(
+        Msg::deserialize_from(&storage),
+        Err(flat_message::Error::UnmatchedName)
+    ));
+}
+
+#[test]
+fn check_version_set() {
+    let set = VersionSet::try_from("1..5, 7, <3").unwrap();
+    for v in [1, 2, 3, 4, 5, 7] {
+        assert!(set.contains(v));
+    }
+    for v in [0, 6, 8, 255] {
+        assert!(!set.contains(v));
+    }
+    let set = VersionSet::try_from("10-12,200:201").unwrap();
+    assert!(set.contains(11));
+    assert!(set.contains(201));
+    assert!(!set.contains(13));
+    assert!(VersionSet::try_from("").unwrap().is_empty());
+    assert!(VersionSet::try_from("5..2").is_err());
+    assert!(VersionSet::try_from("0").is_err());
+    assert!(VersionSet::try_from("1,a").is_err());
+    assert!(VersionSet::try_from("256").is_err());
+}
+
+#[test]
+fn check_deserialize_options_compatible_versions() {
+    mod v2 {
+        use flat_message::*;
+        #[flat_message(version: 2, compatible_versions: "1,2")]
+        pub struct TestStruct {
+            pub value: u64,
+        }
+    }
+    mod v5 {
+        use flat_message::*;
+        #[flat_message(version: 5)]
+        pub struct TestStruct {
+            pub value: u64,
+        }
+    }
+    mod v7 {
+        use flat_message::*;
+        #[flat_message(version: 7, compatible_versions: "<3")]
+        pub struct TestStruct {
+            pub value: u64,
+        }
+    }
+    let mut o2 = Storage::default();
+    let mut o5 = Storage::default();
+    v2::TestStruct {
+        value: 2,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut o2, Config::default())
+    .unwrap();
+    v5::TestStruct {
+        value: 5,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut o5, Config::default())
+    .unwrap();
+    // compiled check
+    assert_eq!(
+        v7::TestStruct::deserialize_from(&o5).err(),
+        Some(flat_message::Error::IncompatibleVersion(5))
+    );
+    assert_eq!(v7::TestStruct::deserialize_from(&o2).unwrap().value, 2);
+    // the runtime set replaces the compiled one
+    let options = DeserializeOptionsBuilder::new()
+        .compatible_versions(VersionSet::try_from("4..6").unwrap())
+        .build();
+    assert_eq!(
+        v7::TestStruct::deserialize_from_with_options(&o5, &options)
+            .unwrap()
+            .value,
+        5
+    );
+    assert_eq!(
+        v7::TestStruct::deserialize_from_with_options(&o2, &options).err(),
+        Some(flat_message::Error::IncompatibleVersion(2))
+    );
+    // a structure without compatible_versions can still be restricted at runtime
+    assert_eq!(
+        v2::TestStruct::deserialize_from_with_options(&o5, &options)
+            .unwrap()
+            .value,
+        5
+    );
+    let options = DeserializeOptionsBuilder::new()
+        .compatible_versions(VersionSet::try_from("1").unwrap())
+        .build();
+    assert_eq!(
+        v5::TestStruct::deserialize_from_slice_with_options(o5.as_slice(), &options).err(),
+        Some(flat_message::Error::IncompatibleVersion(5))
+    );
+    // default options behave like deserialize_from
+    assert_eq!(
+        v7::TestStruct::deserialize_from_with_options(&o5, &DeserializeOptions::default()).err(),
+        Some(flat_message::Error::IncompatibleVersion(5))
+    );
+}
+
+#[test]
+fn check_version_migration() {
+    #[flat_message(version: 1)]
+    struct PersonV1<'a> {
+        name: &'a str,
+    }
+    #[flat_message(version: 2)]
+    struct PersonV2<'a> {
+        name: &'a str,
+        age: u8,
+    }
+    #[flat_message(version: 3, compatible_versions: "3", migrate_from: [PersonV1<'a>, PersonV2<'a>])]
+    struct Person<'a> {
+        first_name: &'a str,
+        last_name: &'a str,
+        age: u8,
+    }
+    impl<'a> From<PersonV1<'a>> for PersonV2<'a> {
+        fn from(value: PersonV1<'a>) -> Self {
+            PersonV2 {
+                name: value.name,
+                age: 0,
+                metadata: value.metadata,
+            }
+        }
+    }
+    impl<'a> From<PersonV2<'a>> for Person<'a> {
+        fn from(value: PersonV2<'a>) -> Self {
+            let (first_name, last_name) = value.name.split_once(' ').unwrap_or((value.name, ""));
+            Person {
+                first_name,
+                last_name,
+                age: value.age,
+                metadata: value.metadata,
+            }
+        }
+    }
+    assert_eq!(<PersonV1 as FlatMessage>::VERSION, Some(1));
+    assert_eq!(<Person as FlatMessage>::VERSION, Some(3));
+    let mut o1 = Storage::default();
+    let mut o2 = Storage::default();
+    PersonV1 {
+        name: "John Doe",
+        metadata: MetaDataBuilder::new().unique_id(1).build(),
+    }
+    .serialize_to(&mut o1, Config::default())
+    .unwrap();
+    PersonV2 {
+        name: "Jane Smith",
+        age: 30,
+        metadata: MetaDataBuilder::new().unique_id(2).build(),
+    }
+    .serialize_to(&mut o2, Config::default())
+    .unwrap();
+    // v1 -> v2 -> v3
+    let p = Person::deserialize_from(&o1).unwrap();
+    assert_eq!((p.first_name, p.last_name, p.age), ("John", "Doe", 0));
+    assert_eq!(p.metadata.unique_id(), Some(1));
+    // v2 -> v3
+    let p = Person::deserialize_from(&o2).unwrap();
+    assert_eq!((p.first_name, p.last_name, p.age), ("Jane", "Smith", 30));
+    let p = unsafe { Person::deserialize_from_unchecked(&o2).unwrap() };
+    assert_eq!(p.first_name, "Jane");
+    // the runtime version set is checked before migrating
+    let options = DeserializeOptionsBuilder::new()
+        .compatible_versions(VersionSet::try_from("2..3").unwrap())
+        .build();
+    assert_eq!(
+        Person::deserialize_from_with_options(&o1, &options).err(),
+        Some(flat_message::Error::IncompatibleVersion(1))
+    );
+    assert_eq!(
+        Person::deserialize_from_with_options(&o2, &options)
+            .unwrap()
+            .last_name,
+        "Smith"
+    );
+}
+
+#[test]
+fn check_version_migration_unknown_version() {
+    #[flat_message(version: 1)]
+    struct CounterV1 {
+        value: u32,
+    }
+    #[flat_message(version: 2)]
+    struct CounterV2 {
+        value: u64,
+    }
+    #[flat_message(version: 3, compatible_versions: "3", migrate_from: [CounterV2])]
+    struct Counter {
+        value: u64,
+    }
+    impl From<CounterV2> for Counter {
+        fn from(value: CounterV2) -> Self {
+            Counter {
+                value: value.value,
+                metadata: value.metadata,
+            }
+        }
+    }
+    let mut o1 = Storage::default();
+    let mut o2 = Storage::default();
+    CounterV1 {
+        value: 1,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut o1, Config::default())
+    .unwrap();
+    CounterV2 {
+        value: 2,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut o2, Config::default())
+    .unwrap();
+    assert_eq!(Counter::deserialize_from(&o2).unwrap().value, 2);
+    // version 1 can not be migrated and is not compatible
+    assert_eq!(
+        Counter::deserialize_from(&o1).err(),
+        Some(flat_message::Error::IncompatibleVersion(1))
+    );
+}
+
+#[test]
+fn check_field_since_version() {
+    #[flat_message(version: 1, metadata: false)]
+    struct PointV1 {
+        x: i32,
+        y: i32,
+    }
+    #[flat_message(version: 2, metadata: false)]
+    struct PointV2Incomplete {
+        x: i32,
+        y: i32,
+    }
+    #[flat_message(version: 2, metadata: false)]
+    struct PointV2<'a> {
+        x: i32,
+        y: i32,
+        #[flat_message(since: 2)]
+        z: i32,
+        #[flat_message(since: 2)]
+        label: &'a str,
+        #[flat_message(since: 2)]
+        tags: Vec<u16>,
+    }
+    let mut o1 = Storage::default();
+    let mut o2 = Storage::default();
+    let mut o3 = Storage::default();
+    PointV1 { x: 1, y: 2 }
+        .serialize_to(&mut o1, Config::default())
+        .unwrap();
+    PointV2Incomplete { x: 1, y: 2 }
+        .serialize_to(&mut o2, Config::default())
+        .unwrap();
+    PointV2 {
+        x: 3,
+        y: 4,
+        z: 5,
+        label: "p",
+        tags: vec![1, 2],
+    }
+    .serialize_to(&mut o3, Config::default())
+    .unwrap();
+    // fields added in version 2 are missing from a version 1 message
+    let p = PointV2::deserialize_from(&o1).unwrap();
+    assert_eq!((p.x, p.y, p.z, p.label, p.tags.len()), (1, 2, 0, "", 0));
+    let p = unsafe { PointV2::deserialize_from_unchecked(&o1).unwrap() };
+    assert_eq!((p.x, p.y, p.z), (1, 2, 0));
+    // ... but they have to exist in a version 2 message
+    assert!(matches!(
+        PointV2::deserialize_from(&o2),
+        Err(flat_message::Error::UnknownHash(_))
+    ));
+    let p = PointV2::deserialize_from(&o3).unwrap();
+    assert_eq!((p.x, p.y, p.z, p.label), (3, 4, 5, "p"));
+    assert_eq!(p.tags, [1, 2]);
+}
+
+#[test]
+fn check_field_until_version() {
+    #[flat_message(version: 2, metadata: false)]
+    struct ConfigV2<'a> {
+        name: &'a str,
+        #[flat_message(until: 2)]
+        legacy_port: u16,
+        port: u32,
+    }
+    #[flat_message(version: 3, metadata: false)]
+    struct ConfigV3<'a> {
+        name: &'a str,
+        port: u32,
+    }
+    let mut o2 = Storage::default();
+    let mut o3 = Storage::default();
+    ConfigV2 {
+        name: "server",
+        legacy_port: 80,
+        port: 8080,
+    }
+    .serialize_to(&mut o2, Config::default())
+    .unwrap();
+    ConfigV3 {
+        name: "server",
+        port: 9090,
+    }
+    .serialize_to(&mut o3, Config::default())
+    .unwrap();
+    let c = ConfigV2::deserialize_from(&o2).unwrap();
+    assert_eq!((c.name, c.legacy_port, c.port), ("server", 80, 8080));
+    // the field was removed in version 3
+    let c = ConfigV2::deserialize_from(&o3).unwrap();
+    assert_eq!((c.name, c.legacy_port, c.port), ("server", 0, 9090));
+}
+
+#[test]
+fn check_schema() {
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u16)]
+    #[sealed]
+    enum Level {
+        Low = 1,
+        High = 2,
+    }
+    #[flat_message(version: 2, checksum: true)]
+    struct Event<'a> {
+        id: u64,
+        source: &'a str,
+        #[flat_message(compress = "lz4")]
+        payload: String,
+        #[flat_message(repr = u16, kind = enum)]
+        level: Level,
+        #[flat_message(repr = u16, kind = enum, since = 2)]
+        history: Vec<Level>,
+        values: &'a [f32],
+    }
+    let schema = Event::SCHEMA;
+    assert_eq!(schema.name, "Event");
+    assert_eq!(schema.version, 2);
+    assert!(schema.store_name && schema.metadata && schema.checksum);
+    assert_eq!(schema.fields.len(), 6);
+    assert!(schema.fields.windows(2).all(|w| w[0].hash < w[1].hash));
+
+    let id = schema.field("id").unwrap();
+    assert_eq!(id.data_format, DataFormat::U64);
+    assert_eq!(id.kind, FieldKind::Object);
+    assert_eq!(id.hash & 0xFF, DataFormat::U64 as u32);
+    assert_eq!((id.since, id.until), (1, 255));
+    assert!(schema.field("payload").unwrap().compressed);
+    let values = schema.field("values").unwrap();
+    assert_eq!(values.kind, FieldKind::Slice);
+    assert!(values.kind.is_list());
+    assert_eq!(values.data_format, DataFormat::F32);
+
+    let level = schema.field("level").unwrap();
+    let history = schema.field("history").unwrap();
+    assert_eq!(level.enum_repr(), Some("u16"));
+    assert_eq!(history.kind, FieldKind::Vector);
+    assert_eq!(history.since, 2);
+    assert!(level.enum_hash.is_some());
+    assert_eq!(level.enum_hash, history.enum_hash);
+    assert_eq!(id.enum_hash, None);
+    assert_eq!(level.enum_variants, &[("Low", 1), ("High", 2)]);
+    assert_eq!(level.enum_variant(2), Some("High"));
+    assert!(id.enum_variants.is_empty());
+    assert_eq!(schema.field("missing"), None);
+
+    // the name hash matches the one that is stored in the message
+    let mut storage = Storage::default();
+    Event {
+        id: 1,
+        source: "s",
+        payload: String::new(),
+        level: Level::Low,
+        history: vec![Level::High],
+        values: &[],
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    let info = StructureInformation::try_from(&storage).unwrap();
+    assert_eq!(info.name(), Some(Name::new(schema.name_hash)));
+}
+
+#[test]
+fn check_schema_to_json() {
+    #[flat_message(version: 3, store_name: false, metadata: false)]
+    struct Point {
+        x: i32,
+        #[flat_message(until = 3)]
+        y: Vec<u8>,
+    }
+    let x = Point::SCHEMA.field("x").unwrap().hash;
+    let y = Point::SCHEMA.field("y").unwrap().hash;
+    let x_json = format!(
+        "{{ \"name\": \"x\", \"hash\": {}, \"data_format\": \"I32\", \"kind\": \"object\", \"compressed\": false, \"since\": 1, \"until\": 255 }}",
+        x
+    );
+    let y_json = format!(
+        "{{ \"name\": \"y\", \"hash\": {}, \"data_format\": \"U8\", \"kind\": \"vector\", \"compressed\": false, \"since\": 1, \"until\": 3 }}",
+        y
+    );
+    let fields = if x < y {
+        format!("{},\n    {}", x_json, y_json)
+    } else {
+        format!("{},\n    {}", y_json, x_json)
+    };
+    let expected = format!(
+        "{{\n  \"name\": \"Point\",\n  \"name_hash\": {},\n  \"version\": 3,\n  \"store_name\": false,\n  \"metadata\": false,\n  \"checksum\": false,\n  \"fields\": [\n    {}\n  ]\n}}",
+        Point::SCHEMA.name_hash,
+        fields
+    );
+    assert_eq!(Point::SCHEMA.to_json(), expected);
+}
+
+#[test]
+fn check_data_format_conversions() {
+    #[flat_message(store_name: false, metadata: false)]
+    struct Point {
+        x: i32,
+    }
+    for value in 0..=u8::MAX {
+        match DataFormat::try_from(value) {
+            Ok(format) => {
+                assert_eq!(format as u8, value);
+                assert_eq!(format.to_string().parse::<DataFormat>(), Ok(format));
+            }
+            Err(e) => {
+                assert!(value > DataFormat::Bytes as u8);
+                assert_eq!(e, format!("Invalid data format value: {} !", value));
+            }
+        }
+    }
+    let id = Point::SCHEMA.field("x").unwrap();
+    assert_eq!(DataFormat::try_from((id.hash & 0x3F) as u8), Ok(DataFormat::I32));
+    assert_eq!(
+        "u32".parse::<DataFormat>(),
+        Err("Invalid data format: 'u32' !".to_string())
+    );
+}
+
+#[test]
+fn check_schema_breaking_changes() {
+    mod v1 {
+        use flat_message::*;
+        #[flat_message(version: 1)]
+        pub struct User<'a> {
+            pub id: u32,
+            pub name: &'a str,
+            pub email: &'a str,
+            pub score: f32,
+            pub tags: Vec<u8>,
+        }
+    }
+    mod v2 {
+        use flat_message::*;
+        #[flat_message(version: 2)]
+        pub struct User<'a> {
+            pub id: u64,
+            pub name: &'a str,
+            pub mail: &'a str,
+            pub score: f32,
+            pub tags: u8,
+            pub age: u8,
+            #[flat_message(since: 2)]
+            pub country: &'a str,
+        }
+    }
+    let changes = v1::User::SCHEMA.breaking_changes(v2::User::SCHEMA);
+    assert_eq!(changes.len(), 4);
+    assert!(changes.contains(&BreakingChange::DataFormatChanged {
+        field: "id".to_string(),
+        old: DataFormat::U32,
+        new: DataFormat::U64
+    }));
+    assert!(changes.contains(&BreakingChange::KindChanged {
+        field: "tags".to_string(),
+        old: FieldKind::Vector,
+        new: FieldKind::Object
+    }));
+    assert!(changes.contains(&BreakingChange::FieldRenamed {
+        old: "email".to_string(),
+        new: "mail".to_string()
+    }));
+    // `age` is required for version 1 messages, `country` is not
+    assert!(changes.contains(&BreakingChange::FieldAdded {
+        field: "age".to_string()
+    }));
+    assert!(!changes.contains(&BreakingChange::FieldAdded {
+        field: "country".to_string()
+    }));
+    assert_eq!(
+        BreakingChange::FieldAdded {
+            field: "age".to_string()
+        }
+        .to_string(),
+        "Field 'age' was added but it is required for older versions (use 'since') !"
+    );
+    // the same schema is always compatible
+    assert!(v2::User::SCHEMA
+        .breaking_changes(v2::User::SCHEMA)
+        .is_empty());
+}
+
+#[test]
+fn check_schema_breaking_changes_versions() {
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    #[sealed]
+    enum StateV1 {
+        On = 1,
+        Off = 2,
+    }
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    #[sealed]
+    enum StateV2 {
+        On = 1,
+        Off = 2,
+        Unknown = 3,
+    }
+    #[flat_message(version: 3, store_name: false)]
+    struct DeviceV1 {
+        #[flat_message(repr = u8, kind = enum)]
+        state: StateV1,
+        #[flat_message(until: 2)]
+        legacy: u32,
+        serial: u64,
+    }
+    #[flat_message(version: 3, store_name: false)]
+    struct DeviceV2 {
+        #[flat_message(repr = u8, kind = enum)]
+        state: StateV2,
+        serial: u64,
+    }
+    #[flat_message(version: 2, store_name: false)]
+    struct DeviceV3 {
+        #[flat_message(repr = u8, kind = enum)]
+        state: StateV1,
+        serial: u64,
+    }
+    // `legacy` is not required for version 3 (so removing it is not a breaking change)
+    assert_eq!(
+        DeviceV1::SCHEMA.breaking_changes(DeviceV2::SCHEMA),
+        vec![
+            BreakingChange::EnumHashChanged {
+                field: "state".to_string()
+            },
+            BreakingChange::VersionNotIncremented { version: 3 }
+        ]
+    );
+    // ... but it is for version 2
+    assert_eq!(
+        DeviceV1::SCHEMA.breaking_changes(DeviceV3::SCHEMA),
+        vec![
+            BreakingChange::FieldRemoved {
+                field: "legacy".to_string()
+            },
+            BreakingChange::VersionDecreased { old: 3, new: 2 }
+        ]
+    );
+}
+
+#[test]
+fn check_codegen_generated_structures() {
+    mod generated {
+        // generated by build.rs from schemas/codegen_test.toml (no `use flat_message::*` is needed)
+        include!(concat!(env!("OUT_DIR"), "/codegen_test.rs"));
+        // the first version of `Task` (without the fields added in version 2)
+        pub mod v1 {
+            use flat_message::*;
+            #[flat_message(version: 1, checksum: true)]
+            pub struct Task<'a> {
+                pub id: u64,
+                pub title: &'a str,
+                #[flat_message(repr = u8, kind = enum)]
+                pub priority: super::Priority,
+                pub estimates: &'a [f32],
+                #[flat_message(compress = "lz4")]
+                pub notes: String,
+            }
+        }
+    }
+    // the JSON and TOML versions of the schema generate the same code
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/codegen_test.rs"));
+    let from_json = flat_message_codegen::generate(include_str!("../schemas/codegen_test.json")).unwrap();
+    assert_eq!(from_json, generated);
+    assert!(!generated.contains("use flat_message"));
+    assert!(generated.contains("#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, flat_message::FlatMessageEnum)]"));
+    assert!(generated.contains("    #[default]\n    Normal = 2,"));
+    use generated::{Priority, Task, TaskList};
+    let task = Task {
+        id: 7,
+        title: "write docs",
+        priority: Priority::High,
+        labels: vec!["docs", "urgent"],
+        previous: Priority::Low,
+        estimates: &[1.5, 2.0],
+        notes: "a long note ".repeat(20),
+        metadata: MetaDataBuilder::new().unique_id(3).build(),
+    };
+    let mut storage = Storage::default();
+    task.serialize_to(&mut storage, Config::default()).unwrap();
+    let restored = Task::deserialize_from(&storage).unwrap();
+    assert_eq!(restored, task);
+    assert_eq!(Task::SCHEMA.version, 2);
+    assert!(Task::SCHEMA.checksum);
+    assert_eq!(Task::SCHEMA.field("labels").unwrap().since, 2);
+    assert!(Task::SCHEMA.field("notes").unwrap().compressed);
+    assert!(!TaskList::SCHEMA.store_name);
+
+    // versioned enum fields that are missing are set to the default variant
+    generated::v1::Task {
+        id: 8,
+        title: "old",
+        priority: Priority::Low,
+        estimates: &[],
+        notes: String::new(),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    let restored = Task::deserialize_from(&storage).unwrap();
+    assert_eq!(restored.previous, Priority::Normal);
+    assert!(restored.labels.is_empty());
+
+    let list = TaskList {
+        ids: vec![1, 2, 3],
+        history: vec![Priority::Low, Priority::Normal],
+        done: true,
+        metadata: MetaData::default(),
+    };
+    list.serialize_to(&mut storage, Config::default()).unwrap();
+    assert_eq!(TaskList::deserialize_from(&storage).unwrap(), list);
+}
+
+#[test]
+fn check_codegen_hashes_and_errors() {
+    let code = flat_message_codegen::generate(
+        r#"{ "structs": [ { "name": "Sample", "fields": [
+            { "name": "value", "type": "i32" },
+            { "name": "items", "type": "u16", "list": true } ] } ] }"#,
+    )
+    .unwrap();
+    assert!(code.contains("#[flat_message::flat_message]\n#[derive(Debug, Clone, PartialEq)]\npub struct Sample {"));
+    assert!(code.contains("    pub items: Vec<u16>,"));
+    // the hashes are the same as the ones computed by #[flat_message]
+    #[flat_message]
+    struct Sample {
+        value: i32,
+        items: Vec<u16>,
+    }
+    for field in Sample::SCHEMA.fields {
+        assert!(code.contains(&format!("// hash: 0x{:08X}\n    pub {}:", field.hash, field.name)));
+    }
+
+    let error = |schema: &str| flat_message_codegen::generate(schema).unwrap_err().to_string();
+    assert!(error("{").starts_with("Invalid schema file"));
+    assert!(error(r#"{ "structs": [ { "name": "A", "fields": [ { "name": "x", "type": "Color" } ] } ] }"#)
+        .contains("Unknown type 'Color'"));
+    assert!(error(r#"{ "structs": [ { "name": "A", "fields": [ { "name": "x", "type": "u8" }, { "name": "x", "type": "u8" } ] } ] }"#)
+        .contains("Duplicate field 'A::x'"));
+    assert!(error(r#"{ "structs": [ { "name": "A", "fields": [ { "name": "x", "type": "u8", "since": 2 } ] } ] }"#)
+        .contains("has no version"));
+    assert!(error(r#"{ "structs": [ { "name": "A", "fields": [ { "name": "x", "type": "u32", "compress": "lz4" } ] } ] }"#)
+        .contains("can not be compressed"));
+    assert!(error(r#"{ "enums": [ { "name": "E", "repr": "u8", "variants": [ { "name": "A", "value": 300 } ] } ] }"#)
+        .contains("does not fit in 'u8'"));
+    assert!(error(r#"{ "structs": [ { "name": "fn", "fields": [] } ] }"#)
+        .contains("not a valid name"));
+    // a missing versioned enum field needs a default value
+    let color = r#"{ "name": "Color", "repr": "u8", "variants": [ { "name": "Red", "value": 1 } ] }"#;
+    assert!(error(&format!(r#"{{ "enums": [ {} ], "structs": [ {{ "name": "A", "version": 2, "fields": [ {{ "name": "c", "type": "Color", "since": 2 }} ] }} ] }}"#, color))
+        .contains("enum 'Color' has no default variant"));
+    assert!(flat_message_codegen::generate(&format!(r#"{{ "enums": [ {} ], "structs": [ {{ "name": "A", "version": 2, "fields": [ {{ "name": "c", "type": "Color", "list": true, "since": 2 }} ] }} ] }}"#, color)).is_ok());
+    assert!(error(r#"{ "enums": [ { "name": "E", "repr": "u8", "default": "B", "variants": [ { "name": "A", "value": 1 } ] } ] }"#)
+        .contains("The default variant 'B' of enum 'E' does not exist"));
+
+    // TOML schemas
+    let code = flat_message_codegen::generate_from_toml(
+        r#"
+        [[structs]]
+        name = "Sample"
+        fields = [ { name = "value", type = "i32" }, { name = "items", type = "u16", list = true } ]
+        "#,
+    )
+    .unwrap();
+    for field in Sample::SCHEMA.fields {
+        assert!(code.contains(&format!("// hash: 0x{:08X}\n    pub {}:", field.hash, field.name)));
+    }
+    let error = |schema: &str| flat_message_codegen::generate_from_toml(schema).unwrap_err().to_string();
+    assert!(error("[[structs]").starts_with("Invalid schema file"));
+    assert!(error("[[structs]]\nname = \"A\"\nfields = []\ncolor = 1").starts_with("Invalid schema file"));
+    assert!(error("[[structs]]\nname = \"A\"\nfields = [ { name = \"x\", type = \"Color\" } ]")
+        .contains("Unknown type 'Color'"));
+}
+
+#[test]
+fn check_flat_message_buffer_get_slice() {
+    #[flat_message(metadata: false)]
+    struct TestStruct<'a> {
+        values: &'a [u32],
+        flags: Vec<bool>,
+    }
+    let a = TestStruct {
+        values: &[1, 2, 3],
+        flags: vec![true, false],
+    };
+    let mut output = Storage::default();
+    a.serialize_to(&mut output, Config::default()).unwrap();
+    let buf = FlatMessageBuffer::try_from(&output).unwrap();
+    assert_eq!(buf.get_slice::<u32>(name!("values")), Some(&[1u32, 2, 3][..]));
+    assert_eq!(buf.get_slice::<bool>(name!("flags")), Some(&[true, false][..]));
+    assert_eq!(buf.get_slice::<u16>(name!("values")), None);
+    assert_eq!(buf.get::<u32>(name!("values")), None);
+    // slices are read in place (a list that is not aligned is not returned)
+    let mut data = Storage::default();
+    data.resize_zero(output.len() + 1);
+    data.as_mut_slice()[1..].copy_from_slice(output.as_slice());
+    let buf = FlatMessageBuffer::try_from(&data.as_slice()[1..]).unwrap();
+    assert_eq!(buf.get_slice::<u32>(name!("values")), None);
+    assert_eq!(buf.get_slice::<bool>(name!("flags")), Some(&[true, false][..]));
+}
+
+#[test]
+fn check_ffi_reader() {
+    use flat_message_ffi::*;
+    #[flat_message(version: 3, checksum: true)]
+    struct Point<'a> {
+        x: i16,
+        y: u64,
+        big: u64,
+        scale: f32,
+        visible: bool,
+        label: &'a str,
+        samples: Vec<i32>,
+    }
+    let p = Point {
+        x: -5,
+        y: 1 << 40,
+        big: u64::MAX,
+        scale: 1.5,
+        visible: true,
+        label: "origin",
+        samples: vec![10, -20, 30],
+        metadata: MetaDataBuilder::new().timestamp(123).unique_id(456).build(),
+    };
+    let mut output = Storage::default();
+    p.serialize_to(&mut output, Config::default()).unwrap();
+    let data = output.as_slice();
+    let hash = |name: &str| {
+        let name = std::ffi::CString::new(name).unwrap();
+        unsafe { fm_name_hash(name.as_ptr()) }
+    };
+    unsafe {
+        let mut status = FmStatus::InvalidMessage;
+        let reader = fm_reader_open(data.as_ptr(), data.len(), &mut status);
+        assert_eq!(status, FmStatus::Ok);
+        assert!(!reader.is_null());
+
+        let (mut name, mut version, mut timestamp, mut unique_id) = (0u32, 0u8, 0u64, 0u64);
+        assert!(fm_reader_name(reader, &mut name));
+        assert_eq!(name, name!("Point").value);
+        assert!(fm_reader_version(reader, &mut version));
+        assert_eq!(version, 3);
+        assert!(fm_reader_timestamp(reader, &mut timestamp));
+        assert_eq!(timestamp, 123);
+        assert!(fm_reader_unique_id(reader, &mut unique_id));
+        assert_eq!(unique_id, 456);
+
+        let (mut int, mut uint, mut float, mut boolean) = (0i64, 0u64, 0f64, false);
+        assert!(fm_get_int(reader, hash("x"), &mut int));
+        assert_eq!(int, -5);
+        assert!(fm_get_uint(reader, hash("y"), &mut uint));
+        assert_eq!(uint, 1 << 40);
+        // u64 values are read as i64 only if they fit
+        assert!(fm_get_int(reader, hash("y"), &mut int));
+        assert_eq!(int, 1 << 40);
+        assert!(!fm_get_int(reader, hash("big"), &mut int));
+        assert!(fm_get_uint(reader, hash("big"), &mut uint));
+        assert_eq!(uint, u64::MAX);
+        assert!(!fm_get_uint(reader, hash("x"), &mut uint));
+        assert!(fm_get_float(reader, hash("scale"), &mut float));
+        assert_eq!(float, 1.5);
+        assert!(fm_get_bool(reader, hash("visible"), &mut boolean));
+        assert!(boolean);
+        assert!(!fm_get_bool(reader, hash("missing"), &mut boolean));
+
+        let mut string = FmStringView {
+            data: std::ptr::null(),
+            len: 0,
+        };
+        assert!(fm_get_string(reader, hash("label"), &mut string));
+        let label = std::slice::from_raw_parts(string.data, string.len);
+        assert_eq!(label, b"origin");
+
+        let mut slice = FmSliceView {
+            data: std::ptr::null(),
+            len: 0,
+            data_format: 0,
+        };
+        assert!(fm_get_slice(reader, hash("samples"), &mut slice));
+        assert_eq!(slice.data_format, FM_DATA_FORMAT_I32);
+        assert_eq!(fm_data_format_size(slice.data_format), 4);
+        let samples = std::slice::from_raw_parts(slice.data as *const i32, slice.len);
+        assert_eq!(samples, &[10, -20, 30]);
+        assert!(!fm_get_slice(reader, hash("label"), &mut slice));
+
+        // fields can be found by name (the low byte of the hash is the type)
+        let mut field = 0u32;
+        assert!(fm_find_by_name(reader, c"label".as_ptr(), &mut field));
+        assert_eq!(field & 0xFF, DataFormat::String as u32);
+        assert_eq!(field & 0xFFFFFF00, hash("label") & 0xFFFFFF00);
+        assert!(fm_get_string(reader, field, &mut string));
+        assert!(fm_find_by_name(reader, c"samples".as_ptr(), &mut field));
+        assert_eq!(field & 0xFF, DataFormat::I32 as u32 | 0x80);
+        assert!(!fm_find_by_name(reader, c"missing".as_ptr(), &mut field));
+        assert!(!fm_find_by_name(reader, std::ptr::null(), &mut field));
+        assert!(!fm_find_by_name(std::ptr::null(), c"label".as_ptr(), &mut field));
+
+        fm_reader_free(reader);
+    }
+}
+
+#[test]
+fn check_ffi_reader_errors() {
+    use flat_message_ffi::*;
+    #[flat_message(checksum: true, metadata: false, store_name: false)]
+    struct TestStruct {
+        value: u32,
+    }
+    let mut output = Storage::default();
+    TestStruct { value: 1 }
+        .serialize_to(&mut output, Config::default())
+        .unwrap();
+    let mut data = output.as_slice().to_vec();
+    let mut status = FmStatus::Ok;
+    unsafe {
+        assert!(fm_reader_open(std::ptr::null(), 0, &mut status).is_null());
+        assert_eq!(status, FmStatus::NullPointer);
+        // the storage is aligned to 16 bytes, a sub-slice starting at 1 is not (and is copied)
+        let mut storage = Storage::default();
+        storage.resize_zero(output.len() + 1);
+        storage.as_mut_slice()[1..].copy_from_slice(output.as_slice());
+        let unaligned = &storage.as_slice()[1..];
+        let reader = fm_reader_open(unaligned.as_ptr(), unaligned.len(), &mut status);
+        assert_eq!(status, FmStatus::Ok);
+        let mut value = 0u64;
+        assert!(fm_get_uint(reader, name!("value").value, &mut value));
+        assert_eq!(value, 1);
+        fm_reader_free(reader);
+        assert!(fm_reader_open(output.as_slice().as_ptr(), 4, &mut status).is_null());
+        assert_eq!(status, FmStatus::InvalidMessage);
+        // corrupt the value (the checksum no longer matches)
+        let len = data.len();
+        data[8] ^= 0xFF;
+        let corrupted = Storage::from_buffer(&data);
+        assert!(fm_reader_open(corrupted.as_slice().as_ptr(), len, &mut status).is_null());
+        assert_eq!(status, FmStatus::InvalidHash);
+        // null readers and outputs are ignored
+        let mut value = 0u64;
+        assert!(!fm_get_uint(std::ptr::null(), 0, &mut value));
+        let reader = fm_reader_open(output.as_slice().as_ptr(), output.len(), &mut status);
+        assert!(!fm_get_uint(reader, name!("value").value, std::ptr::null_mut()));
+        assert!(fm_get_uint(reader, name!("value").value, &mut value));
+        assert_eq!(value, 1);
+        assert!(!fm_reader_name(reader, &mut 0));
+        assert_eq!(fm_name_hash(std::ptr::null()), 0);
+        fm_reader_free(reader);
+        fm_reader_free(std::ptr::null_mut());
+    }
+}
+
+#[test]
+fn check_inspect_message() {
+    use std::collections::HashMap;
+    #[flat_message(checksum: true)]
+    struct Sample {
+        id: u32,
+        label: String,
+        values: Vec<u16>,
+    }
+    let s = Sample {
+        id: 7,
+        label: "Hello, World ! ".repeat(20),
+        values: vec![1, 2, 3],
+        metadata: MetaDataBuilder::new().timestamp(86_400_000).unique_id(42).build(),
+    };
+    let mut names = NameRegistry::new();
+    names
+        .register_name("Sample")
+        .register_name("id")
+        .register_name("label")
+        .register_name("values");
+    let field = |report: &serde_json::Value, name: &str| {
+        report["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["name"] == name)
+            .map(|f| f["value"].clone())
+    };
+
+    // checksum, name, timestamp, unique id and MAC
+    let mut storage = Storage::default();
+    let config = ConfigBuilder::new().authentication_key([3u8; 32]).build();
+    s.serialize_to(&mut storage, config).unwrap();
+    let report = flatmsg::inspect::inspect(storage.as_slice(), &names).unwrap();
+    assert!(report.is_valid());
+    let json = report.to_json();
+    assert_eq!(
+        json["flag_names"],
+        serde_json::json!(["checksum", "name", "timestamp", "unique_id", "mac"])
+    );
+    assert_eq!(json["compressed"], false);
+    assert_eq!(json["name"], "Sample");
+    assert_eq!(json["name_hash"], name!("Sample").value);
+    assert_eq!(json["timestamp"], 86_400_000);
+    assert_eq!(json["date"], "1970-01-02 00:00:00.000 UTC");
+    assert_eq!(json["unique_id"], 42);
+    assert_eq!(json["checksum"]["valid"], true);
+    assert_eq!(json["fields_count"], 3);
+    assert_eq!(field(&json, "id"), Some(serde_json::json!(7)));
+    assert_eq!(field(&json, "label"), Some(serde_json::json!(s.label)));
+    assert_eq!(field(&json, "values"), Some(serde_json::json!([1, 2, 3])));
+    assert!(report.to_text().contains("Checksum    : "));
+    // an invalid checksum is reported (the fields are still decoded)
+    let mut data = storage.as_slice().to_vec();
+    let pos = data.windows(5).position(|w| w == b"Hello").unwrap();
+    data[pos] = b'J';
+    let report = flatmsg::inspect::inspect(&data, &names).unwrap();
+    assert!(!report.is_valid());
+    let json = report.to_json();
+    assert_eq!(json["checksum"]["valid"], false);
+    assert_eq!(json["errors"], serde_json::json!(["Invalid checksum !"]));
+    assert_eq!(json["fields"].as_array().unwrap().len(), 3);
+
+    // LZ4 compressed messages are decompressed first
+    let config = ConfigBuilder::new().compression(Compression::Lz4).build();
+    s.serialize_to(&mut storage, config).unwrap();
+    assert!(compression::is_compressed(storage.as_slice()));
+    let report = flatmsg::inspect::inspect(storage.as_slice(), &names).unwrap();
+    assert!(report.is_valid());
+    let json = report.to_json();
+    assert_eq!(json["compressed"], true);
+    assert_eq!(json["checksum"]["valid"], true);
+    assert_eq!(field(&json, "label"), Some(serde_json::json!(s.label)));
+    assert!(report.to_text().contains("LZ4 compressed"));
+
+    // only the header and the metadata of encrypted messages are reported
+    let mut keys = HashMap::new();
+    keys.insert(5u32, [9u8; 32]);
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    encryption::encrypt(&mut storage, 5, &keys).unwrap();
+    let report = flatmsg::inspect::inspect(storage.as_slice(), &names).unwrap();
+    assert!(report.is_valid());
+    let json = report.to_json();
+    assert_eq!(json["encryption_key_id"], 5);
+    assert_eq!(json["unique_id"], 42);
+    assert_eq!(json["checksum"]["valid"], true);
+    assert!(json["fields"].as_array().unwrap().is_empty());
+    assert!(report.to_text().contains("Fields      : 3 (encrypted)"));
+
+    // malformed messages
+    let error = |buf: &[u8]| flatmsg::inspect::inspect(buf, &names).err().unwrap();
+    assert!(error(&[1, 2, 3]).starts_with("Invalid header length"));
+    assert!(error(&[0u8; 16]).starts_with("Invalid magic"));
+    s.serialize_to(&mut storage, Config::default()).unwrap();
+    let truncated = &storage.as_slice()[..12];
+    assert!(error(truncated).starts_with("Invalid buffer size to store fields table"));
+    let mut bad_offset = storage.as_slice().to_vec();
+    bad_offset[7] |= 3;
+    assert!(error(&bad_offset).starts_with("Invalid offset size"));
+}
+
+#[test]
+fn check_inspect_nested_messages() {
+    use std::collections::HashMap;
+    #[flat_message]
+    struct Config {
+        id: u32,
+        limits: HashMap<String, u32>,
+    }
+    let mut storage = Storage::default();
+    Config {
+        id: 1,
+        limits: HashMap::from([("cpu".to_string(), 4)]),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, flat_message::Config::default())
+    .unwrap();
+    let mut names = NameRegistry::new();
+    names.register::<Config>();
+    // maps are valid (flatmsg inspect exits with success) and are decoded as nested messages
+    let report = flatmsg::inspect::inspect(storage.as_slice(), &names).unwrap();
+    assert!(report.is_valid());
+    let json = report.to_json();
+    let limits = json["fields"].as_array().unwrap().iter().find(|f| f["name"] == "limits").unwrap();
+    assert_eq!(limits["decoded"], true);
+    assert_eq!(limits["value"], serde_json::json!({ "keys": ["cpu"], "values": [4] }));
+
+    // lists of nested messages are valid, but they are not decoded
+    mod wire {
+        #[derive(serde_derive::Serialize)]
+        pub struct Point {
+            pub x: u32,
+        }
+        #[derive(serde_derive::Serialize)]
+        pub struct Shape {
+            pub origin: Point,
+            pub points: Vec<Point>,
+        }
+    }
+    let shape = wire::Shape {
+        origin: wire::Point { x: 3 },
+        points: vec![wire::Point { x: 1 }, wire::Point { x: 2 }],
+    };
+    serde_wire::serialize_to(&shape, &mut storage, flat_message::Config::default()).unwrap();
+    let mut names = NameRegistry::new();
+    names.register_name("origin").register_name("points").register_name("x");
+    let report = flatmsg::inspect::inspect(storage.as_slice(), &names).unwrap();
+    assert!(report.is_valid());
+    let json = report.to_json();
+    let field = |name: &str| json["fields"].as_array().unwrap().iter().find(|f| f["name"] == name).unwrap().clone();
+    assert_eq!(field("origin")["value"], serde_json::json!({ "x": 3 }));
+    assert_eq!(field("points")["decoded"], false);
+    assert!(field("points")["value"].is_null());
+    assert!(report.to_text().contains("(not decoded)"));
+
+    // a corrupted nested message is still an error
+    let mut data = storage.as_slice().to_vec();
+    let offset = field("origin")["offset"].as_u64().unwrap() as usize;
+    data[offset + 16..offset + 20].copy_from_slice(&[0; 4]);
+    let report = flatmsg::inspect::inspect(&data, &names).unwrap();
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn check_inspect_text_input() {
+    use flatmsg::encoding::{from_base64, from_hex};
+    assert_eq!(from_hex("00 ff\n1A").unwrap(), vec![0x00, 0xFF, 0x1A]);
+    assert_eq!(from_base64("aGVs\nbG8=").unwrap(), b"hello");
+    assert_eq!(from_base64("-_8").unwrap(), vec![0xFB, 0xFF]);
+    assert!(from_hex("abc").unwrap_err().contains("odd number of digits"));
+    assert!(from_hex("zz").unwrap_err().contains("Invalid hex digits 'zz'"));
+    assert!(from_base64("aGV*").unwrap_err().contains("Invalid base64 character '*'"));
+
+    // a message passed as text is decoded to the same bytes
+    #[flat_message]
+    struct Sample {
+        value: u64,
+    }
+    let mut storage = Storage::default();
+    Sample {
+        value: 12345,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    let data = storage.as_slice();
+    let hex: String = data.iter().map(|b| format!("{:02x} ", b)).collect();
+    assert_eq!(from_hex(&hex).unwrap(), data);
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut base64 = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+        for i in 0..=chunk.len() {
+            base64.push(ALPHABET[(n >> (18 - i * 6)) as usize & 63] as char);
+        }
+        base64.push_str(&"=".repeat(3 - chunk.len()));
+    }
+    let decoded = from_base64(&base64).unwrap();
+    assert_eq!(decoded, data);
+    let report = flatmsg::inspect::inspect(&decoded, &NameRegistry::new()).unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.to_json()["fields"][0]["value"], 12345);
+}
+
+#[test]
+fn check_name_registry() {
+    #[flat_message]
+    struct Account {
+        id: u64,
+        owner: String,
+    }
+    let mut registry = NameRegistry::new();
+    registry.register::<Account>();
+    assert!(registry.collisions().is_empty());
+    assert_eq!(registry.name(name!("Account")), Some("Account"));
+    assert_eq!(registry.name(name!("Other")), None);
+    for field in Account::SCHEMA.fields {
+        assert_eq!(registry.field(field.hash), Some(field.name));
+    }
+    assert_eq!(registry.display(name!("Account")), format!("Account ({})", name!("Account")));
+    assert_eq!(name!("Other").to_string(), format!("0x{:08X}", name!("Other").value));
+    assert_eq!(registry.display(name!("Other")), name!("Other").to_string());
+
+    // errors with hashes are resolved
+    #[flat_message(store_name: false)]
+    struct Reduced {
+        id: u64,
+    }
+    let mut storage = Storage::default();
+    Reduced {
+        id: 1,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    #[flat_message(store_name: false)]
+    struct Extended {
+        id: u64,
+        owner: String,
+    }
+    let error = Extended::deserialize_from(&storage).err().unwrap();
+    assert!(registry.error_message(&error).contains("'owner'"));
+    let error = flat_message::Error::UnknownMessage(name!("Account").value);
+    assert!(registry.error_message(&error).contains("Account (0x"));
+    assert_eq!(
+        registry.error_message(&flat_message::Error::InvalidMagic),
+        flat_message::Error::InvalidMagic.to_string()
+    );
+}
+
+#[test]
+fn check_name_registry_collisions() {
+    let mut registry = NameRegistry::new();
+    // names are hashed without case sensitivity
+    registry.register_name("value").register_name("value").register_name("Value");
+    assert_eq!(registry.field(name!("value").value), Some("value"));
+    assert_eq!(registry.collisions().len(), 2);
+    let collision = &registry.collisions()[0];
+    assert_eq!(collision.existing, "value");
+    assert_eq!(collision.new, "Value");
+    assert_eq!(collision.hash, name!("value").value);
+    assert!(collision.to_string().contains("have the same hash"));
+}
+
+#[test]
+fn check_serde_wire_to_flat_message() {
+    mod wire {
+        #[derive(serde_derive::Serialize)]
+        pub struct Account {
+            pub id: u64,
+            pub owner: String,
+            pub scores: Vec<u16>,
+            pub tags: Vec<String>,
+            pub active: bool,
+            pub nickname: Option<String>,
+        }
+    }
+    #[flat_message]
+    struct Account<'a> {
+        id: u64,
+        owner: &'a str,
+        scores: &'a [u16],
+        tags: Vec<&'a str>,
+        active: bool,
+    }
+    let value = wire::Account {
+        id: 12345,
+        owner: "John".to_string(),
+        scores: vec![10, 20, 30],
+        tags: vec!["a".to_string(), "bc".to_string()],
+        active: true,
+        nickname: None,
+    };
+    let mut storage = Storage::default();
+    serde_wire::serialize_to(&value, &mut storage, Config::default()).unwrap();
+    let account = Account::deserialize_from(&storage).unwrap();
+    assert_eq!(account.id, 12345);
+    assert_eq!(account.owner, "John");
+    assert_eq!(account.scores, &[10, 20, 30]);
+    assert_eq!(account.tags, vec!["a", "bc"]);
+    assert!(account.active);
+    // same output for the buffer API (the name is stored)
+    let buffer = FlatMessageBuffer::try_from(&storage).unwrap();
+    assert_eq!(buffer.name(), Some(name!("Account")));
+    assert_eq!(buffer.get::<u64>(name!("id")), Some(12345));
+    // `None` values are not stored
+    assert_eq!(buffer.get::<&str>(name!("nickname")), None);
+}
+
+#[test]
+fn check_serde_wire_empty_lists() {
+    mod wire {
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+        pub struct Point {
+            pub x: u32,
+        }
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+        pub struct Account {
+            pub id: u64,
+            pub scores: Vec<u16>,
+            pub tags: Vec<String>,
+            pub weights: Option<Vec<f64>>,
+            pub letters: [char; 0],
+            pub raw: Vec<u8>,
+            pub limits: std::collections::HashMap<String, u32>,
+        }
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+        pub struct Shape {
+            pub points: Vec<Point>,
+        }
+    }
+    #[flat_message]
+    #[derive(Debug, PartialEq)]
+    struct Account<'a> {
+        id: u64,
+        scores: &'a [u16],
+        tags: Vec<&'a str>,
+        weights: Vec<f64>,
+        letters: Vec<char>,
+        raw: Vec<u8>,
+        limits: std::collections::HashMap<&'a str, u32>,
+    }
+    // serde does not provide the type of the elements of an empty list, so it is taken from the
+    // type of the field (the lists are written with their own data format)
+    let value = wire::Account {
+        id: 1,
+        scores: vec![],
+        tags: vec![],
+        weights: Some(vec![]),
+        letters: [],
+        raw: vec![],
+        limits: Default::default(),
+    };
+    let mut storage = Storage::default();
+    serde_wire::serialize_to(&value, &mut storage, Config::default()).unwrap();
+    let buffer = FlatMessageBuffer::try_from(&storage).unwrap();
+    assert_eq!(buffer.get_slice::<u16>(name!("scores")), Some(&[][..]));
+    assert_eq!(buffer.get_slice::<u8>(name!("scores")), None);
+    assert_eq!(buffer.get_slice::<f64>(name!("weights")), Some(&[][..]));
+    assert_eq!(buffer.get_slice::<u32>(name!("letters")), Some(&[][..]));
+    let account = Account::deserialize_from(&storage).unwrap();
+    assert_eq!(account.id, 1);
+    assert!(account.scores.is_empty() && account.tags.is_empty());
+    assert!(account.weights.is_empty() && account.raw.is_empty());
+    assert!(account.letters.is_empty() && account.limits.is_empty());
+    let account = unsafe { Account::deserialize_from_unchecked(&storage).unwrap() };
+    assert!(account.scores.is_empty() && account.limits.is_empty());
+    // and back
+    let mut output = Storage::default();
+    account.serialize_to(&mut output, Config::default()).unwrap();
+    let restored: wire::Account = serde_wire::deserialize_from(&output).unwrap();
+    assert_eq!(restored, value);
+
+    // the type of an empty list of structures is not known (it is still read by serde_wire)
+    let shape = wire::Shape { points: vec![] };
+    serde_wire::serialize_to(&shape, &mut storage, Config::default()).unwrap();
+    assert_eq!(serde_wire::deserialize_from::<wire::Shape>(&storage).unwrap(), shape);
+
+    // #[flat_message] structures only accept lists with their own data format
+    #[flat_message(version: 2, store_name: false, metadata: false)]
+    struct Old {
+        values: Vec<u8>,
+    }
+    #[flat_message(version: 2, store_name: false, metadata: false)]
+    #[derive(Debug)]
+    struct New {
+        #[flat_message(since = 2)]
+        values: Vec<u32>,
+    }
+    Old { values: vec![] }
+        .serialize_to(&mut storage, Config::default())
+        .unwrap();
+    assert!(New::deserialize_from(&storage).is_err());
+}
+
+#[test]
+fn check_flat_message_to_serde_wire() {
+    mod wire {
+        #[derive(serde_derive::Deserialize, Debug, PartialEq)]
+        pub struct Account<'a> {
+            pub id: u64,
+            pub owner: &'a str,
+            pub scores: Vec<u16>,
+            pub tags: Vec<String>,
+            pub nickname: Option<String>,
+            pub description: String,
+        }
+    }
+    #[flat_message]
+    struct Account<'a> {
+        id: u64,
+        owner: &'a str,
+        scores: &'a [u16],
+        tags: Vec<String>,
+        #[flat_message(compress = "lz4")]
+        description: String,
+        extra: u8,
+    }
+    let account = Account {
+        id: 7,
+        owner: "Jane",
+        scores: &[1, 2],
+        tags: vec!["x".to_string()],
+        description: "text ".repeat(20),
+        extra: 1,
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    account
+        .serialize_to(&mut storage, Config::default())
+        .unwrap();
+    let value: wire::Account = serde_wire::deserialize_from(&storage).unwrap();
+    assert_eq!(
+        value,
+        wire::Account {
+            id: 7,
+            owner: "Jane",
+            scores: vec![1, 2],
+            tags: vec!["x".to_string()],
+            nickname: None,
+            description: "text ".repeat(20),
+        }
+    );
+    // the structure name is validated
+    #[derive(serde_derive::Deserialize, Debug)]
+    struct Other {
+        _id: u64,
+    }
+    assert_eq!(
+        serde_wire::deserialize_from::<Other>(&storage).unwrap_err(),
+        serde_wire::Error::Format(flat_message::Error::UnmatchedName)
+    );
+}
+
+#[test]
+fn check_serde_wire_roundtrip() {
+    use std::collections::BTreeMap;
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    enum Level {
+        Low,
+        High,
+    }
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    struct Shape {
+        name: String,
+        origin: Point,
+        points: Vec<Point>,
+        level: Level,
+        limits: BTreeMap<String, f64>,
+        weights: Vec<f32>,
+        parent: Option<u32>,
+        empty: Vec<u64>,
+    }
+    let shape = Shape {
+        name: "triangle".repeat(50),
+        origin: Point { x: -1, y: 1 },
+        points: vec![Point { x: 0, y: 0 }, Point { x: 10, y: 0 }, Point { x: 0, y: 10 }],
+        level: Level::High,
+        limits: BTreeMap::from([("max".to_string(), 1.5), ("min".to_string(), -1.5)]),
+        weights: vec![0.5, 0.25],
+        parent: Some(3),
+        empty: Vec::new(),
+    };
+    for config in [
+        Config::default(),
+        ConfigBuilder::new()
+            .compression(Compression::Lz4)
+            .compression_threshold(0)
+            .build(),
+    ] {
+        let mut storage = Storage::default();
+        serde_wire::serialize_to(&shape, &mut storage, config).unwrap();
+        compression::decompress(&mut storage, config).unwrap();
+        let result: Shape = serde_wire::deserialize_from(&storage).unwrap();
+        assert_eq!(result, shape);
+    }
+
+    // enum variants with values and mixed lists can not be represented
+    #[derive(serde_derive::Serialize)]
+    enum Value {
+        Number(u32),
+    }
+    #[derive(serde_derive::Serialize)]
+    struct Unsupported {
+        value: Value,
+    }
+    let mut storage = Storage::default();
+    let error = serde_wire::serialize_to(
+        &Unsupported {
+            value: Value::Number(1),
+        },
+        &mut storage,
+        Config::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(error, serde_wire::Error::Unsupported(_)));
+    assert!(serde_wire::serialize_to(&5u32, &mut storage, Config::default()).is_err());
+}
+
+#[test]
+fn check_json_conversion() {
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    enum Color {
+        Red = 1,
+        Green = 2,
+    }
+    #[flat_message(checksum: true)]
+    struct Item<'a> {
+        id: u64,
+        name: &'a str,
+        #[flat_message(compress = "lz4")]
+        description: String,
+        scores: &'a [i16],
+        tags: Vec<String>,
+        big: u128,
+        ratio: f64,
+        #[flat_message(repr = u8, kind = enum)]
+        color: Color,
+        #[flat_message(repr = u8, kind = enum)]
+        palette: Vec<Color>,
+        empty: Vec<u32>,
+    }
+    let item = Item {
+        id: 10,
+        name: "pen",
+        description: "blue ".repeat(10),
+        scores: &[-1, 2],
+        tags: vec!["a".to_string()],
+        big: u128::MAX,
+        ratio: 0.5,
+        color: Color::Green,
+        palette: vec![Color::Red, Color::Green],
+        empty: Vec::new(),
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    item.serialize_to(&mut storage, Config::default()).unwrap();
+    let value = json::to_json::<Item>(&storage).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "id": 10,
+            "name": "pen",
+            "description": "blue ".repeat(10),
+            "scores": [-1, 2],
+            "tags": ["a"],
+            "big": u128::MAX.to_string(),
+            "ratio": 0.5,
+            "color": "Green",
+            "palette": ["Red", "Green"],
+            "empty": [],
+        })
+    );
+
+    // the JSON value can be converted back into the structure
+    let mut output = Storage::default();
+    let result: Item = json::from_json(&value, &mut output).unwrap();
+    assert_eq!(result.id, 10);
+    assert_eq!(result.name, "pen");
+    assert_eq!(result.description, item.description);
+    assert_eq!(result.scores, &[-1, 2]);
+    assert_eq!(result.tags, vec!["a"]);
+    assert_eq!(result.big, u128::MAX);
+    assert_eq!(result.color, Color::Green);
+    assert_eq!(result.palette, vec![Color::Red, Color::Green]);
+    assert!(result.empty.is_empty());
+    assert_eq!(json::to_json::<Item>(&output).unwrap(), value);
+}
+
+#[test]
+fn check_json_with_names_and_errors() {
+    #[flat_message]
+    struct Point {
+        x: i32,
+        y: i32,
+        #[flat_message(repr = u8, kind = enum)]
+        state: State,
+    }
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    enum State {
+        On = 1,
+    }
+    let mut storage = Storage::default();
+    Point {
+        x: 1,
+        y: -2,
+        state: State::On,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    // unknown names are replaced by the hash of the field, enum values are numbers
+    let mut names = NameRegistry::new();
+    names.register_name("x");
+    let value = json::to_json_with_names(&storage, &names).unwrap();
+    assert_eq!(value["x"], 1);
+    let y = Point::SCHEMA.field("y").unwrap().hash;
+    assert_eq!(value[format!("0x{:08X}", y)], -2);
+    let state = Point::SCHEMA.field("state").unwrap().hash;
+    assert_eq!(value[format!("0x{:08X}", state)], 1);
+
+    let mut output = Storage::default();
+    let error = json::from_json::<Point>(&serde_json::json!({ "x": 1, "z": 2 }), &mut output);
+    assert!(error.err().unwrap().to_string().contains("Unknown field 'z'"));
+    let error = json::from_json::<Point>(
+        &serde_json::json!({ "x": 1, "y": "a", "state": "On" }),
+        &mut output,
+    );
+    assert!(error.err().unwrap().to_string().contains("'y'"));
+    let error = json::from_json::<Point>(
+        &serde_json::json!({ "x": 1, "y": 1, "state": "Off" }),
+        &mut output,
+    );
+    assert!(error.err().unwrap().to_string().contains("'state'"));
+    // missing fields are reported by the structure
+    let error = json::from_json::<Point>(&serde_json::json!({ "x": 1 }), &mut output);
+    assert!(matches!(error.err().unwrap(), json::Error::Format(_)));
+    let point: Point = json::from_json(
+        &serde_json::json!({ "x": 3, "y": 4, "state": 1 }),
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!((point.x, point.y, point.state), (3, 4, State::On));
+}
+
+#[test]
+fn check_serde_fixed_size_arrays() {
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    enum Axis {
+        X = 1,
+        Y = 2,
+    }
+    #[flat_message]
+    struct Geometry<'a> {
+        hash: [u8; 32],
+        position: [f32; 3],
+        keys: &'a [u64; 4],
+        flags: [bool; 2],
+        #[flat_message(repr = u8, kind = enum)]
+        axes: [Axis; 2],
+    }
+    let keys = [1, 2, 3, u64::MAX];
+    let g = Geometry {
+        hash: [7; 32],
+        position: [1.0, -2.5, 3.25],
+        keys: &keys,
+        flags: [true, false],
+        axes: [Axis::Y, Axis::X],
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    g.serialize_to(&mut storage, Config::default()).unwrap();
+    let r = Geometry::deserialize_from(&storage).unwrap();
+    assert_eq!(r.hash, [7; 32]);
+    assert_eq!(r.position, [1.0, -2.5, 3.25]);
+    assert_eq!(r.keys, &keys);
+    assert_eq!(r.flags, [true, false]);
+    assert_eq!(r.axes, [Axis::Y, Axis::X]);
+    let r = unsafe { Geometry::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(r.keys, &keys);
+    assert_eq!(r.position, [1.0, -2.5, 3.25]);
+
+    let schema = Geometry::SCHEMA;
+    let position = schema.field("position").unwrap();
+    assert_eq!(position.kind, FieldKind::Array);
+    assert!(position.kind.is_list());
+    assert_eq!(position.data_format, DataFormat::F32);
+    assert_eq!(schema.field("axes").unwrap().data_format, DataFormat::EnumU8);
+}
+
+#[test]
+fn check_serde_fixed_size_arrays_as_lists() {
+    // arrays are stored as lists (they can be read as slices or vectors)
+    #[flat_message(store_name: false)]
+    struct WithArray {
+        values: [u16; 3],
+    }
+    #[flat_message(store_name: false)]
+    struct WithVec {
+        values: Vec<u16>,
+    }
+    let mut storage = Storage::default();
+    WithArray {
+        values: [1, 2, 3],
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(WithVec::deserialize_from(&storage).unwrap().values, vec![1, 2, 3]);
+
+    // the length is validated
+    #[flat_message(store_name: false)]
+    struct Longer {
+        values: [u16; 4],
+    }
+    let error = Longer::deserialize_from(&storage).err().unwrap();
+    assert!(matches!(error, flat_message::Error::FailToDeserialize(_)));
+    WithVec {
+        values: vec![4, 5, 6],
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    assert_eq!(WithArray::deserialize_from(&storage).unwrap().values, [4, 5, 6]);
+}
+
+#[test]
+fn check_serde_maps() {
+    use std::collections::{BTreeMap, HashMap};
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    enum Level {
+        Low = 1,
+        High = 2,
+    }
+    #[flat_message]
+    struct Request<'a> {
+        id: u32,
+        headers: HashMap<String, String>,
+        limits: BTreeMap<u16, i64>,
+        labels: std::collections::HashMap<&'a str, &'a str>,
+        levels: BTreeMap<String, Level>,
+        empty: HashMap<u8, bool>,
+    }
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "text/plain".to_string());
+    headers.insert("x-id".to_string(), "".to_string());
+    let limits = BTreeMap::from([(1, -10), (500, i64::MAX)]);
+    let labels = HashMap::from([("env", "prod"), ("zone", "eu")]);
+    let levels = BTreeMap::from([("cpu".to_string(), Level::High), ("io".to_string(), Level::Low)]);
+    let r = Request {
+        id: 7,
+        headers: headers.clone(),
+        limits: limits.clone(),
+        labels: labels.clone(),
+        levels: levels.clone(),
+        empty: HashMap::new(),
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    r.serialize_to(&mut storage, Config::default()).unwrap();
+    let d = Request::deserialize_from(&storage).unwrap();
+    assert_eq!(d.id, 7);
+    assert_eq!(d.headers, headers);
+    assert_eq!(d.limits, limits);
+    assert_eq!(d.labels, labels);
+    assert_eq!(d.levels, levels);
+    assert!(d.empty.is_empty());
+    let d = unsafe { Request::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(d.limits, limits);
+    assert_eq!(d.labels, labels);
+
+    // a map can be read with other map types (with the same key and value types)
+    #[flat_message(store_name: false)]
+    struct Limits {
+        limits: HashMap<u16, i64>,
+    }
+    let l = Limits::deserialize_from(&storage).unwrap();
+    assert_eq!(l.limits, HashMap::from([(1, -10), (500, i64::MAX)]));
+    // but not with different types
+    #[flat_message(store_name: false)]
+    struct OtherLimits {
+        limits: HashMap<u16, u64>,
+    }
+    assert!(OtherLimits::deserialize_from(&storage).is_err());
+
+    let schema = Request::SCHEMA;
+    let field = schema.field("headers").unwrap();
+    assert_eq!(field.kind, FieldKind::Map);
+    assert!(!field.kind.is_list());
+    assert_eq!(field.data_format, DataFormat::GenericObject);
+}
+
+#[test]
+fn check_serde_map_item_types() {
+    use std::collections::{BTreeMap, HashMap};
+    use std::num::NonZeroU32;
+    #[derive(Copy, Clone, FlatMessageEnum, PartialEq, Eq, Debug, Hash)]
+    #[repr(u32)]
+    enum Kind {
+        Read = 1,
+        Write = 2,
+    }
+    #[flat_message(store_name: false)]
+    struct Maps {
+        chars: BTreeMap<char, f64>,
+        ids: HashMap<NonZeroU32, u128>,
+        kinds: HashMap<Kind, String>,
+        large: BTreeMap<u16, i8>,
+        names: BTreeMap<String, bool>,
+    }
+    let chars = BTreeMap::from([('a', 1.5), ('ț', -2.25), ('🦀', f64::MAX)]);
+    let ids = HashMap::from([
+        (NonZeroU32::new(1).unwrap(), u128::MAX),
+        (NonZeroU32::new(u32::MAX).unwrap(), 7),
+    ]);
+    let kinds = HashMap::from([(Kind::Read, "r".to_string()), (Kind::Write, "w".to_string())]);
+    // more than 255 entries (the size of the lists uses the extended format)
+    let large: BTreeMap<u16, i8> = (0..1000).map(|i| (i, ((i % 200) as i16 - 100) as i8)).collect();
+    let names: BTreeMap<String, bool> = (0..300).map(|i| (format!("name-{i}"), i % 3 == 0)).collect();
+    let m = Maps {
+        chars: chars.clone(),
+        ids: ids.clone(),
+        kinds: kinds.clone(),
+        large: large.clone(),
+        names: names.clone(),
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    m.serialize_to(&mut storage, Config::default()).unwrap();
+    let d = Maps::deserialize_from(&storage).unwrap();
+    assert_eq!(d.chars, chars);
+    assert_eq!(d.ids, ids);
+    assert_eq!(d.kinds, kinds);
+    assert_eq!(d.large, large);
+    assert_eq!(d.names, names);
+    let d = unsafe { Maps::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(d.chars, chars);
+    assert_eq!(d.ids, ids);
+    assert_eq!(d.kinds, kinds);
+    assert_eq!(d.large, large);
+    assert_eq!(d.names, names);
+
+    // the computed size is the number of bytes that are written
+    let check_size = |size: usize, write: &dyn Fn(*mut u8) -> usize| {
+        let mut buf = vec![0u8; size];
+        assert_eq!(write(buf.as_mut_ptr()), size);
+    };
+    check_size(SerDe::size(&m.chars), &|p| unsafe { SerDe::write(&m.chars, p, 0) });
+    check_size(SerDe::size(&m.ids), &|p| unsafe { SerDe::write(&m.ids, p, 0) });
+    check_size(SerDe::size(&m.kinds), &|p| unsafe { SerDe::write(&m.kinds, p, 0) });
+    check_size(SerDe::size(&m.large), &|p| unsafe { SerDe::write(&m.large, p, 0) });
+    check_size(SerDe::size(&m.names), &|p| unsafe { SerDe::write(&m.names, p, 0) });
+}
+
+#[test]
+fn check_serde_map_view() {
+    use std::collections::{BTreeMap, HashMap};
+    #[flat_message(store_name: false)]
+    struct Owned {
+        tags: HashMap<String, String>,
+    }
+    #[flat_message(store_name: false)]
+    struct Borrowed<'a> {
+        tags: MapView<'a>,
+    }
+    let tags = HashMap::from([
+        ("service".to_string(), "api".to_string()),
+        ("region".to_string(), "eu-west".to_string()),
+        ("empty".to_string(), "".to_string()),
+    ]);
+    let mut storage = Storage::default();
+    Owned {
+        tags: tags.clone(),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut storage, Config::default())
+    .unwrap();
+    let view = Borrowed::deserialize_from(&storage).unwrap().tags;
+    assert_eq!(view.len(), 3);
+    assert!(!view.is_empty());
+    assert_eq!(view.get("service"), Some("api"));
+    assert_eq!(view.get("region"), Some("eu-west"));
+    assert_eq!(view.get("empty"), Some(""));
+    assert_eq!(view.get("missing"), None);
+    assert!(view.contains_key("region"));
+    assert_eq!(view.keys().len(), 3);
+    let entries: HashMap<String, String> = view
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    assert_eq!(entries, tags);
+
+    // a view is written as it is (and can be read as an owned map)
+    let mut copy = Storage::default();
+    Borrowed {
+        tags: view,
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut copy, Config::default())
+    .unwrap();
+    #[flat_message(store_name: false)]
+    struct Sorted {
+        tags: BTreeMap<String, String>,
+    }
+    let sorted = Sorted::deserialize_from(&copy).unwrap();
+    assert_eq!(sorted.tags.len(), 3);
+    assert_eq!(sorted.tags["service"], "api");
+
+    // an empty view
+    Borrowed {
+        tags: MapView::default(),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut copy, Config::default())
+    .unwrap();
+    assert!(Owned::deserialize_from(&copy).unwrap().tags.is_empty());
+    assert!(Borrowed::deserialize_from(&copy).unwrap().tags.is_empty());
+
+    // only maps with strings as keys and values can be viewed
+    #[flat_message(store_name: false)]
+    struct Counters {
+        tags: HashMap<String, u32>,
+    }
+    Counters {
+        tags: HashMap::from([("a".to_string(), 1)]),
+        metadata: MetaData::default(),
+    }
+    .serialize_to(&mut copy, Config::default())
+    .unwrap();
+    assert!(Borrowed::deserialize_from(&copy).is_err());
+}
+
+#[test]
+fn check_serde_maps_with_serde_wire_and_json() {
+    use std::collections::{BTreeMap, HashMap};
+    mod wire {
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+        pub struct Event {
+            pub name: String,
+            pub counters: std::collections::BTreeMap<String, u32>,
+            pub empty: std::collections::HashMap<String, String>,
+        }
+    }
+    #[flat_message]
+    struct Event<'a> {
+        name: &'a str,
+        counters: HashMap<&'a str, u32>,
+        empty: MapView<'a>,
+    }
+    let value = wire::Event {
+        name: "login".to_string(),
+        counters: BTreeMap::from([("ok".to_string(), 10), ("failed".to_string(), 2)]),
+        empty: HashMap::new(),
+    };
+    let mut storage = Storage::default();
+    serde_wire::serialize_to(&value, &mut storage, Config::default()).unwrap();
+    let event = Event::deserialize_from(&storage).unwrap();
+    assert_eq!(event.name, "login");
+    assert_eq!(event.counters, HashMap::from([("ok", 10), ("failed", 2)]));
+    assert!(event.empty.is_empty());
+
+    // and the other way around
+    let event = Event {
+        name: "logout",
+        counters: HashMap::from([("ok", 1)]),
+        empty: MapView::default(),
+        metadata: MetaData::default(),
+    };
+    event.serialize_to(&mut storage, Config::default()).unwrap();
+    let value: wire::Event = serde_wire::deserialize_from(&storage).unwrap();
+    assert_eq!(value.name, "logout");
+    assert_eq!(value.counters, BTreeMap::from([("ok".to_string(), 1)]));
+    assert!(value.empty.is_empty());
+
+    // maps are converted into JSON objects
+    let json = json::to_json::<Event>(&storage).unwrap();
+    assert_eq!(json["counters"], serde_json::json!({"ok": 1}));
+    assert_eq!(json["empty"], serde_json::json!({}));
+    let mut output = Storage::default();
+    assert!(json::from_json::<Event>(&json, &mut output).is_err());
+}
+
+#[test]
+fn check_serde_bytes_fields() {
+    use bytes::Bytes;
+    use std::borrow::Cow;
+    #[flat_message]
+    struct Packet<'a> {
+        id: u32,
+        #[flat_message(kind = bytes)]
+        payload: Vec<u8>,
+        #[flat_message(kind = bytes)]
+        raw: &'a [u8],
+        blob: Cow<'a, [u8]>,
+        data: bytes::Bytes,
+        #[flat_message(kind = bytes)]
+        imported: Bytes,
+        numbers: Vec<u8>,
+    }
+    let p = Packet {
+        id: 1,
+        payload: vec![1, 2, 3],
+        raw: &[0xFF; 300],
+        blob: Cow::Owned(vec![9, 8]),
+        data: bytes::Bytes::from_static(b"hello"),
+        imported: Bytes::from_static(b"world"),
+        numbers: vec![4, 5],
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    p.serialize_to(&mut storage, Config::default()).unwrap();
+    let r = Packet::deserialize_from(&storage).unwrap();
+    assert_eq!(r.id, 1);
+    assert_eq!(r.payload, vec![1, 2, 3]);
+    assert_eq!(r.raw, &[0xFF; 300]);
+    assert!(matches!(r.blob, Cow::Borrowed(&[9, 8])));
+    assert_eq!(r.data, bytes::Bytes::from_static(b"hello"));
+    assert_eq!(r.imported, Bytes::from_static(b"world"));
+    assert_eq!(r.numbers, vec![4, 5]);
+    let r = unsafe { Packet::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(r.raw, &[0xFF; 300]);
+    assert_eq!(r.blob.as_ref(), &[9, 8]);
+
+    // bytes fields have their own data format (they are not lists of u8 values)
+    let schema = Packet::SCHEMA;
+    for name in ["payload", "raw", "blob", "data", "imported"] {
+        let field = schema.field(name).unwrap();
+        assert_eq!(field.data_format, DataFormat::Bytes);
+        assert_eq!(field.kind, FieldKind::Object);
+    }
+    assert_eq!(schema.field("numbers").unwrap().data_format, DataFormat::U8);
+    let buffer = FlatMessageBuffer::try_from(&storage).unwrap();
+    assert_eq!(buffer.get::<&[u8]>(name!("payload")), Some(&[1u8, 2, 3][..]));
+    assert_eq!(buffer.get_slice::<u8>(name!("payload")), None);
+    #[flat_message(store_name: false)]
+    struct AsList {
+        payload: Vec<u8>,
+    }
+    assert!(AsList::deserialize_from(&storage).is_err());
+
+    // bytes fields are converted to JSON as lists of numbers
+    let json = json::to_json::<Packet>(&storage).unwrap();
+    assert_eq!(json["data"], serde_json::json!([104, 101, 108, 108, 111]));
+    let mut output = Storage::default();
+    let r = json::from_json::<Packet>(&json, &mut output).unwrap();
+    assert_eq!(r.payload, vec![1, 2, 3]);
+    assert_eq!(r.blob.as_ref(), &[9, 8]);
+}
+
+#[test]
+fn check_serde_bytes_alignment() {
+    #[flat_message(store_name: false)]
+    struct Page<'a> {
+        flag: u8,
+        name: &'a str,
+        #[flat_message(kind = bytes, align = 4096)]
+        page: &'a [u8],
+        #[flat_message(kind = bytes, align = 64)]
+        line: Vec<u8>,
+        values: Vec<u32>,
+    }
+    let page = vec![7u8; 8192];
+    let p = Page {
+        flag: 1,
+        name: "page",
+        page: &page,
+        line: vec![1; 10],
+        values: vec![1, 2, 3],
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    p.serialize_to(&mut storage, Config::default()).unwrap();
+    let r = Page::deserialize_from(&storage).unwrap();
+    assert_eq!(r.page, &page[..]);
+    assert_eq!(r.line, vec![1; 10]);
+    assert_eq!(r.values, vec![1, 2, 3]);
+    assert_eq!(r.name, "page");
+    // the data is aligned relative to the start of the message
+    let start = storage.as_slice().as_ptr() as usize;
+    assert_eq!((r.page.as_ptr() as usize - start) % 4096, 0);
+    let buffer = FlatMessageBuffer::try_from(&storage).unwrap();
+    let line: &[u8] = buffer.get(name!("line")).unwrap();
+    assert_eq!((line.as_ptr() as usize - start) % 64, 0);
+    // and in memory up to the alignment of the buffer (16 bytes for a storage)
+    assert_eq!(r.page.as_ptr() as usize % 16, 0);
+
+    // bytes fields in the generated code
+    let code = flat_message_codegen::generate(
+        r#"{ "structs": [ { "name": "Blob", "fields": [
+            { "name": "payload", "type": "bytes" } ] } ] }"#,
+    )
+    .unwrap();
+    assert!(code.contains("#[flat_message(kind = bytes)]"));
+    assert!(code.contains("    pub payload: Vec<u8>,"));
+    let error = flat_message_codegen::generate(
+        r#"{ "structs": [ { "name": "Blob", "fields": [
+            { "name": "payload", "type": "bytes", "list": true } ] } ] }"#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("can not be a list of bytes"));
+}
+
+#[test]
+fn check_serde_char_size_and_non_zero_types() {
+    use std::num::{NonZero, NonZeroI64, NonZeroU16, NonZeroU32};
+    #[flat_message]
+    struct Record<'a> {
+        letter: char,
+        count: usize,
+        offset: isize,
+        id: NonZeroU32,
+        delta: NonZero<i64>,
+        sizes: Vec<usize>,
+        text: &'a [char],
+        ports: Vec<NonZeroU16>,
+    }
+    let text = ['a', 'ă', '🦀'];
+    let r = Record {
+        letter: '€',
+        count: 123456,
+        offset: -5,
+        id: NonZeroU32::new(7).unwrap(),
+        delta: NonZeroI64::new(-1).unwrap(),
+        sizes: vec![0, 1, usize::MAX >> 32],
+        text: &text,
+        ports: vec![NonZeroU16::new(80).unwrap(), NonZeroU16::new(443).unwrap()],
+        metadata: MetaData::default(),
+    };
+    let mut storage = Storage::default();
+    r.serialize_to(&mut storage, Config::default()).unwrap();
+    let d = Record::deserialize_from(&storage).unwrap();
+    assert_eq!(d.letter, '€');
+    assert_eq!(d.count, 123456);
+    assert_eq!(d.offset, -5);
+    assert_eq!(d.id.get(), 7);
+    assert_eq!(d.delta.get(), -1);
+    assert_eq!(d.sizes, vec![0, 1, usize::MAX >> 32]);
+    assert_eq!(d.text, &text);
+    assert_eq!(d.ports, r.ports);
+    let d = unsafe { Record::deserialize_from_unchecked(&storage).unwrap() };
+    assert_eq!(d.letter, '€');
+    assert_eq!(d.text, &text);
+
+    // the types are stored with portable integer formats
+    let schema = Record::SCHEMA;
+    assert_eq!(schema.field("letter").unwrap().data_format, DataFormat::U32);
+    assert_eq!(schema.field("count").unwrap().data_format, DataFormat::U64);
+    assert_eq!(schema.field("offset").unwrap().data_format, DataFormat::I64);
+    assert_eq!(schema.field("id").unwrap().data_format, DataFormat::U32);
+    assert_eq!(schema.field("delta").unwrap().data_format, DataFormat::I64);
+    assert_eq!(schema.field("sizes").unwrap().data_format, DataFormat::U64);
+    assert_eq!(schema.field("text").unwrap().data_format, DataFormat::U32);
+    assert_eq!(schema.field("ports").unwrap().data_format, DataFormat::U16);
+    let buffer = FlatMessageBuffer::try_from(&storage).unwrap();
+    assert_eq!(buffer.get::<u32>(name!("letter")), Some('€' as u32));
+    assert_eq!(buffer.get::<u64>(name!("count")), Some(123456));
+    assert_eq!(buffer.get::<char>(name!("letter")), Some('€'));
+    assert_eq!(buffer.get_slice::<u64>(name!("sizes")), Some(&[0, 1, u64::from(u32::MAX)][..]));
+
+    // lists of usize/isize have the same layout as lists of u64/i64
+    let sizes: Vec<usize> = (0..300).collect();
+    let offsets: Vec<isize> = (-150..150).collect();
+    let mut buf = vec![0u8; SerDeVec::size(&sizes)];
+    assert_eq!(SerDeVec::size(&sizes), SerDeSlice::size(&[0u64; 300][..]));
+    assert_eq!(unsafe { SerDeVec::write(&sizes, buf.as_mut_ptr(), 0) }, buf.len());
+    assert_eq!(<u64 as SerDeVec>::from_buffer(&buf, 0).unwrap(), (0..300).collect::<Vec<u64>>());
+    let mut buf = vec![0u8; SerDeVec::size(&offsets)];
+    assert_eq!(unsafe { SerDeVec::write(&offsets, buf.as_mut_ptr(), 0) }, buf.len());
+    assert_eq!(<isize as SerDeVec>::from_buffer(&buf, 0).unwrap(), offsets);
+}
+
+#[test]
+fn check_serde_char_size_and_non_zero_validation() {
+    use std::num::{NonZeroI8, NonZeroU32};
+    #[flat_message(store_name: false)]
+    struct Raw<'a> {
+        letter: u32,
+        id: u32,
+        level: i8,
+        text: &'a [u32],
+    }
+    #[flat_message(store_name: false)]
+    struct Typed<'a> {
+        letter: char,
+        id: NonZeroU32,
+        level: NonZeroI8,
+        text: &'a [char],
+    }
+    let mut storage = Storage::default();
+    let write = |letter: u32, id: u32, level: i8, text: &[u32], storage: &mut Storage| {
+        Raw { letter, id, level, text, metadata: MetaData::default() }
+            .serialize_to(storage, Config::default())
+            .unwrap();
+    };
+    write('x' as u32, 1, -1, &[65, 66], &mut storage);
+    let t = Typed::deserialize_from(&storage).unwrap();
+    assert_eq!((t.letter, t.id.get(), t.level.get(), t.text), ('x', 1, -1, &['A', 'B'][..]));
+    // surrogates and values above 0x10FFFF are not Unicode scalar values
+    write(0xD800, 1, -1, &[65], &mut storage);
+    assert!(Typed::deserialize_from(&storage).is_err());
+    write('x' as u32, 1, -1, &[65, 0x110000], &mut storage);
+    assert!(Typed::deserialize_from(&storage).is_err());
+    // zero is rejected for NonZero types
+    write('x' as u32, 0, -1, &[], &mut storage);
+    assert!(Typed::deserialize_from(&storage).is_err());
+    write('x' as u32, 1, 0, &[], &mut storage);
+    assert!(Typed::deserialize_from(&storage).is_err());
+
+    // isize values are stored as i64 (and read as i64 or isize)
+    #[flat_message(store_name: false)]
+    struct Wide {
+        value: i64,
+        values: Vec<u64>,
+    }
+    #[flat_message(store_name: false)]
+    struct Native {
+        value: isize,
+        values: Vec<usize>,
+    }
+    Wide { value: i64::MIN, values: vec![u32::MAX as u64], metadata: MetaData::default() }
+        .serialize_to(&mut storage, Config::default())
+        .unwrap();
+    let n = Native::deserialize_from(&storage);
+    if cfg!(target_pointer_width = "64") {
+        let n = n.unwrap();
+        assert_eq!((n.value as i64, n.values), (i64::MIN, vec![u32::MAX as usize]));
+    } else {
+        // values that do not fit on 32 bit targets are rejected
+        assert!(n.is_err());
+    }
+}
+
+#[test]
+fn check_serde_wire_char() {
+    mod wire {
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+        pub struct Letter {
+            pub value: char,
+            pub count: usize,
+        }
+    }
+    #[flat_message]
+    struct Letter {
+        value: char,
+        count: usize,
+    }
+    let mut storage = Storage::default();
+    let value = wire::Letter { value: 'ș', count: 3 };
+    serde_wire::serialize_to(&value, &mut storage, Config::default()).unwrap();
+    let l = Letter::deserialize_from(&storage).unwrap();
+    assert_eq!((l.value, l.count), ('ș', 3));
+    Letter { value: 'z', count: 4, metadata: MetaData::default() }
+        .serialize_to(&mut storage, Config::default())
+        .unwrap();
+    let l: wire::Letter = serde_wire::deserialize_from(&storage).unwrap();
+    assert_eq!((l.value, l.count), ('z', 4));
+}
+
 #[test]
 fn check_serde_buffer_i8() {
     #[flat_message(metadata: false)]
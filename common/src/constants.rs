@@ -1,6 +1,12 @@
 pub const MAGIC_V1: u32 = u32::from_ne_bytes(*b"GTH\x01");
+pub const MAGIC_V1_LZ4: u32 = u32::from_ne_bytes(*b"GTH\x11");
+pub const MAGIC_ARCHIVE_V1: u32 = u32::from_ne_bytes(*b"GTHA");
 pub const FLAGS_OFFSET_SIZE: u8 = 0b0000_0011;
 pub const FLAG_HAS_CHECKSUM: u8 = 0b0000_0100;
 pub const FLAG_HAS_NAME_HASH: u8 = 0b0000_1000;
 pub const FLAG_HAS_TIMESTAMP: u8 = 0b0001_0000;
 pub const FLAG_HAS_UNIQUEID: u8 = 0b0010_0000;
+pub const FLAG_HAS_MAC: u8 = 0b0100_0000;
+pub const FLAG_IS_ENCRYPTED: u8 = 0b1000_0000;
+pub const MAC_SIZE: usize = 32;
+pub const ENCRYPTION_INFO_SIZE: usize = 32;
@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -27,6 +28,9 @@ pub enum DataFormat {
     EnumU16,
     EnumU32,
     EnumU64,
+
+    /// Opaque binary data (a `u32` length followed by the bytes)
+    Bytes,
 }
 impl DataFormat {
     pub fn is_enum(&self) -> bool {
@@ -67,6 +71,7 @@ impl DataFormat {
             DataFormat::EnumU32 => 4,
             DataFormat::EnumU64 => 8,
             DataFormat::GenericObject => 1,
+            DataFormat::Bytes => 1,
         }
     }
 }
@@ -98,6 +103,73 @@ impl Display for DataFormat {
             DataFormat::EnumU64 => write!(f, "EnumU64"),
 
             DataFormat::GenericObject => write!(f, "GenericObject"),
+            DataFormat::Bytes => write!(f, "Bytes"),
+        }
+    }
+}
+
+impl TryFrom<u8> for DataFormat {
+    type Error = String;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DataFormat::GenericObject),
+            1 => Ok(DataFormat::U8),
+            2 => Ok(DataFormat::U16),
+            3 => Ok(DataFormat::U32),
+            4 => Ok(DataFormat::U64),
+            5 => Ok(DataFormat::U128),
+            6 => Ok(DataFormat::I8),
+            7 => Ok(DataFormat::I16),
+            8 => Ok(DataFormat::I32),
+            9 => Ok(DataFormat::I64),
+            10 => Ok(DataFormat::I128),
+            11 => Ok(DataFormat::F32),
+            12 => Ok(DataFormat::F64),
+            13 => Ok(DataFormat::Bool),
+            14 => Ok(DataFormat::String),
+            15 => Ok(DataFormat::EnumI8),
+            16 => Ok(DataFormat::EnumI16),
+            17 => Ok(DataFormat::EnumI32),
+            18 => Ok(DataFormat::EnumI64),
+            19 => Ok(DataFormat::EnumU8),
+            20 => Ok(DataFormat::EnumU16),
+            21 => Ok(DataFormat::EnumU32),
+            22 => Ok(DataFormat::EnumU64),
+            23 => Ok(DataFormat::Bytes),
+            _ => Err(format!("Invalid data format value: {} !", value)),
+        }
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "GenericObject" => Ok(DataFormat::GenericObject),
+            "U8" => Ok(DataFormat::U8),
+            "U16" => Ok(DataFormat::U16),
+            "U32" => Ok(DataFormat::U32),
+            "U64" => Ok(DataFormat::U64),
+            "U128" => Ok(DataFormat::U128),
+            "I8" => Ok(DataFormat::I8),
+            "I16" => Ok(DataFormat::I16),
+            "I32" => Ok(DataFormat::I32),
+            "I64" => Ok(DataFormat::I64),
+            "I128" => Ok(DataFormat::I128),
+            "F32" => Ok(DataFormat::F32),
+            "F64" => Ok(DataFormat::F64),
+            "Bool" => Ok(DataFormat::Bool),
+            "String" => Ok(DataFormat::String),
+            "EnumI8" => Ok(DataFormat::EnumI8),
+            "EnumI16" => Ok(DataFormat::EnumI16),
+            "EnumI32" => Ok(DataFormat::EnumI32),
+            "EnumI64" => Ok(DataFormat::EnumI64),
+            "EnumU8" => Ok(DataFormat::EnumU8),
+            "EnumU16" => Ok(DataFormat::EnumU16),
+            "EnumU32" => Ok(DataFormat::EnumU32),
+            "EnumU64" => Ok(DataFormat::EnumU64),
+            "Bytes" => Ok(DataFormat::Bytes),
+            _ => Err(format!("Invalid data format: '{}' !", value)),
         }
     }
 }
@@ -118,6 +190,19 @@ impl From<&str> for DataFormat {
             "f32" => DataFormat::F32,
             "f64" => DataFormat::F64,
             "bool" => DataFormat::Bool,
+            "char" => DataFormat::U32,
+            "usize" => DataFormat::U64,
+            "isize" => DataFormat::I64,
+            "NonZeroU8" | "NonZero<u8>" => DataFormat::U8,
+            "NonZeroU16" | "NonZero<u16>" => DataFormat::U16,
+            "NonZeroU32" | "NonZero<u32>" => DataFormat::U32,
+            "NonZeroU64" | "NonZero<u64>" => DataFormat::U64,
+            "NonZeroU128" | "NonZero<u128>" => DataFormat::U128,
+            "NonZeroI8" | "NonZero<i8>" => DataFormat::I8,
+            "NonZeroI16" | "NonZero<i16>" => DataFormat::I16,
+            "NonZeroI32" | "NonZero<i32>" => DataFormat::I32,
+            "NonZeroI64" | "NonZero<i64>" => DataFormat::I64,
+            "NonZeroI128" | "NonZero<i128>" => DataFormat::I128,
             "&str" => DataFormat::String,
             "String" => DataFormat::String,
             "enum_i8" => DataFormat::EnumI8,
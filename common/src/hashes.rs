@@ -1,3 +1,5 @@
+use crate::data_format::DataFormat;
+
 const CRC32_TABLE: [u32; 256] = [
     0x00000000, 0x77073096, 0xee0e612c, 0x990951ba, 0x076dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
     0x0edb8832, 0x79dcb8a4, 0xe0d5e91e, 0x97d2d988, 0x09b64c2b, 0x7eb17cbd, 0xe7b82d07, 0x90bf1d91,
@@ -80,3 +82,17 @@ pub fn fnv_32(text: &str) -> u32 {
     }
     hash
 }
+
+/// The hash of a field (as it is stored in the hash table): the upper 24 bits are computed from
+/// the name and the low byte is the data format (| 0x80 for slices and vectors, | 0x40 for
+/// compressed fields)
+pub fn field_hash(name: &str, data_format: DataFormat, list: bool, compressed: bool) -> u32 {
+    let mut type_hash = data_format as u32;
+    if list {
+        type_hash |= 0x80;
+    }
+    if compressed {
+        type_hash |= 0x40;
+    }
+    (fnv_32(name) & 0xFFFFFF00) | type_hash
+}
@@ -1,3 +1,4 @@
 pub mod constants;
 pub mod data_format;
 pub mod hashes;
+pub mod version_set;
@@ -0,0 +1,206 @@
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum VersionToken {
+    Number(u8),
+    Interval,
+    Lower,
+    Separator,
+    Skip,
+}
+impl VersionToken {
+    fn new(ctype: CharType, value: &str) -> Result<VersionToken, String> {
+        match ctype {
+            CharType::Number => {
+                if let Ok(num) = value.parse::<u8>() {
+                    if num == 0 {
+                        return Err("A version can not be 0 ".to_string());
+                    }
+                    Ok(VersionToken::Number(num))
+                } else {
+                    Err(format!(
+                        "Invalid version number '{}' (should be a number between 1 and 255) !",
+                        value
+                    ))
+                }
+            }
+            CharType::Operator => match value {
+                "=" => Ok(VersionToken::Skip),
+                "<" => Ok(VersionToken::Lower),
+                "-" => Ok(VersionToken::Interval),
+                ":" => Ok(VersionToken::Interval),
+                ".." => Ok(VersionToken::Interval),
+                _ => Err(format!(
+                    "Invalid operator '{}' (accepted operators are '<', '..', '-', ':') !",
+                    value
+                )),
+            },
+            CharType::Space => Ok(VersionToken::Skip),
+            CharType::Separator => Ok(VersionToken::Separator),
+            CharType::Invalid => Err(format!("Invalid character '{}' !", value)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum CharType {
+    Number,
+    Operator,
+    Space,
+    Separator,
+    Invalid,
+}
+impl From<char> for CharType {
+    fn from(c: char) -> Self {
+        match c {
+            '0'..='9' => Self::Number,
+            '<' => Self::Operator,
+            '.' => Self::Operator,
+            ':' => Self::Operator,
+            '-' => Self::Operator,
+            ' ' => Self::Space,
+            ',' => Self::Separator,
+            _ => Self::Invalid,
+        }
+    }
+}
+
+/// A set of versions (1..=255) described by a list of expressions separated by commas:
+/// a version (`7`), an interval (`1..5`, `1-5` or `1:5`) or all versions up to a value (`<3`)
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct VersionSet {
+    bits: [u64; 4],
+}
+impl VersionSet {
+    pub const fn new() -> Self {
+        Self { bits: [0; 4] }
+    }
+    #[inline(always)]
+    pub const fn contains(&self, version: u8) -> bool {
+        self.bits[(version >> 6) as usize] & (1u64 << (version & 63)) != 0
+    }
+    /// Adds a version (0 is not a valid version and is ignored)
+    pub fn insert(&mut self, version: u8) {
+        if version != 0 {
+            self.bits[(version >> 6) as usize] |= 1u64 << (version & 63);
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits == [0; 4]
+    }
+    fn add(&mut self, expr: &[VersionToken], expr_name: &str) -> Result<(), String> {
+        match expr.len() {
+            0 => Err(format!("Empty version expression '{}'", expr_name)),
+            1 => {
+                if let VersionToken::Number(value) = expr[0] {
+                    self.insert(value);
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "Invalid version expression '{}', expected a version number !",
+                        expr_name
+                    ))
+                }
+            }
+            2 => {
+                if let VersionToken::Lower = expr[0] {
+                    if let VersionToken::Number(value) = expr[1] {
+                        for i in 1..=value {
+                            self.insert(i);
+                        }
+                        Ok(())
+                    } else {
+                        Err(format!(
+                            "Invalid version expression '{}', expected a version number after the lower sign (ex: <10) !",
+                            expr_name
+                        ))
+                    }
+                } else {
+                    Err(format!(
+                        "Invalid version expression '{}', expected a lower operator followed by a number !",
+                        expr_name
+                    ))
+                }
+            }
+            3 => {
+                if let VersionToken::Number(start) = expr[0] {
+                    if let VersionToken::Interval = expr[1] {
+                        if let VersionToken::Number(end) = expr[2] {
+                            if start > end {
+                                return Err(format!(
+                                    "Invalid version expression '{}', the start value '{}' should be lower than the end value '{}' !",
+                                    expr_name, start, end
+                                ));
+                            }
+                            for i in start..=end {
+                                self.insert(i);
+                            }
+                            Ok(())
+                        } else {
+                            Err(format!(
+                                "Invalid version expression '{}', expected a version number after the interval operator (ex: 1-255) !",
+                                expr_name
+                            ))
+                        }
+                    } else {
+                        Err(format!(
+                            "Invalid version expression '{}', expected an interval operator between the two numbers. An interval operator can be ':', '-' or '..' !",
+                            expr_name
+                        ))
+                    }
+                } else {
+                    Err(format!(
+                        "Invalid version expression '{}', expected an interval (ex: '1-10' or '5:8' or '11..100')",
+                        expr_name
+                    ))
+                }
+            }
+            _ => Err(format!("Unkown version format express: '{}'", expr_name)),
+        }
+    }
+}
+impl TryFrom<&str> for VersionSet {
+    type Error = String;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut tokens = Vec::new();
+        let mut set = Self::default();
+        let mut start = 0;
+        let mut start_expr = 0;
+        let mut current_type = CharType::Invalid;
+        for (pos, c) in value.char_indices() {
+            let c_type = CharType::from(c);
+            if c_type == CharType::Invalid {
+                return Err(format!(
+                    "Invalid character '{}' at position '{}' from '{}' !",
+                    c, pos, value
+                ));
+            }
+            if pos == 0 {
+                start = pos;
+                current_type = c_type;
+                continue;
+            } else if c_type != current_type {
+                let token = VersionToken::new(current_type, &value[start..pos])?;
+                match token {
+                    VersionToken::Separator => {
+                        set.add(tokens.as_slice(), &value[start_expr..pos])?;
+                        start_expr = pos + 1;
+                        tokens.clear();
+                    }
+                    VersionToken::Skip => {}
+                    _ => tokens.push(token),
+                }
+                start = pos;
+                current_type = c_type;
+            }
+        }
+        if start < value.len() {
+            let token = VersionToken::new(current_type, &value[start..])?;
+            if (token != VersionToken::Skip) && (token != VersionToken::Separator) {
+                tokens.push(token);
+            }
+            if !tokens.is_empty() {
+                set.add(tokens.as_slice(), &value[start_expr..])?;
+            }
+        }
+        Ok(set)
+    }
+}
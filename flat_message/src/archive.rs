@@ -0,0 +1,187 @@
+use crate::{Config, Error, FlatMessage, Storage, VecLike};
+use common::constants;
+use std::io::Write;
+
+const ALIGNMENT: usize = 16;
+const INDEX_ENTRY_SIZE: usize = 16;
+const FOOTER_SIZE: usize = 16;
+
+/// Archive layout
+/// | Offset | Type | Observation                                                        |
+/// |--------|------|--------------------------------------------------------------------|
+/// | +0     | ?    | Messages (each one starts at an offset aligned to 16 bytes)        |
+/// | ?      | ?    | Index (u64 offset + u64 size for each message), aligned to 16 bytes|
+/// | -16    | u64  | Offset of the index                                                |
+/// | -8     | u32  | Number of messages                                                 |
+/// | -4     | u32  | Magic (GTHA)                                                       |
+pub struct ArchiveWriter<W: Write> {
+    writer: W,
+    config: Config,
+    storage: Storage,
+    index: Vec<(u64, u64)>,
+    position: u64,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(writer: W, config: Config) -> Self {
+        Self {
+            writer,
+            config,
+            storage: Storage::default(),
+            index: Vec::new(),
+            position: 0,
+        }
+    }
+    /// Serializes the message and adds it to the archive
+    pub fn append<'a, T: FlatMessage<'a>>(&mut self, message: &T) -> Result<(), Error> {
+        let mut storage = std::mem::take(&mut self.storage);
+        let result = message
+            .serialize_to(&mut storage, self.config)
+            .and_then(|_| self.append_storage(&storage));
+        self.storage = storage;
+        result
+    }
+    /// Adds an already serialized message to the archive
+    pub fn append_storage(&mut self, storage: &Storage) -> Result<(), Error> {
+        if storage.len() > self.config.max_size() as usize {
+            return Err(Error::ExceedMaxSize((
+                storage.len() as u32,
+                self.config.max_size(),
+            )));
+        }
+        self.index.push((self.position, storage.len() as u64));
+        self.write(storage.as_slice())?;
+        self.align()
+    }
+    /// Writes the index and the footer and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, Error> {
+        let index_offset = self.position;
+        let index = std::mem::take(&mut self.index);
+        for (offset, size) in index.iter() {
+            self.write(&offset.to_ne_bytes())?;
+            self.write(&size.to_ne_bytes())?;
+        }
+        self.write(&index_offset.to_ne_bytes())?;
+        self.write(&(index.len() as u32).to_ne_bytes())?;
+        self.write(&constants::MAGIC_ARCHIVE_V1.to_ne_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(buf)?;
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+    fn align(&mut self) -> Result<(), Error> {
+        let padding = (ALIGNMENT - (self.position as usize % ALIGNMENT)) % ALIGNMENT;
+        self.write(&[0u8; ALIGNMENT][..padding])
+    }
+}
+
+/// Zero-copy reader for an archive (the buffer has to be aligned to 16 bytes)
+#[derive(Debug, Copy, Clone)]
+pub struct Archive<'a> {
+    data: &'a [u8],
+    index: &'a [u8],
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if !(data.as_ptr() as usize).is_multiple_of(ALIGNMENT) {
+            return Err(Error::UnalignedBuffer);
+        }
+        let len = data.len();
+        if len < FOOTER_SIZE {
+            return Err(Error::InvalidArchive);
+        }
+        let footer = &data[len - FOOTER_SIZE..];
+        let index_offset = u64::from_ne_bytes(footer[..8].try_into().unwrap());
+        let count = u32::from_ne_bytes(footer[8..12].try_into().unwrap()) as u64;
+        let magic = u32::from_ne_bytes(footer[12..].try_into().unwrap());
+        let index_end = index_offset.checked_add(count * INDEX_ENTRY_SIZE as u64);
+        if magic != constants::MAGIC_ARCHIVE_V1 || index_end != Some((len - FOOTER_SIZE) as u64) {
+            return Err(Error::InvalidArchive);
+        }
+        let archive = Self::from_validated(data, index_offset as usize);
+        // validate all entries once (so that `get` does not have to)
+        for idx in 0..archive.len() {
+            let (offset, size) = archive.entry(idx);
+            let end = offset.checked_add(size);
+            if !offset.is_multiple_of(ALIGNMENT as u64) || end.is_none_or(|end| end > index_offset)
+            {
+                return Err(Error::InvalidArchive);
+            }
+        }
+        Ok(archive)
+    }
+    // an archive whose footer and index were already validated by `new`
+    #[inline(always)]
+    fn from_validated(data: &'a [u8], index_offset: usize) -> Self {
+        Self {
+            data,
+            index: &data[index_offset..data.len() - FOOTER_SIZE],
+        }
+    }
+    /// The offset of the index (where the messages end)
+    #[inline(always)]
+    fn index_offset(&self) -> usize {
+        self.data.len() - FOOTER_SIZE - self.index.len()
+    }
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.index.len() / INDEX_ENTRY_SIZE
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+    /// The serialized message with the provided index
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.len() {
+            return None;
+        }
+        let (offset, size) = self.entry(index);
+        Some(&self.data[offset as usize..(offset + size) as usize])
+    }
+    /// Deserializes the message with the provided index (fields are borrowed from the archive)
+    pub fn deserialize<T: FlatMessage<'a>>(&self, index: usize) -> Result<T, Error> {
+        match self.get(index) {
+            Some(buf) => T::deserialize_from_slice(buf),
+            None => Err(Error::InvalidArchive),
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        (0..self.len()).map(|idx| self.get(idx).unwrap())
+    }
+    #[inline(always)]
+    fn entry(&self, index: usize) -> (u64, u64) {
+        let entry = &self.index[index * INDEX_ENTRY_SIZE..(index + 1) * INDEX_ENTRY_SIZE];
+        (
+            u64::from_ne_bytes(entry[..8].try_into().unwrap()),
+            u64::from_ne_bytes(entry[8..].try_into().unwrap()),
+        )
+    }
+}
+
+/// An archive file mapped in memory (requires the `mmap` feature)
+#[cfg(feature = "mmap")]
+pub struct MmapArchive {
+    mmap: memmap2::Mmap,
+    // validated when the file is opened
+    index_offset: usize,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapArchive {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        // the file should not be modified while it is mapped
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        let index_offset = Archive::new(&mmap)?.index_offset();
+        Ok(Self { mmap, index_offset })
+    }
+    #[inline(always)]
+    pub fn archive(&self) -> Archive<'_> {
+        Archive::from_validated(&self.mmap, self.index_offset)
+    }
+}
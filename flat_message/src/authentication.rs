@@ -0,0 +1,47 @@
+use crate::headers::Layout;
+use crate::{Error, Storage, VecLike};
+use common::constants;
+
+#[inline(always)]
+pub fn compute_mac(key: &[u8; constants::MAC_SIZE], data: &[u8]) -> [u8; constants::MAC_SIZE] {
+    *blake3::keyed_hash(key, data).as_bytes()
+}
+
+/// Checks the MAC of a serialized message. The MAC covers the plain (not encrypted and not
+/// compressed) message, so encrypted messages have to be decrypted first and compressed messages
+/// have to be decompressed first.
+pub fn verify_mac(key: &[u8; constants::MAC_SIZE], buf: &[u8]) -> Result<(), Error> {
+    let layout = Layout::new(buf)?;
+    if layout.header.magic == constants::MAGIC_V1_LZ4 {
+        return Err(Error::CompressedMessage);
+    }
+    if layout.has_flag(constants::FLAG_IS_ENCRYPTED) {
+        return Err(Error::EncryptedMessage);
+    }
+    if !layout.has_flag(constants::FLAG_HAS_MAC) {
+        return Err(Error::AuthenticationFailed);
+    }
+    // the MAC is stored right before the CRC32 value (if any)
+    let mac_offset = buf.len() - layout.checksum_size() - constants::MAC_SIZE;
+    let expected = blake3::keyed_hash(key, &buf[..mac_offset]);
+    let mut stored = [0u8; constants::MAC_SIZE];
+    stored.copy_from_slice(&buf[mac_offset..mac_offset + constants::MAC_SIZE]);
+    // blake3::Hash comparison is done in constant time
+    if expected != blake3::Hash::from(stored) {
+        return Err(Error::AuthenticationFailed);
+    }
+    Ok(())
+}
+
+pub struct Verifier {
+    key: [u8; constants::MAC_SIZE],
+}
+
+impl Verifier {
+    pub fn new(key: [u8; constants::MAC_SIZE]) -> Self {
+        Self { key }
+    }
+    pub fn verify(&self, input: &Storage) -> Result<(), Error> {
+        verify_mac(&self.key, input.as_slice())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::frame::{self, FrameHeader};
+use crate::{Config, Error, FlatMessage, Storage, VecLike};
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Tokio codec for FlatMessage frames (same layout as `FrameWriter` / `FrameReader`)
+pub struct FrameCodec {
+    config: Config,
+    storage: Storage,
+    skipped: u64,
+}
+
+impl FrameCodec {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            storage: Storage::default(),
+            skipped: 0,
+        }
+    }
+    /// Number of bytes that were skipped (while resynchronizing) so far
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+    fn encode_buffer(&self, message: &[u8], dst: &mut BytesMut) -> Result<(), Error> {
+        if message.len() > self.config.max_size() as usize {
+            return Err(Error::ExceedMaxSize((
+                message.len() as u32,
+                self.config.max_size(),
+            )));
+        }
+        dst.reserve(frame::LENGTH_SIZE + message.len());
+        dst.put_slice(&(message.len() as u32).to_ne_bytes());
+        dst.put_slice(message);
+        Ok(())
+    }
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl<'a, T: FlatMessage<'a>> Encoder<&T> for FrameCodec {
+    type Error = Error;
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> Result<(), Error> {
+        let mut storage = std::mem::take(&mut self.storage);
+        let result = item
+            .serialize_to(&mut storage, self.config)
+            .and_then(|_| self.encode_buffer(storage.as_slice(), dst));
+        self.storage = storage;
+        result
+    }
+}
+
+impl Encoder<Storage> for FrameCodec {
+    type Error = Error;
+    fn encode(&mut self, item: Storage, dst: &mut BytesMut) -> Result<(), Error> {
+        self.encode_buffer(item.as_slice(), dst)
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Storage;
+    type Error = Error;
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Storage>, Error> {
+        loop {
+            match frame::parse_frame_header(src, &self.config) {
+                FrameHeader::Incomplete => return Ok(None),
+                FrameHeader::Valid(size) => {
+                    let frame_size = frame::LENGTH_SIZE + size;
+                    if src.len() < frame_size {
+                        src.reserve(frame_size - src.len());
+                        return Ok(None);
+                    }
+                    let storage = Storage::from_buffer(&src[frame::LENGTH_SIZE..frame_size]);
+                    src.advance(frame_size);
+                    return Ok(Some(storage));
+                }
+                FrameHeader::Invalid => {
+                    let count = frame::resync_offset(src);
+                    src.advance(count);
+                    self.skipped += count as u64;
+                }
+            }
+        }
+    }
+}
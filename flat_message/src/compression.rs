@@ -0,0 +1,139 @@
+use crate::headers::{self, Layout};
+use crate::{Config, Error, VecLike};
+use common::constants;
+use std::mem::size_of;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Lz4,
+}
+
+impl Compression {
+    #[inline(always)]
+    pub(crate) fn magic(&self) -> u32 {
+        match self {
+            Compression::None => constants::MAGIC_V1,
+            Compression::Lz4 => constants::MAGIC_V1_LZ4,
+        }
+    }
+}
+
+/// Compressed data region
+/// | Offset | Type | Observation                                               |
+/// |--------|------|-----------------------------------------------------------|
+/// | +8     | u32  | Size of the uncompressed data region                      |
+/// | +12    | u32  | Size of the compressed data                               |
+/// | +16    | ?    | Compressed data (followed by padding to 4 bytes)          |
+///
+/// The hash table, the offsets and the trailer are not compressed (offsets are
+/// relative to the uncompressed message).
+pub fn compress<V: VecLike>(
+    output: &mut V,
+    compression: Compression,
+    threshold: u32,
+) -> Result<(), Error> {
+    if compression == Compression::None {
+        return Ok(());
+    }
+    let layout = Layout::new(output.as_slice())?;
+    if layout.header.magic != constants::MAGIC_V1 {
+        return Err(Error::CompressedMessage);
+    }
+    if layout.has_flag(constants::FLAG_IS_ENCRYPTED) {
+        return Err(Error::EncryptedMessage);
+    }
+    let header_size = size_of::<headers::HeaderV1>();
+    let data_size = layout.hash_table_offset - header_size;
+    if data_size < threshold as usize {
+        return Ok(());
+    }
+    let buf = output.as_slice();
+    let compressed = lz4_flex::block::compress(&buf[header_size..layout.hash_table_offset]);
+    let compressed_size = compressed.len();
+    // the hash table has to remain aligned to 4 bytes
+    let new_hash_table_offset = (header_size + 8 + compressed_size + 3) & !3;
+    if new_hash_table_offset >= layout.hash_table_offset {
+        // not worth it
+        return Ok(());
+    }
+    let len = buf.len();
+    let tail_size = len - layout.hash_table_offset;
+    let mut message = Vec::with_capacity(new_hash_table_offset + tail_size);
+    message.extend_from_slice(&compression.magic().to_ne_bytes());
+    message.extend_from_slice(&buf[4..header_size]);
+    message.extend_from_slice(&(data_size as u32).to_ne_bytes());
+    message.extend_from_slice(&(compressed_size as u32).to_ne_bytes());
+    message.extend_from_slice(&compressed);
+    message.resize(new_hash_table_offset, 0);
+    message.extend_from_slice(&buf[layout.hash_table_offset..]);
+    headers::update_checksum(&mut message);
+
+    output.clear();
+    output.resize_zero(message.len());
+    output.as_mut_slice().copy_from_slice(&message);
+    Ok(())
+}
+
+/// Returns `true` if the data region of the message is compressed
+#[inline(always)]
+pub fn is_compressed(input: &[u8]) -> bool {
+    input.len() >= 4 && input[..4] == constants::MAGIC_V1_LZ4.to_ne_bytes()
+}
+
+/// Restores the original message (does nothing if the message is not compressed)
+pub fn decompress<V: VecLike>(input: &mut V, config: Config) -> Result<(), Error> {
+    let layout = Layout::new(input.as_slice())?;
+    if layout.header.magic == constants::MAGIC_V1 {
+        return Ok(());
+    }
+    if layout.has_flag(constants::FLAG_IS_ENCRYPTED) {
+        return Err(Error::EncryptedMessage);
+    }
+    let buf = input.as_slice();
+    let len = buf.len();
+    if layout.has_flag(constants::FLAG_HAS_CHECKSUM) {
+        let stored = u32::from_ne_bytes(buf[len - 4..].try_into().unwrap());
+        let computed = common::hashes::crc32(&buf[..len - 4]);
+        if stored != computed {
+            return Err(Error::InvalidChecksum((computed, stored)));
+        }
+    }
+    let header_size = size_of::<headers::HeaderV1>();
+    if layout.hash_table_offset < header_size + 8 {
+        return Err(Error::DecompressionFailed);
+    }
+    let data_size =
+        u32::from_ne_bytes(buf[header_size..header_size + 4].try_into().unwrap()) as usize;
+    let compressed_size =
+        u32::from_ne_bytes(buf[header_size + 4..header_size + 8].try_into().unwrap()) as usize;
+    if header_size + 8 + compressed_size > layout.hash_table_offset {
+        return Err(Error::DecompressionFailed);
+    }
+    let tail_size = len - layout.hash_table_offset;
+    let new_len = header_size + data_size + tail_size;
+    if new_len > config.max_size() as usize {
+        return Err(Error::ExceedMaxSize((new_len as u32, config.max_size())));
+    }
+    if (header_size + data_size) & 3 != 0 {
+        return Err(Error::DecompressionFailed);
+    }
+    let mut message = vec![0u8; new_len];
+    message[..4].copy_from_slice(&constants::MAGIC_V1.to_ne_bytes());
+    message[4..header_size].copy_from_slice(&buf[4..header_size]);
+    let decompressed_size = lz4_flex::block::decompress_into(
+        &buf[header_size + 8..header_size + 8 + compressed_size],
+        &mut message[header_size..header_size + data_size],
+    )
+    .map_err(|_| Error::DecompressionFailed)?;
+    if decompressed_size != data_size {
+        return Err(Error::DecompressionFailed);
+    }
+    message[header_size + data_size..].copy_from_slice(&buf[layout.hash_table_offset..]);
+    headers::update_checksum(&mut message);
+
+    input.clear();
+    input.resize_zero(new_len);
+    input.as_mut_slice().copy_from_slice(&message);
+    Ok(())
+}
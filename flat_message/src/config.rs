@@ -1,17 +1,38 @@
+use crate::Compression;
+use common::constants;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Config {
     max_size: u32,
+    authentication_key: Option<[u8; constants::MAC_SIZE]>,
+    compression: Compression,
+    compression_threshold: u32,
 }
 impl Config {
     #[inline(always)]
     pub fn max_size(&self) -> u32 {
         self.max_size
     }
+    #[inline(always)]
+    pub fn authentication_key(&self) -> Option<&[u8; constants::MAC_SIZE]> {
+        self.authentication_key.as_ref()
+    }
+    #[inline(always)]
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+    #[inline(always)]
+    pub fn compression_threshold(&self) -> u32 {
+        self.compression_threshold
+    }
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             max_size: 16 * 1024 * 1024,
+            authentication_key: None,
+            compression: Compression::None,
+            compression_threshold: 256,
         }
     }
 }
@@ -31,6 +52,21 @@ impl ConfigBuilder {
         self
     }
     #[inline(always)]
+    pub fn authentication_key(mut self, key: [u8; constants::MAC_SIZE]) -> Self {
+        self.config.authentication_key = Some(key);
+        self
+    }
+    #[inline(always)]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+    #[inline(always)]
+    pub fn compression_threshold(mut self, threshold: u32) -> Self {
+        self.config.compression_threshold = threshold;
+        self
+    }
+    #[inline(always)]
     pub fn build(self) -> Config {
         self.config
     }
@@ -0,0 +1,55 @@
+use crate::{Config, VersionSet};
+use common::constants;
+
+/// Options that are decided at runtime when deserializing a message
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+pub struct DeserializeOptions {
+    compatible_versions: Option<VersionSet>,
+    authentication_key: Option<[u8; constants::MAC_SIZE]>,
+}
+impl DeserializeOptions {
+    /// If set, it replaces the `compatible_versions` attribute of the structure
+    #[inline(always)]
+    pub fn compatible_versions(&self) -> Option<&VersionSet> {
+        self.compatible_versions.as_ref()
+    }
+    /// If set, the message must have a valid MAC (`Error::AuthenticationFailed` otherwise)
+    #[inline(always)]
+    pub fn authentication_key(&self) -> Option<&[u8; constants::MAC_SIZE]> {
+        self.authentication_key.as_ref()
+    }
+}
+/// The MAC is checked when deserializing if the configuration has an authentication key
+impl From<Config> for DeserializeOptions {
+    fn from(config: Config) -> Self {
+        Self {
+            compatible_versions: None,
+            authentication_key: config.authentication_key().copied(),
+        }
+    }
+}
+pub struct DeserializeOptionsBuilder {
+    options: DeserializeOptions,
+}
+impl DeserializeOptionsBuilder {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            options: DeserializeOptions::default(),
+        }
+    }
+    #[inline(always)]
+    pub fn compatible_versions(mut self, versions: VersionSet) -> Self {
+        self.options.compatible_versions = Some(versions);
+        self
+    }
+    #[inline(always)]
+    pub fn authentication_key(mut self, key: [u8; constants::MAC_SIZE]) -> Self {
+        self.options.authentication_key = Some(key);
+        self
+    }
+    #[inline(always)]
+    pub fn build(self) -> DeserializeOptions {
+        self.options
+    }
+}
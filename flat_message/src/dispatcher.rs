@@ -0,0 +1,77 @@
+use crate::{Error, FlatMessageOwned, Name, Storage, StructureInformation};
+use std::collections::HashMap;
+
+type Handler<'h, R> = Box<dyn FnMut(&Storage) -> Result<R, Error> + 'h>;
+type Fallback<'h, R> = Box<dyn FnMut(&Storage) -> R + 'h>;
+
+/// Routes serialized messages to the handler registered for their type (based on the name hash
+/// stored in the message, so the types must be serialized with `store_name = true`).
+pub struct Dispatcher<'h, R = ()> {
+    handlers: HashMap<u32, Handler<'h, R>>,
+    fallback: Option<Fallback<'h, R>>,
+}
+
+impl<'h, R> Dispatcher<'h, R> {
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            fallback: None,
+        }
+    }
+    /// Registers a handler for messages of type `T` (a new handler replaces the previous one).
+    /// `T` must store its name (`store_name = true`), which is checked at compile time.
+    pub fn register<T, F>(&mut self, mut handler: F) -> &mut Self
+    where
+        T: FlatMessageOwned,
+        F: FnMut(T) -> R + 'h,
+    {
+        let name = const {
+            match <T as crate::FlatMessage<'static>>::NAME {
+                Some(name) => name,
+                None => panic!("A type that does not store its name can not be dispatched (use store_name = true) !"),
+            }
+        };
+        self.register_raw(name, move |input| Ok(handler(T::deserialize_from(input)?)))
+    }
+    /// Registers a handler that receives the serialized message (useful for types that borrow
+    /// data from the buffer or for messages that have to be decrypted / decompressed first)
+    pub fn register_raw<F>(&mut self, name: Name, handler: F) -> &mut Self
+    where
+        F: FnMut(&Storage) -> Result<R, Error> + 'h,
+    {
+        self.handlers.insert(name.value, Box::new(handler));
+        self
+    }
+    /// Handler for messages with an unknown name (or without a name)
+    pub fn fallback<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(&Storage) -> R + 'h,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+    /// Sends the message to the matching handler. Without a fallback, a message with an unknown
+    /// name returns `Error::UnknownMessage` and one without a name returns `Error::NameNotStored`.
+    pub fn dispatch(&mut self, input: &Storage) -> Result<R, Error> {
+        let info = StructureInformation::try_from(input)?;
+        let handler = info
+            .name()
+            .and_then(|name| self.handlers.get_mut(&name.value));
+        if let Some(handler) = handler {
+            return handler(input);
+        }
+        if let Some(fallback) = self.fallback.as_mut() {
+            return Ok(fallback(input));
+        }
+        match info.name() {
+            Some(name) => Err(Error::UnknownMessage(name.value)),
+            None => Err(Error::NameNotStored),
+        }
+    }
+}
+
+impl<R> Default for Dispatcher<'_, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,101 @@
+use crate::headers::{self, Layout};
+use crate::{Error, VecLike};
+use chacha20poly1305::aead::{AeadCore, AeadInPlace, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use common::constants;
+use std::collections::HashMap;
+use std::mem::size_of;
+
+pub trait KeyProvider {
+    fn key(&self, key_id: u32) -> Option<[u8; 32]>;
+}
+
+impl KeyProvider for HashMap<u32, [u8; 32]> {
+    fn key(&self, key_id: u32) -> Option<[u8; 32]> {
+        self.get(&key_id).copied()
+    }
+}
+
+// everything except for the data region and the encryption info is authenticated
+fn associated_data(buf: &[u8], layout: &Layout, encryption_info_offset: usize, key_id: u32) -> Vec<u8> {
+    let header_size = size_of::<headers::HeaderV1>();
+    let mut aad =
+        Vec::with_capacity(header_size + 4 + encryption_info_offset - layout.hash_table_offset);
+    aad.extend_from_slice(&buf[..header_size]);
+    aad.extend_from_slice(&buf[layout.hash_table_offset..encryption_info_offset]);
+    aad.extend_from_slice(&key_id.to_ne_bytes());
+    aad
+}
+
+pub fn encrypt<V: VecLike>(
+    output: &mut V,
+    key_id: u32,
+    provider: &impl KeyProvider,
+) -> Result<(), Error> {
+    let layout = Layout::new(output.as_slice())?;
+    if layout.has_flag(constants::FLAG_IS_ENCRYPTED) {
+        return Err(Error::EncryptedMessage);
+    }
+    let Some(key) = provider.key(key_id) else {
+        return Err(Error::UnknownEncryptionKey(key_id));
+    };
+    // make room for the encryption info (right before the CRC32 value, if any)
+    let len = output.as_slice().len();
+    let info_offset = len - layout.checksum_size();
+    output.resize_zero(len + constants::ENCRYPTION_INFO_SIZE);
+    let buf = output.as_mut_slice();
+    buf.copy_within(info_offset..len, info_offset + constants::ENCRYPTION_INFO_SIZE);
+    buf[headers::FLAGS_OFFSET] |= constants::FLAG_IS_ENCRYPTED;
+
+    let aad = associated_data(buf, &layout, info_offset, key_id);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let tag = cipher
+        .encrypt_in_place_detached(
+            &nonce,
+            &aad,
+            &mut buf[size_of::<headers::HeaderV1>()..layout.hash_table_offset],
+        )
+        .map_err(|_| Error::EncryptionFailed)?;
+
+    let info = &mut buf[info_offset..info_offset + constants::ENCRYPTION_INFO_SIZE];
+    info[..4].copy_from_slice(&key_id.to_ne_bytes());
+    info[4..16].copy_from_slice(nonce.as_slice());
+    info[16..].copy_from_slice(tag.as_slice());
+    headers::update_checksum(buf);
+    Ok(())
+}
+
+pub fn decrypt<V: VecLike>(input: &mut V, provider: &impl KeyProvider) -> Result<(), Error> {
+    let layout = Layout::new(input.as_slice())?;
+    if !layout.has_flag(constants::FLAG_IS_ENCRYPTED) {
+        return Err(Error::NotEncrypted);
+    }
+    let len = input.as_slice().len();
+    let info_offset = len - layout.checksum_size() - constants::ENCRYPTION_INFO_SIZE;
+    let buf = input.as_mut_slice();
+    let key_id = u32::from_ne_bytes(buf[info_offset..info_offset + 4].try_into().unwrap());
+    let nonce = *Nonce::from_slice(&buf[info_offset + 4..info_offset + 16]);
+    let tag = *Tag::from_slice(&buf[info_offset + 16..info_offset + 32]);
+    let Some(key) = provider.key(key_id) else {
+        return Err(Error::UnknownEncryptionKey(key_id));
+    };
+
+    let aad = associated_data(buf, &layout, info_offset, key_id);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt_in_place_detached(
+            &nonce,
+            &aad,
+            &mut buf[size_of::<headers::HeaderV1>()..layout.hash_table_offset],
+            &tag,
+        )
+        .map_err(|_| Error::DecryptionFailed)?;
+
+    // remove the encryption info and restore the original message
+    buf[headers::FLAGS_OFFSET] &= !constants::FLAG_IS_ENCRYPTED;
+    buf.copy_within(info_offset + constants::ENCRYPTION_INFO_SIZE..len, info_offset);
+    input.resize_zero(len - constants::ENCRYPTION_INFO_SIZE);
+    headers::update_checksum(input.as_mut_slice());
+    Ok(())
+}
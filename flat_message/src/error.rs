@@ -18,6 +18,19 @@ pub enum Error {
     ChecksumNotStored,
     InvalidChecksum((u32, u32)),
     ExceedMaxSize((u32, u32)),
+    AuthenticationFailed,
+    EncryptedMessage,
+    NotEncrypted,
+    UnknownEncryptionKey(u32),
+    EncryptionFailed,
+    DecryptionFailed,
+    CompressedMessage,
+    DecompressionFailed,
+    Io(std::io::ErrorKind),
+    UnalignedBuffer,
+    InvalidArchive,
+    UnknownMessage(u32),
+    SliceNotSupported,
 }
 
 impl fmt::Display for Error {
@@ -71,6 +84,25 @@ impl fmt::Display for Error {
                 "Exceed maximum size (maximum size allowed: {} bytes - but found: {})",
                 max_size, actual
             ),
+            Error::AuthenticationFailed => write!(f, "Message authentication failed (the MAC is missing or was not produced with the provided key) !"),
+            Error::EncryptedMessage => write!(f, "The message is encrypted and has to be decrypted first !"),
+            Error::NotEncrypted => write!(f, "The message is not encrypted !"),
+            Error::UnknownEncryptionKey(key_id) => write!(f, "Unknown encryption key (key id: {})", key_id),
+            Error::EncryptionFailed => write!(f, "Fail to encrypt the message !"),
+            Error::DecryptionFailed => write!(f, "Fail to decrypt the message (invalid key or the message was tampered with) !"),
+            Error::CompressedMessage => write!(f, "The message is compressed and has to be decompressed first (see deserialize_from_storage) !"),
+            Error::DecompressionFailed => write!(f, "Fail to decompress the message (invalid or corrupted compressed data) !"),
+            Error::Io(kind) => write!(f, "I/O error: {}", kind),
+            Error::UnalignedBuffer => write!(f, "The buffer is not aligned to 16 bytes !"),
+            Error::SliceNotSupported => write!(f, "This type can only be deserialized from a Storage object !"),
+            Error::InvalidArchive => write!(f, "Invalid archive (the footer or the index of the archive is invalid) !"),
+            Error::UnknownMessage(name) => write!(f, "No handler registered for messages with name hash: 0x{:08X}", name),
         }
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Io(value.kind())
+    }
+}
@@ -1,8 +1,19 @@
 use crate::error::Error;
+use crate::headers::Layout;
 use crate::metadata::MetaData;
-use crate::{Config, Storage, VecLike};
+use crate::{authentication, compression};
+use crate::{Config, DeserializeOptions, Name, Schema, Storage, VecLike};
 
 pub trait FlatMessage<'a> {
+    /// The name hash of the structure (`None` if the name is not stored in the serialized message)
+    const NAME: Option<Name> = None;
+    /// The version of the structure (`None` if the structure does not have a version)
+    const VERSION: Option<u8> = None;
+    /// The description of the structure (`None` if the type was not generated with
+    /// `#[flat_message]`)
+    fn schema() -> Option<&'static Schema> {
+        None
+    }
     fn metadata(&self) -> &MetaData;
     fn update_metada(&mut self, new: MetaData);
     fn serialize_to<V: VecLike>(&self, output: &mut V, config: Config) -> Result<(), Error>;
@@ -12,4 +23,73 @@ pub trait FlatMessage<'a> {
     unsafe fn deserialize_from_unchecked(input: &'a Storage) -> Result<Self, Error>
     where
         Self: Sized;
+    /// Same as `deserialize_from`, but for a buffer that is not a `Storage` object (for example,
+    /// a memory mapped file). An unaligned buffer is copied into a `Storage` object if the type
+    /// does not borrow from it, otherwise the buffer has to be aligned to 16 bytes
+    /// (`Error::UnalignedBuffer`).
+    fn deserialize_from_slice(input: &'a [u8]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Self::deserialize_from_slice_with_options(input, &DeserializeOptions::default())
+    }
+    /// Same as `deserialize_from`, but with options decided at runtime (for example, the set of
+    /// compatible versions)
+    fn deserialize_from_with_options(
+        input: &'a Storage,
+        options: &DeserializeOptions,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        if let Some(versions) = options.compatible_versions() {
+            let version = Layout::new(input.as_slice())?.header.version;
+            if !versions.contains(version) {
+                return Err(Error::IncompatibleVersion(version));
+            }
+        }
+        if let Some(key) = options.authentication_key() {
+            authentication::verify_mac(key, input.as_slice())?;
+        }
+        Self::deserialize_from(input)
+    }
+    /// Same as `deserialize_from`, but a compressed message is transparently decompressed into
+    /// `buffer` (the result borrows from `buffer` in this case, otherwise from `input`). The
+    /// `max_size` and the authentication key (if any) from `config` are checked as well.
+    fn deserialize_from_storage(
+        input: &'a Storage,
+        buffer: &'a mut Storage,
+        config: Config,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let options = DeserializeOptions::from(config);
+        if !compression::is_compressed(input.as_slice()) {
+            return Self::deserialize_from_with_options(input, &options);
+        }
+        *buffer = Storage::from_buffer(input.as_slice());
+        compression::decompress(buffer, config)?;
+        Self::deserialize_from_with_options(buffer, &options)
+    }
+    /// Types that implement `FlatMessage` manually (and only provide `deserialize_from`) can not
+    /// be deserialized from a slice (`Error::SliceNotSupported`)
+    fn deserialize_from_slice_with_options(
+        _input: &'a [u8],
+        _options: &DeserializeOptions,
+    ) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Err(Error::SliceNotSupported)
+    }
+    /// # Safety
+    /// The buffer has to contain a valid message. If the type borrows from the buffer, the buffer
+    /// has to be aligned to 16 bytes.
+    unsafe fn deserialize_from_slice_unchecked(_input: &'a [u8]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Err(Error::SliceNotSupported)
+    }
 }
@@ -7,6 +7,8 @@ use crate::VecLike;
 use super::Error;
 use super::Name;
 use super::SerDe;
+use super::SerDeCompressed;
+use super::SerDeSlice;
 use common::constants;
 use common::hashes;
 use std::num::NonZeroU64;
@@ -170,6 +172,76 @@ impl FlatMessageBuffer<'_> {
         }
     }
 
+    /// Reads (and decompresses) a field marked with `#[flat_message(compress = "lz4")]`
+    pub fn get_compressed<T>(&self, field_name: Name) -> Option<T>
+    where
+        T: SerDeCompressed,
+    {
+        let hash = (field_name.value & 0xFFFFFF00) | T::TYPE_HASH;
+        T::from_buffer(self.buf, self.find(hash)?)
+    }
+
+    /// Reads a slice field (`&[T]` or `Vec<T>` in the structure) without copying it
+    pub fn get_slice<'a, T>(&'a self, field_name: Name) -> Option<&'a [T]>
+    where
+        T: SerDeSlice<'a>,
+    {
+        let hash = (field_name.value & 0xFFFFFF00) | (T::DATA_FORMAT as u32) | 0x80;
+        T::from_buffer(self.buf, self.find(hash)?)
+    }
+
+    /// The hashes of the fields from the message (sorted, as they are stored in the hash table).
+    /// The low byte of each hash is the type of the field (data format, list and compressed bits).
+    pub fn fields(&self) -> impl Iterator<Item = u32> + '_ {
+        let p = self.buf.as_ptr();
+        (0..self.header.fields_count as usize).map(move |index| unsafe {
+            buffer::read::<u32>(p, self.field_table_offset + index * 4)
+        })
+    }
+
+    // the full hash and the offset of the field with the provided name (the data format is
+    // ignored)
+    #[cfg(feature = "serde")]
+    pub(crate) fn find_name(&self, name: u32) -> Option<(u32, usize)> {
+        let name = name & 0xFFFFFF00;
+        let p = self.buf.as_ptr();
+        let hash_at =
+            |index: usize| unsafe { buffer::read::<u32>(p, self.field_table_offset + index * 4) };
+        // first hash that is not lower than the name (hashes with the same name are adjacent)
+        let mut left = 0usize;
+        let mut right = self.header.fields_count as usize;
+        while left < right {
+            let mid = (left + right) / 2;
+            if hash_at(mid) < name {
+                left = mid + 1;
+            } else {
+                right = mid;
+            }
+        }
+        if left < self.header.fields_count as usize && hash_at(left) & 0xFFFFFF00 == name {
+            Some((hash_at(left), self.index_to_offset(left)))
+        } else {
+            None
+        }
+    }
+
+    // the offset of the field with the provided hash (the hash table is sorted)
+    pub(crate) fn find(&self, hash: u32) -> Option<usize> {
+        let p = self.buf.as_ptr();
+        let mut left = 0usize;
+        let mut right = self.header.fields_count as usize;
+        while left < right {
+            let mid = (left + right) / 2;
+            let k = unsafe { buffer::read::<u32>(p, self.field_table_offset + mid * 4) };
+            match k.cmp(&hash) {
+                std::cmp::Ordering::Equal => return Some(self.index_to_offset(mid)),
+                std::cmp::Ordering::Less => left = mid + 1,
+                std::cmp::Ordering::Greater => right = mid,
+            }
+        }
+        None
+    }
+
     #[inline(always)]
     fn index_to_offset(&self, index: usize) -> usize {
         match self.offset_size {
@@ -186,11 +258,27 @@ impl FlatMessageBuffer<'_> {
     }
 }
 
+impl<'a> FlatMessageBuffer<'a> {
+    pub(crate) fn buffer(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
 impl<'a> TryFrom<&'a Storage> for FlatMessageBuffer<'a> {
     type Error = Error;
 
     fn try_from(buf: &'a Storage) -> Result<Self, Self::Error> {
-        let buf = buf.as_slice();
+        FlatMessageBuffer::try_from(buf.as_slice())
+    }
+}
+
+/// Slices are read in place: if the buffer is not aligned to 16 bytes, lists of values larger
+/// than one byte might not be aligned either (`get_slice` returns `None` in this case, and the
+/// buffer has to be copied into a `Storage` object first)
+impl<'a> TryFrom<&'a [u8]> for FlatMessageBuffer<'a> {
+    type Error = Error;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
         // validate buf length - minimum 8 bytes
         let len = buf.len();
         if len < 8 {
@@ -199,6 +287,9 @@ impl<'a> TryFrom<&'a Storage> for FlatMessageBuffer<'a> {
         let p = buf.as_ptr();
         let header: HeaderV1 = unsafe { buffer::read(p, 0) };
         if header.magic != constants::MAGIC_V1 {
+            if header.magic == constants::MAGIC_V1_LZ4 {
+                return Err(Error::CompressedMessage);
+            }
             return Err(Error::InvalidMagic);
         }
         // now check flags
@@ -208,6 +299,9 @@ impl<'a> TryFrom<&'a Storage> for FlatMessageBuffer<'a> {
             2 => OffsetSize::U32,
             _ => return Err(Error::InvalidOffsetSize),
         };
+        if header.flags & constants::FLAG_IS_ENCRYPTED != 0 {
+            return Err(Error::EncryptedMessage);
+        }
         let mut metadata_size = 0usize;
         if header.flags & constants::FLAG_HAS_CHECKSUM != 0 {
             metadata_size += 4;
@@ -221,6 +315,9 @@ impl<'a> TryFrom<&'a Storage> for FlatMessageBuffer<'a> {
         if header.flags & constants::FLAG_HAS_UNIQUEID != 0 {
             metadata_size += 8;
         }
+        if header.flags & constants::FLAG_HAS_MAC != 0 {
+            metadata_size += constants::MAC_SIZE;
+        }
         if (metadata_size + 8) as usize > len {
             return Err(Error::InvalidSizeToStoreMetaData((
                 len as u32,
@@ -269,6 +366,9 @@ impl<'a> TryFrom<&'a Storage> for FlatMessageBuffer<'a> {
         } else {
             None
         };
+        if header.flags & constants::FLAG_HAS_MAC != 0 {
+            offset += constants::MAC_SIZE;
+        }
         if header.flags & constants::FLAG_HAS_CHECKSUM != 0 {
             let crc = unsafe { buffer::read::<u32>(p, offset) };
             let calculated_crc = hashes::crc32(&buf[..offset]);
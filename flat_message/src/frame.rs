@@ -0,0 +1,190 @@
+use crate::{Config, Error, FlatMessage, Storage, VecLike};
+use common::constants;
+use std::io::{ErrorKind, Read, Write};
+
+pub(crate) const LENGTH_SIZE: usize = 4;
+// length prefix + magic
+pub(crate) const FRAME_HEADER_SIZE: usize = LENGTH_SIZE + 4;
+const READ_CHUNK_SIZE: usize = 0x1000;
+
+pub(crate) enum FrameHeader {
+    // a valid frame of the given size (without the length prefix)
+    Valid(usize),
+    Invalid,
+    Incomplete,
+}
+
+pub(crate) fn parse_frame_header(data: &[u8], config: &Config) -> FrameHeader {
+    if data.len() < FRAME_HEADER_SIZE {
+        return FrameHeader::Incomplete;
+    }
+    let size = u32::from_ne_bytes(data[..LENGTH_SIZE].try_into().unwrap()) as usize;
+    let magic = u32::from_ne_bytes(data[LENGTH_SIZE..FRAME_HEADER_SIZE].try_into().unwrap());
+    let valid_magic = magic == constants::MAGIC_V1 || magic == constants::MAGIC_V1_LZ4;
+    if valid_magic && size >= 8 && size <= config.max_size() as usize {
+        FrameHeader::Valid(size)
+    } else {
+        FrameHeader::Invalid
+    }
+}
+
+// number of bytes to skip (at least one) to reach the next possible frame (a length followed by a magic)
+pub(crate) fn resync_offset(data: &[u8]) -> usize {
+    let magic_v1 = constants::MAGIC_V1.to_ne_bytes();
+    let magic_lz4 = constants::MAGIC_V1_LZ4.to_ne_bytes();
+    let next = data
+        .get(LENGTH_SIZE + 1..)
+        .and_then(|d| d.windows(4).position(|w| w == magic_v1 || w == magic_lz4))
+        .map(|pos| pos + 1);
+    // if no magic was found, keep the last bytes (they might be the start of a new frame)
+    next.unwrap_or_else(|| data.len().saturating_sub(FRAME_HEADER_SIZE - 1).max(1))
+}
+
+/// Frame layout (repeated for every message in the stream)
+/// | Offset | Type | Observation                                    |
+/// |--------|------|------------------------------------------------|
+/// | +0     | u32  | Size of the message                            |
+/// | +4     | ?    | Serialized message (starts with the magic)     |
+pub struct FrameWriter<W: Write> {
+    writer: W,
+    storage: Storage,
+    config: Config,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(writer: W, config: Config) -> Self {
+        Self {
+            writer,
+            storage: Storage::default(),
+            config,
+        }
+    }
+    /// Serializes the message and writes it as a new frame
+    pub fn write<'a, T: FlatMessage<'a>>(&mut self, message: &T) -> Result<(), Error> {
+        message.serialize_to(&mut self.storage, self.config)?;
+        Self::write_frame(&mut self.writer, self.storage.as_slice())
+    }
+    /// Writes an already serialized (and possibly compressed or encrypted) message as a new frame
+    pub fn write_storage(&mut self, storage: &Storage) -> Result<(), Error> {
+        if storage.len() > self.config.max_size() as usize {
+            return Err(Error::ExceedMaxSize((
+                storage.len() as u32,
+                self.config.max_size(),
+            )));
+        }
+        Self::write_frame(&mut self.writer, storage.as_slice())
+    }
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+    fn write_frame(writer: &mut W, message: &[u8]) -> Result<(), Error> {
+        writer.write_all(&(message.len() as u32).to_ne_bytes())?;
+        writer.write_all(message)?;
+        Ok(())
+    }
+}
+
+pub struct FrameReader<R: Read> {
+    reader: R,
+    storage: Storage,
+    config: Config,
+    // bytes read from the stream that were not consumed yet (starting from `start`)
+    buffer: Vec<u8>,
+    start: usize,
+    skipped: u64,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, config: Config) -> Self {
+        Self {
+            reader,
+            storage: Storage::default(),
+            config,
+            buffer: Vec::with_capacity(READ_CHUNK_SIZE),
+            start: 0,
+            skipped: 0,
+        }
+    }
+    /// Reads the next message from the stream (the same storage is reused for every message).
+    /// Returns `Ok(None)` when the stream ends on a frame boundary. Invalid frames (unknown magic
+    /// or a size that exceeds `Config::max_size`) are skipped until the next magic is found.
+    pub fn read(&mut self) -> Result<Option<&Storage>, Error> {
+        loop {
+            if !self.fill(FRAME_HEADER_SIZE)? {
+                if self.available() == 0 {
+                    return Ok(None);
+                }
+                return Err(Error::Io(ErrorKind::UnexpectedEof));
+            }
+            if let FrameHeader::Valid(size) =
+                parse_frame_header(&self.buffer[self.start..], &self.config)
+            {
+                self.read_message(size)?;
+                return Ok(Some(&self.storage));
+            }
+            self.resync();
+        }
+    }
+    /// Number of bytes that were skipped (while resynchronizing) so far
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+    #[inline(always)]
+    fn available(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+    // makes sure that at least `size` bytes are available (returns false if the stream ends)
+    fn fill(&mut self, size: usize) -> Result<bool, Error> {
+        while self.available() < size {
+            if self.start > 0 {
+                self.buffer.drain(..self.start);
+                self.start = 0;
+            }
+            let len = self.buffer.len();
+            self.buffer.resize(len + READ_CHUNK_SIZE, 0);
+            let count = match self.reader.read(&mut self.buffer[len..]) {
+                Ok(0) => {
+                    self.buffer.truncate(len);
+                    return Ok(false);
+                }
+                Ok(count) => count,
+                Err(e) if e.kind() == ErrorKind::Interrupted => 0,
+                Err(e) => {
+                    self.buffer.truncate(len);
+                    return Err(e.into());
+                }
+            };
+            self.buffer.truncate(len + count);
+        }
+        Ok(true)
+    }
+    fn read_message(&mut self, size: usize) -> Result<(), Error> {
+        self.start += LENGTH_SIZE;
+        self.storage.clear();
+        self.storage.resize_zero(size);
+        // whatever was already read, the rest comes directly from the stream
+        let buffered = self.available().min(size);
+        let output = self.storage.as_mut_slice();
+        output[..buffered].copy_from_slice(&self.buffer[self.start..self.start + buffered]);
+        self.start += buffered;
+        self.reader.read_exact(&mut output[buffered..])?;
+        Ok(())
+    }
+    fn resync(&mut self) {
+        let count = resync_offset(&self.buffer[self.start..]);
+        self.start += count;
+        self.skipped += count as u64;
+    }
+}
@@ -1,3 +1,7 @@
+use crate::{buffer, Error};
+use common::constants;
+use std::mem::size_of;
+
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct HeaderV1 {
@@ -6,3 +10,141 @@ pub struct HeaderV1 {
     pub version: u8,
     pub flags: u8,
 }
+
+// offset of the flags field within the header
+pub(crate) const FLAGS_OFFSET: usize = 7;
+
+/// Where the parts of a serialized message are: the data region, the hash table, the offsets
+/// (ref table) and the trailer (timestamp, unique id, name hash, MAC, encryption info and
+/// checksum, in this order, each one present only if its flag is set).
+pub struct Layout {
+    pub header: HeaderV1,
+    /// End of the data region (where the hash table starts)
+    pub hash_table_offset: usize,
+    /// Where the offsets of the fields start (after the hash table)
+    pub ref_table_offset: usize,
+    /// The size of an offset from the ref table (1, 2 or 4 bytes)
+    pub offset_size: usize,
+    /// Where the trailer starts (after the ref table)
+    pub trailer_offset: usize,
+}
+
+impl Layout {
+    /// Checks the header and computes the layout (works for both plain and compressed messages)
+    pub fn new(buf: &[u8]) -> Result<Self, Error> {
+        let len = buf.len();
+        if len < size_of::<HeaderV1>() {
+            return Err(Error::InvalidHeaderLength(len));
+        }
+        let header: HeaderV1 = unsafe { buffer::read(buf.as_ptr(), 0) };
+        if header.magic != constants::MAGIC_V1 && header.magic != constants::MAGIC_V1_LZ4 {
+            return Err(Error::InvalidMagic);
+        }
+        let offset_size = match header.flags & constants::FLAGS_OFFSET_SIZE {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => return Err(Error::InvalidOffsetSize),
+        };
+        let ref_table_size = header.fields_count as usize * offset_size;
+        let mut metadata_size = 0usize;
+        if header.flags & constants::FLAG_HAS_CHECKSUM != 0 {
+            metadata_size += 4;
+        }
+        if header.flags & constants::FLAG_HAS_NAME_HASH != 0 {
+            metadata_size += 4;
+        }
+        if header.flags & constants::FLAG_HAS_TIMESTAMP != 0 {
+            metadata_size += 8;
+        }
+        if header.flags & constants::FLAG_HAS_UNIQUEID != 0 {
+            metadata_size += 8;
+        }
+        if header.flags & constants::FLAG_HAS_MAC != 0 {
+            metadata_size += constants::MAC_SIZE;
+        }
+        if header.flags & constants::FLAG_IS_ENCRYPTED != 0 {
+            metadata_size += constants::ENCRYPTION_INFO_SIZE;
+        }
+        let tables_size = ref_table_size + header.fields_count as usize * 4;
+        let min_size = size_of::<HeaderV1>() + tables_size + metadata_size;
+        if min_size > len {
+            return Err(Error::InvalidSizeToStoreFieldsTable((
+                len as u32,
+                min_size as u32,
+            )));
+        }
+        let hash_table_offset = len - metadata_size - tables_size;
+        Ok(Self {
+            header,
+            hash_table_offset,
+            ref_table_offset: hash_table_offset + header.fields_count as usize * 4,
+            offset_size,
+            trailer_offset: len - metadata_size,
+        })
+    }
+    #[inline(always)]
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.header.flags & flag != 0
+    }
+    /// The offset of the trailer entry for a flag (`FLAG_HAS_TIMESTAMP`, `FLAG_HAS_UNIQUEID`,
+    /// `FLAG_HAS_NAME_HASH`, `FLAG_HAS_MAC`, `FLAG_IS_ENCRYPTED` or `FLAG_HAS_CHECKSUM`) or None
+    /// if the flag is not set.
+    pub fn trailer_entry(&self, flag: u8) -> Option<usize> {
+        if !self.has_flag(flag) {
+            return None;
+        }
+        let mut offset = self.trailer_offset;
+        for (entry, size) in [
+            (constants::FLAG_HAS_TIMESTAMP, 8),
+            (constants::FLAG_HAS_UNIQUEID, 8),
+            (constants::FLAG_HAS_NAME_HASH, 4),
+            (constants::FLAG_HAS_MAC, constants::MAC_SIZE),
+            (
+                constants::FLAG_IS_ENCRYPTED,
+                constants::ENCRYPTION_INFO_SIZE,
+            ),
+            (constants::FLAG_HAS_CHECKSUM, 4),
+        ] {
+            if entry == flag {
+                return Some(offset);
+            }
+            if self.has_flag(entry) {
+                offset += size;
+            }
+        }
+        None
+    }
+    /// The hash and the offset (from the ref table) of the field with the provided index (`buf`
+    /// is the message the layout was computed for)
+    pub fn field(&self, buf: &[u8], index: usize) -> Option<(u32, usize)> {
+        if index >= self.header.fields_count as usize || buf.len() < self.trailer_offset {
+            return None;
+        }
+        let p = buf.as_ptr();
+        let hash = unsafe { buffer::read::<u32>(p, self.hash_table_offset + index * 4) };
+        let pos = self.ref_table_offset + index * self.offset_size;
+        let offset = match self.offset_size {
+            1 => unsafe { buffer::read::<u8>(p, pos) as usize },
+            2 => unsafe { buffer::read::<u16>(p, pos) as usize },
+            _ => unsafe { buffer::read::<u32>(p, pos) as usize },
+        };
+        Some((hash, offset))
+    }
+    #[inline(always)]
+    pub fn checksum_size(&self) -> usize {
+        if self.has_flag(constants::FLAG_HAS_CHECKSUM) {
+            4
+        } else {
+            0
+        }
+    }
+}
+
+pub(crate) fn update_checksum(buf: &mut [u8]) {
+    if buf[FLAGS_OFFSET] & constants::FLAG_HAS_CHECKSUM != 0 {
+        let len = buf.len();
+        let crc = common::hashes::crc32(&buf[..len - 4]);
+        buf[len - 4..].copy_from_slice(&crc.to_ne_bytes());
+    }
+}
@@ -0,0 +1,279 @@
+//! Converts messages to JSON (for logs, debugging or test fixtures) and builds messages from JSON.
+//!
+//! A message is converted into a JSON object with one entry for each field:
+//! * 128 bit integers are stored as strings (they do not fit in a JSON number)
+//! * enums are stored as the name of the variant (or as a number if the variant is not known)
+//! * nested messages (written with `serde_wire`) are stored as JSON objects
+//! * map fields are stored as JSON objects (keys that are not strings are converted to strings)
+//! * bytes fields are stored as lists of numbers (the same as `Vec<u8>` fields)
+use crate::serde_wire::de::Element;
+use crate::serde_wire::ser::{self, MessageInfo};
+use crate::{
+    FieldKind, FieldSchema, FlatMessage, FlatMessageBuffer, NameRegistry, Schema, SerDeCompressed,
+    Storage,
+};
+use common::data_format::DataFormat;
+use serde_json::{Map, Value};
+
+pub use crate::serde_wire::Error;
+
+fn schema<'a, T: FlatMessage<'a>>() -> Result<&'static Schema, Error> {
+    T::schema().ok_or(Error::Unsupported(
+        "A type without a schema (not generated with #[flat_message])",
+    ))
+}
+
+/// Converts a message into a JSON object (the names of the fields and of the enum variants are
+/// taken from the schema of `T`)
+pub fn to_json<'a, T: FlatMessage<'a>>(input: &Storage) -> Result<Value, Error> {
+    let schema = schema::<T>()?;
+    let mut names = NameRegistry::new();
+    names.register_schema(schema);
+    object(&FlatMessageBuffer::try_from(input)?, &names, Some(schema))
+}
+
+/// Same as `to_json`, but the names of the fields are resolved with a `NameRegistry` (fields
+/// with unknown names use their hash as a name, for example `"0x1234AB03"`)
+pub fn to_json_with_names(input: &Storage, names: &NameRegistry) -> Result<Value, Error> {
+    object(&FlatMessageBuffer::try_from(input)?, names, None)
+}
+
+fn object(
+    buffer: &FlatMessageBuffer,
+    names: &NameRegistry,
+    schema: Option<&Schema>,
+) -> Result<Value, Error> {
+    let mut map = Map::new();
+    for hash in buffer.fields() {
+        let Some(pos) = buffer.find(hash) else {
+            continue;
+        };
+        let key = match names.field(hash) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:08X}", hash),
+        };
+        let field = schema.and_then(|s| s.fields.iter().find(|f| f.hash == hash));
+        let element = Element::read(buffer.buffer(), hash, pos)?;
+        map.insert(key, value(element, names, field)?);
+    }
+    Ok(Value::Object(map))
+}
+
+fn number(value: i128) -> Value {
+    match i64::try_from(value) {
+        Ok(value) => Value::from(value),
+        Err(_) => Value::from(value as u64),
+    }
+}
+
+fn value(
+    element: Element,
+    names: &NameRegistry,
+    field: Option<&FieldSchema>,
+) -> Result<Value, Error> {
+    Ok(match element {
+        Element::Bool(v) => Value::from(v),
+        Element::U8(v) => Value::from(v),
+        Element::U16(v) => Value::from(v),
+        Element::U32(v) => Value::from(v),
+        Element::U64(v) => Value::from(v),
+        Element::U128(v) => Value::String(v.to_string()),
+        Element::I8(v) => Value::from(v),
+        Element::I16(v) => Value::from(v),
+        Element::I32(v) => Value::from(v),
+        Element::I64(v) => Value::from(v),
+        Element::I128(v) => Value::String(v.to_string()),
+        Element::F32(v) => Value::from(v),
+        Element::F64(v) => Value::from(v),
+        Element::Str(v) => Value::from(v),
+        Element::String(v) => Value::String(v),
+        Element::Bytes(v) => Value::Array(v.iter().map(|b| Value::from(*b)).collect()),
+        Element::ByteBuf(v) => Value::Array(v.into_iter().map(Value::from).collect()),
+        Element::List(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| value(item, names, field))
+                .collect::<Result<_, _>>()?,
+        ),
+        Element::Object(buffer) => match field {
+            Some(field) if field.kind == FieldKind::Map => map(Element::Object(buffer), names)?,
+            _ => object(&buffer, names, None)?,
+        },
+        Element::Enum(v) => match field.and_then(|f| f.enum_variant(v)) {
+            Some(variant) => Value::from(variant),
+            None => number(v),
+        },
+    })
+}
+
+// map fields are stored as a nested message with a list of keys and a list of values
+fn map(element: Element, names: &NameRegistry) -> Result<Value, Error> {
+    let Some(entries) = element.into_map()? else {
+        return Err(Error::Message(
+            "A map field must have a list of keys and a list of values !".to_string(),
+        ));
+    };
+    let mut map = Map::new();
+    for (key, item) in entries {
+        let key = match value(key, names, None)? {
+            Value::String(key) => key,
+            key => key.to_string(),
+        };
+        map.insert(key, value(item, names, None)?);
+    }
+    Ok(Value::Object(map))
+}
+
+/// Builds a message from a JSON object (with the format produced by `to_json`) and deserializes
+/// it into `T`. The message is written in `storage` (so that `T` can borrow from it).
+pub fn from_json<'a, T: FlatMessage<'a>>(
+    value: &Value,
+    storage: &'a mut Storage,
+) -> Result<T, Error> {
+    let schema = schema::<T>()?;
+    let Some(object) = value.as_object() else {
+        return Err(Error::Message(format!(
+            "Expected a JSON object (found: {}) !",
+            value
+        )));
+    };
+    if let Some(key) = object.keys().find(|key| schema.field(key).is_none()) {
+        return Err(Error::Message(format!(
+            "Unknown field '{}' for structure '{}' !",
+            key, schema.name
+        )));
+    }
+    let mut fields = Vec::with_capacity(schema.fields.len());
+    for field in schema.fields.iter() {
+        if let Some(value) = object.get(field.name) {
+            fields.push((field.name, field_value(field, value)?));
+        }
+    }
+    let info = MessageInfo {
+        name: schema.store_name.then_some(schema.name),
+        version: schema.version,
+        checksum: schema.checksum,
+        authentication_key: None,
+    };
+    *storage = Storage::from_buffer(&ser::build_message(&info, &fields)?);
+    let storage: &'a Storage = storage;
+    Ok(T::deserialize_from(storage)?)
+}
+
+fn integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(n) => n.as_i64().map(i128::from).or(n.as_u64().map(i128::from)),
+        // 128 bit values are stored as strings
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn scalar(data_format: DataFormat, value: &Value) -> Option<ser::Value> {
+    Some(match data_format {
+        DataFormat::U8 => ser::Value::U8(integer(value)?.try_into().ok()?),
+        DataFormat::U16 => ser::Value::U16(integer(value)?.try_into().ok()?),
+        DataFormat::U32 => ser::Value::U32(integer(value)?.try_into().ok()?),
+        DataFormat::U64 => ser::Value::U64(integer(value)?.try_into().ok()?),
+        DataFormat::U128 => ser::Value::U128(match value {
+            Value::String(s) => s.parse().ok()?,
+            _ => integer(value)?.try_into().ok()?,
+        }),
+        DataFormat::I8 => ser::Value::I8(integer(value)?.try_into().ok()?),
+        DataFormat::I16 => ser::Value::I16(integer(value)?.try_into().ok()?),
+        DataFormat::I32 => ser::Value::I32(integer(value)?.try_into().ok()?),
+        DataFormat::I64 => ser::Value::I64(integer(value)?.try_into().ok()?),
+        DataFormat::I128 => ser::Value::I128(integer(value)?),
+        DataFormat::F32 => ser::Value::F32(value.as_f64()? as f32),
+        DataFormat::F64 => ser::Value::F64(value.as_f64()?),
+        DataFormat::Bool => ser::Value::Bool(value.as_bool()?),
+        DataFormat::String => ser::Value::String(value.as_str()?.to_string()),
+        DataFormat::Bytes => ser::Value::Bytes(
+            value
+                .as_array()?
+                .iter()
+                .map(|v| integer(v).and_then(|v| u8::try_from(v).ok()))
+                .collect::<Option<_>>()?,
+        ),
+        _ => return None,
+    })
+}
+
+fn field_value(field: &FieldSchema, value: &Value) -> Result<ser::Value, Error> {
+    let invalid = || {
+        Error::Message(format!(
+            "Invalid value for field '{}' (expected: {}{}, found: {}) !",
+            field.name,
+            field.data_format,
+            if field.kind.is_list() { " list" } else { "" },
+            value
+        ))
+    };
+    if field.kind == FieldKind::Map {
+        // the schema does not store the types of the keys and values
+        return Err(Error::Message(format!(
+            "Map fields can not be built from JSON (field: '{}') !",
+            field.name
+        )));
+    }
+    let list = field.kind.is_list();
+    let items = || value.as_array().ok_or_else(invalid);
+    if field.compressed {
+        let data = match (field.data_format, list) {
+            (DataFormat::String, false) => {
+                String::compress(&value.as_str().ok_or_else(invalid)?.to_string())
+            }
+            (DataFormat::U8, true) => Vec::<u8>::compress(
+                &items()?
+                    .iter()
+                    .map(|v| integer(v).and_then(|v| u8::try_from(v).ok()))
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?,
+            ),
+            (DataFormat::String, true) => Vec::<String>::compress(
+                &items()?
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?,
+            ),
+            _ => return Err(invalid()),
+        };
+        return Ok(ser::Value::Compressed(field.data_format, list, data));
+    }
+    if field.data_format.is_enum() {
+        let variant = |value: &Value| match value {
+            Value::String(name) => field
+                .enum_variants
+                .iter()
+                .find(|(variant, _)| variant == name)
+                .map(|(_, v)| *v),
+            _ => integer(value),
+        };
+        let hash = field.enum_hash.unwrap_or_default();
+        if !list {
+            let value = variant(value).ok_or_else(invalid)?;
+            return Ok(ser::Value::Enum(field.data_format, hash, value));
+        }
+        let values = items()?
+            .iter()
+            .map(variant)
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        return Ok(ser::Value::EnumList(field.data_format, hash, values));
+    }
+    if !list {
+        return scalar(field.data_format, value).ok_or_else(invalid);
+    }
+    let items = items()?;
+    if items.is_empty() {
+        return Ok(ser::Value::EmptyList(field.data_format));
+    }
+    Ok(ser::Value::List(
+        items
+            .iter()
+            .map(|v| scalar(field.data_format, v))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?,
+    ))
+}
@@ -1,17 +1,36 @@
+mod archive;
+pub mod authentication;
 mod buffer;
+#[cfg(feature = "tokio")]
+mod codec;
+pub mod compression;
 mod config;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+mod deserialize_options;
+mod dispatcher;
 mod error;
 mod flat_message;
 mod flat_message_buffer;
+mod frame;
 pub mod headers;
+#[cfg(feature = "json")]
+pub mod json;
+mod message_log;
 mod metadata;
 mod name;
+mod name_registry;
+mod schema;
+mod schema_compatibility;
 mod serde;
+#[cfg(feature = "serde")]
+pub mod serde_wire;
 /// FlatMessage format
 /// |--------|-------------|------|----------------------------------------------------------|
 /// | Offset | Name        | Type | Observation                                              |
 /// |--------|-------------|------|----------------------------------------------------------|
 /// | +0     | Magic       | u32  | always GTH+ver  (GTH\1)                                  |
+/// |        |             |      | high nibble of ver is the codec (GTH\x11 for LZ4)        |
 /// | +4     | FieldsCount | u16  | Can not be more than 0xFFFF                              |
 /// | +6     | Struct Ver  | u8   | Version of the structure                                 |
 /// | +7     | Flags       | u8   | Flags for the structure as follows                       |
@@ -20,6 +39,8 @@ mod serde;
 /// |        |             |      | ...x.... -> Name hash (4 bytes value)                    |
 /// |        |             |      | ....x... -> TimeStamp (8 bytes)                          |
 /// |        |             |      | .....x.. -> UniqueID (8 bytes)                           |
+/// |        |             |      | ......x. -> MAC (32 bytes value)                         |
+/// |        |             |      | .......x -> Encrypted (32 bytes encryption info)         |
 /// |--------|-------------|------|----------------------------------------------------------|
 /// | +8     | Actual data | ?    | Data for all fields                                      |
 /// |--------|-------------|------|----------------------------------------------------------|
@@ -29,6 +50,8 @@ mod serde;
 /// | +?     | TimeStamp   | u64  | TimeStamp (only if TimeStamp flag is set)                |
 /// | +?     | UniqueID    | u64  | UniqueID (only if UniqueID flag is set)                  |
 /// | +?     | Name Hash   | u32  | Hash of the structure name (only if NameHash flag is set)|
+/// | +?     | MAC         | [32] | Keyed BLAKE3 hash of all previous bytes (if MAC is set)  |
+/// | +?     | Encryption  | [32] | Key ID (u32) + Nonce (12 bytes) + Tag (16 bytes)         |
 /// | Last   | CRC32 value | u32  | Last 4 bytes, only if CRC32 flags is set                 |
 /// |--------|-------------|------|----------------------------------------------------------|
 pub mod size;
@@ -36,15 +59,41 @@ mod storage;
 mod structure_information;
 mod builder;
 
+pub use self::archive::Archive;
+pub use self::archive::ArchiveWriter;
+#[cfg(feature = "mmap")]
+pub use self::archive::MmapArchive;
+pub use self::authentication::Verifier;
+#[cfg(feature = "tokio")]
+pub use self::codec::FrameCodec;
+pub use self::compression::Compression;
 pub use self::config::Config;
 pub use self::config::ConfigBuilder;
+#[cfg(feature = "encryption")]
+pub use self::encryption::KeyProvider;
+pub use self::deserialize_options::DeserializeOptions;
+pub use self::deserialize_options::DeserializeOptionsBuilder;
+pub use self::dispatcher::Dispatcher;
 pub use self::error::Error;
 pub use self::flat_message::FlatMessage;
 pub use self::flat_message_buffer::FlatMessageBuffer;
+pub use self::frame::FrameReader;
+pub use self::frame::FrameWriter;
+pub use self::message_log::LogEntry;
+pub use self::message_log::MessageLog;
 pub use self::metadata::MetaData;
 pub use self::metadata::MetaDataBuilder;
 pub use self::name::Name;
+pub use self::name_registry::NameCollision;
+pub use self::name_registry::NameRegistry;
+pub use self::schema::FieldKind;
+pub use self::schema::FieldSchema;
+pub use self::schema::Schema;
+pub use self::schema_compatibility::BreakingChange;
 pub use self::serde::SerDe;
+pub use self::serde::MapView;
+pub use self::serde::SerDeCompressed;
+pub use self::serde::SerDeMapItem;
 pub use self::serde::SerDeSlice;
 pub use self::serde::SerDeVec;
 pub use self::storage::Storage;
@@ -55,6 +104,7 @@ pub use flat_message_proc_macro::*;
 
 pub use common::data_format::DataFormat;
 pub use common::hashes::crc32;
+pub use common::version_set::VersionSet;
 
 pub trait FlatMessageOwned: for<'de> FlatMessage<'de> {}
 impl<T> FlatMessageOwned for T where T: for<'de> FlatMessage<'de> {}
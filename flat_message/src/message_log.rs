@@ -0,0 +1,295 @@
+use crate::frame::{self, FrameHeader};
+use crate::{Config, Error, FlatMessage, Name, Storage, StructureInformation, VecLike};
+use common::constants;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+
+const INDEX_ENTRY_SIZE: usize = 32;
+
+/// Index entry (stored in the sidecar `.idx` file)
+/// | Offset | Type | Observation                                         |
+/// |--------|------|-----------------------------------------------------|
+/// | +0     | u64  | Offset of the frame in the log file                 |
+/// | +8     | u64  | Timestamp (0 if not stored in the message)          |
+/// | +16    | u64  | Unique ID (0 if not stored in the message)          |
+/// | +24    | u32  | Size of the message                                 |
+/// | +28    | u32  | Name hash (0 if not stored in the message)          |
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    offset: u64,
+    timestamp: u64,
+    unique_id: u64,
+    size: u32,
+    name: u32,
+}
+
+impl LogEntry {
+    #[inline(always)]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    #[inline(always)]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+    #[inline(always)]
+    pub fn timestamp(&self) -> Option<u64> {
+        (self.timestamp != 0).then_some(self.timestamp)
+    }
+    #[inline(always)]
+    pub fn unique_id(&self) -> Option<u64> {
+        (self.unique_id != 0).then_some(self.unique_id)
+    }
+    #[inline(always)]
+    pub fn name(&self) -> Option<Name> {
+        (self.name != 0).then_some(Name::new(self.name))
+    }
+    #[inline(always)]
+    fn end(&self) -> u64 {
+        self.offset + frame::LENGTH_SIZE as u64 + self.size as u64
+    }
+    fn to_bytes(self) -> [u8; INDEX_ENTRY_SIZE] {
+        let mut buf = [0u8; INDEX_ENTRY_SIZE];
+        buf[0..8].copy_from_slice(&self.offset.to_ne_bytes());
+        buf[8..16].copy_from_slice(&self.timestamp.to_ne_bytes());
+        buf[16..24].copy_from_slice(&self.unique_id.to_ne_bytes());
+        buf[24..28].copy_from_slice(&self.size.to_ne_bytes());
+        buf[28..32].copy_from_slice(&self.name.to_ne_bytes());
+        buf
+    }
+    fn from_bytes(buf: &[u8]) -> Self {
+        Self {
+            offset: u64::from_ne_bytes(buf[0..8].try_into().unwrap()),
+            timestamp: u64::from_ne_bytes(buf[8..16].try_into().unwrap()),
+            unique_id: u64::from_ne_bytes(buf[16..24].try_into().unwrap()),
+            size: u32::from_ne_bytes(buf[24..28].try_into().unwrap()),
+            name: u32::from_ne_bytes(buf[28..32].try_into().unwrap()),
+        }
+    }
+}
+
+/// Append-only file of framed messages (same layout as `FrameWriter`) with a sidecar index.
+/// When opened, the index is rebuilt for the messages that were not indexed and an incomplete
+/// (or corrupted, if the message has a CRC32) message at the end of the log is removed.
+pub struct MessageLog {
+    log: File,
+    index: File,
+    config: Config,
+    storage: Storage,
+    entries: Vec<LogEntry>,
+    unique_ids: HashMap<u64, usize>,
+    log_size: u64,
+}
+
+impl MessageLog {
+    pub fn open<P: AsRef<Path>>(path: P, config: Config) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut index_path = path.as_os_str().to_owned();
+        index_path.push(".idx");
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        let mut log = MessageLog {
+            log: options.open(path)?,
+            index: options.open(PathBuf::from(index_path))?,
+            config,
+            storage: Storage::default(),
+            entries: Vec::new(),
+            unique_ids: HashMap::new(),
+            log_size: 0,
+        };
+        log.recover()?;
+        Ok(log)
+    }
+    /// Serializes the message and appends it to the log (returns the index of the new entry)
+    pub fn append<'a, T: FlatMessage<'a>>(&mut self, message: &T) -> Result<usize, Error> {
+        let mut storage = std::mem::take(&mut self.storage);
+        let result = message
+            .serialize_to(&mut storage, self.config)
+            .and_then(|_| self.append_storage(&storage));
+        self.storage = storage;
+        result
+    }
+    /// Appends an already serialized message to the log (returns the index of the new entry)
+    pub fn append_storage(&mut self, storage: &Storage) -> Result<usize, Error> {
+        if storage.len() > self.config.max_size() as usize {
+            return Err(Error::ExceedMaxSize((
+                storage.len() as u32,
+                self.config.max_size(),
+            )));
+        }
+        let entry = Self::create_entry(self.log_size, storage)?;
+        self.log.seek(SeekFrom::Start(self.log_size))?;
+        self.log.write_all(&(storage.len() as u32).to_ne_bytes())?;
+        self.log.write_all(storage.as_slice())?;
+        self.log_size = entry.end();
+        self.index.seek(SeekFrom::End(0))?;
+        self.index.write_all(&entry.to_bytes())?;
+        Ok(self.add_entry(entry))
+    }
+    /// Flushes both the log and the index to the disk
+    pub fn sync(&mut self) -> Result<(), Error> {
+        self.log.sync_data()?;
+        self.index.sync_data()?;
+        Ok(())
+    }
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    #[inline(always)]
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+    /// Reads the message described by the entry into the provided storage (positional reads are
+    /// used, so the log can be read from multiple threads at the same time)
+    pub fn read(&self, entry: &LogEntry, output: &mut Storage) -> Result<(), Error> {
+        output.clear();
+        output.resize_zero(entry.size as usize);
+        read_exact_at(
+            &self.log,
+            output.as_mut_slice(),
+            entry.offset + frame::LENGTH_SIZE as u64,
+        )?;
+        Ok(())
+    }
+    /// Iterates over all messages from the log (in the order they were appended)
+    pub fn iter(&self) -> impl Iterator<Item = Result<Storage, Error>> + '_ {
+        self.entries.iter().map(|entry| {
+            let mut storage = Storage::default();
+            self.read(entry, &mut storage)?;
+            Ok(storage)
+        })
+    }
+    pub fn find_by_unique_id(&self, unique_id: u64) -> Option<&LogEntry> {
+        self.unique_ids.get(&unique_id).map(|index| &self.entries[*index])
+    }
+    /// All entries with a timestamp within the provided range (in the order they were appended)
+    pub fn range_by_timestamp<'a, R: RangeBounds<u64> + 'a>(
+        &'a self,
+        range: R,
+    ) -> impl Iterator<Item = &'a LogEntry> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.timestamp().is_some_and(|t| range.contains(&t)))
+    }
+
+    fn create_entry(offset: u64, storage: &Storage) -> Result<LogEntry, Error> {
+        let info = StructureInformation::try_from(storage)?;
+        Ok(LogEntry {
+            offset,
+            timestamp: info.timestamp().unwrap_or(0),
+            unique_id: info.unique_id().unwrap_or(0),
+            size: storage.len() as u32,
+            name: info.name().map(|n| n.value).unwrap_or(0),
+        })
+    }
+    fn add_entry(&mut self, entry: LogEntry) -> usize {
+        let index = self.entries.len();
+        if let Some(unique_id) = entry.unique_id() {
+            self.unique_ids.insert(unique_id, index);
+        }
+        self.entries.push(entry);
+        index
+    }
+    fn recover(&mut self) -> Result<(), Error> {
+        let log_size = self.log.metadata()?.len();
+        // load the index (and drop the entries that do not match the log)
+        let mut data = Vec::new();
+        self.index.seek(SeekFrom::Start(0))?;
+        self.index.read_to_end(&mut data)?;
+        for buf in data.chunks_exact(INDEX_ENTRY_SIZE) {
+            let entry = LogEntry::from_bytes(buf);
+            if entry.offset != self.log_size || entry.end() > log_size {
+                break;
+            }
+            self.log_size = entry.end();
+            self.add_entry(entry);
+        }
+        self.index
+            .set_len((self.entries.len() * INDEX_ENTRY_SIZE) as u64)?;
+        // index the messages that were written after the last indexed one
+        let mut storage = Storage::default();
+        while let Some(size) = self.read_frame(log_size, &mut storage)? {
+            let entry = Self::create_entry(self.log_size, &storage)?;
+            self.index.seek(SeekFrom::End(0))?;
+            self.index.write_all(&entry.to_bytes())?;
+            self.log_size += (frame::LENGTH_SIZE + size) as u64;
+            self.add_entry(entry);
+        }
+        // remove the torn tail (if any)
+        if self.log_size != log_size {
+            self.log.set_len(self.log_size)?;
+        }
+        Ok(())
+    }
+    // reads the frame that starts at `self.log_size` (None if the frame is incomplete or invalid)
+    fn read_frame(&mut self, log_size: u64, storage: &mut Storage) -> Result<Option<usize>, Error> {
+        let remaining = log_size - self.log_size;
+        if remaining < frame::FRAME_HEADER_SIZE as u64 {
+            return Ok(None);
+        }
+        let mut header = [0u8; frame::FRAME_HEADER_SIZE];
+        self.log.seek(SeekFrom::Start(self.log_size))?;
+        self.log.read_exact(&mut header)?;
+        let FrameHeader::Valid(size) = frame::parse_frame_header(&header, &self.config) else {
+            return Ok(None);
+        };
+        if (frame::LENGTH_SIZE + size) as u64 > remaining {
+            return Ok(None);
+        }
+        storage.clear();
+        storage.resize_zero(size);
+        let buf = storage.as_mut_slice();
+        buf[..4].copy_from_slice(&header[frame::LENGTH_SIZE..]);
+        match self.log.read_exact(&mut buf[4..]) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        if buf[crate::headers::FLAGS_OFFSET] & constants::FLAG_HAS_CHECKSUM != 0 {
+            let stored = u32::from_ne_bytes(buf[size - 4..].try_into().unwrap());
+            if common::hashes::crc32(&buf[..size - 4]) != stored {
+                return Ok(None);
+            }
+        }
+        if StructureInformation::try_from(&*storage).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(size))
+    }
+}
+
+// reads from an offset without changing the position of the file (that is shared by `&self`)
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_exact_at(_: &File, _: &mut [u8], _: u64) -> std::io::Result<()> {
+    Err(ErrorKind::Unsupported.into())
+}
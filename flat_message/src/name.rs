@@ -1,3 +1,5 @@
+use std::fmt::{Display, Formatter};
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Name {
     pub value: u32,
@@ -7,3 +9,9 @@ impl Name {
         Self { value }
     }
 }
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:08X}", self.value)
+    }
+}
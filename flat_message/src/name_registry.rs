@@ -0,0 +1,140 @@
+use crate::{Error, FlatMessage, Name, Schema};
+use common::hashes;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Two different names with the same hash (for fields, only the upper 24 bits of the hash are
+/// compared as the low byte is the data format)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameCollision {
+    pub hash: u32,
+    pub existing: String,
+    pub new: String,
+}
+
+impl Display for NameCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Names '{}' and '{}' have the same hash (0x{:08X}) !",
+            self.existing, self.new, self.hash
+        )
+    }
+}
+
+/// Resolves structure names (`Name`) and field hashes back to their names (for example to
+/// print readable errors or to inspect captured messages)
+#[derive(Debug, Default)]
+pub struct NameRegistry {
+    structures: HashMap<u32, String>,
+    fields: HashMap<u32, String>,
+    collisions: Vec<NameCollision>,
+}
+
+impl NameRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(
+        map: &mut HashMap<u32, String>,
+        collisions: &mut Vec<NameCollision>,
+        hash: u32,
+        name: &str,
+    ) {
+        match map.get(&hash) {
+            Some(existing) if existing != name => collisions.push(NameCollision {
+                hash,
+                existing: existing.clone(),
+                new: name.to_string(),
+            }),
+            Some(_) => {}
+            None => {
+                map.insert(hash, name.to_string());
+            }
+        }
+    }
+
+    /// Registers the name of a structure generated with `#[flat_message]` and the names of its
+    /// fields. Panics if `T` does not have a schema (it was not generated with `#[flat_message]`).
+    pub fn register<'a, T: FlatMessage<'a>>(&mut self) -> &mut Self {
+        let Some(schema) = T::schema() else {
+            panic!(
+                "Type '{}' does not have a schema (it was not generated with #[flat_message]) !",
+                std::any::type_name::<T>()
+            );
+        };
+        self.register_schema(schema)
+    }
+
+    pub fn register_schema(&mut self, schema: &Schema) -> &mut Self {
+        Self::insert(
+            &mut self.structures,
+            &mut self.collisions,
+            schema.name_hash,
+            schema.name,
+        );
+        for field in schema.fields.iter() {
+            Self::insert(
+                &mut self.fields,
+                &mut self.collisions,
+                field.hash & 0xFFFFFF00,
+                field.name,
+            );
+        }
+        self
+    }
+
+    /// Registers a name that can be used both for structures and fields
+    pub fn register_name(&mut self, name: &str) -> &mut Self {
+        let hash = hashes::fnv_32(name);
+        Self::insert(&mut self.structures, &mut self.collisions, hash, name);
+        Self::insert(
+            &mut self.fields,
+            &mut self.collisions,
+            hash & 0xFFFFFF00,
+            name,
+        );
+        self
+    }
+
+    /// The name of a structure
+    pub fn name(&self, name: Name) -> Option<&str> {
+        self.structures.get(&name.value).map(String::as_str)
+    }
+
+    /// The name of a field (from the hash table of a message, the data format is ignored)
+    pub fn field(&self, hash: u32) -> Option<&str> {
+        self.fields.get(&(hash & 0xFFFFFF00)).map(String::as_str)
+    }
+
+    /// Names registered with different values but with the same hash
+    pub fn collisions(&self) -> &[NameCollision] {
+        &self.collisions
+    }
+
+    /// The name of a structure (or its hash if the name is unknown) for `Display`
+    pub fn display(&self, name: Name) -> String {
+        match self.name(name) {
+            Some(text) => format!("{} ({})", text, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Same as `error.to_string()`, but with the hashes replaced by the registered names
+    pub fn error_message(&self, error: &Error) -> String {
+        let field = |hash: u32| match self.field(hash) {
+            Some(name) => format!("'{}' (hash: 0x{:08X})", name, hash),
+            None => format!("with hash: 0x{:08X}", hash),
+        };
+        match *error {
+            Error::UnknownHash(hash) => format!("Unknown field {}", field(hash)),
+            Error::FailToDeserialize(hash) => format!("Fail to deserialize field {}", field(hash)),
+            Error::UnknownMessage(hash) => format!(
+                "No handler registered for messages of type {}",
+                self.display(Name::new(hash))
+            ),
+            _ => error.to_string(),
+        }
+    }
+}
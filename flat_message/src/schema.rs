@@ -0,0 +1,153 @@
+use common::data_format::DataFormat;
+use std::fmt::Write;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    Object,
+    Slice,
+    Vector,
+    /// `[T; N]` or `&[T; N]` (stored as a slice)
+    Array,
+    /// `HashMap<K, V>`, `BTreeMap<K, V>` or `MapView` (stored as a nested message with a list of
+    /// keys and a list of values)
+    Map,
+}
+impl FieldKind {
+    #[inline(always)]
+    pub fn is_list(&self) -> bool {
+        !matches!(self, FieldKind::Object | FieldKind::Map)
+    }
+    fn as_str(&self) -> &'static str {
+        match self {
+            FieldKind::Object => "object",
+            FieldKind::Slice => "slice",
+            FieldKind::Vector => "vector",
+            FieldKind::Array => "array",
+            FieldKind::Map => "map",
+        }
+    }
+}
+
+/// Description of a field (as it is stored in the serialized message)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// The hash from the hash table (the low byte is the data format, list and compressed bits)
+    pub hash: u32,
+    pub data_format: DataFormat,
+    pub kind: FieldKind,
+    /// The hash stored by enums (derived with `FlatMessageEnum`) for validation
+    pub enum_hash: Option<u32>,
+    /// The variants (name and value) of enum fields
+    pub enum_variants: &'static [(&'static str, i128)],
+    pub compressed: bool,
+    /// The field must exist in all versions from `since` to `until` (inclusive)
+    pub since: u8,
+    pub until: u8,
+}
+impl FieldSchema {
+    /// The representation of an enum field (`None` if the field is not an enum)
+    pub fn enum_repr(&self) -> Option<&'static str> {
+        match self.data_format {
+            DataFormat::EnumI8 => Some("i8"),
+            DataFormat::EnumI16 => Some("i16"),
+            DataFormat::EnumI32 => Some("i32"),
+            DataFormat::EnumI64 => Some("i64"),
+            DataFormat::EnumU8 => Some("u8"),
+            DataFormat::EnumU16 => Some("u16"),
+            DataFormat::EnumU32 => Some("u32"),
+            DataFormat::EnumU64 => Some("u64"),
+            _ => None,
+        }
+    }
+    /// The name of the variant with the provided value (for enum fields)
+    pub fn enum_variant(&self, value: i128) -> Option<&'static str> {
+        self.enum_variants
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(name, _)| *name)
+    }
+}
+
+/// Description of a structure (generated by `#[flat_message]` as the `SCHEMA` constant)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Schema {
+    pub name: &'static str,
+    pub name_hash: u32,
+    /// 0 if the structure does not have a version
+    pub version: u8,
+    pub store_name: bool,
+    pub metadata: bool,
+    pub checksum: bool,
+    /// Fields sorted by their hash (the order from the hash table)
+    pub fields: &'static [FieldSchema],
+}
+impl Schema {
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+    pub fn to_json(&self) -> String {
+        let mut s = String::with_capacity(128 + self.fields.len() * 160);
+        s.push_str("{\n");
+        s.push_str("  \"name\": ");
+        write_json_string(&mut s, self.name);
+        let _ = write!(s, ",\n  \"name_hash\": {},", self.name_hash);
+        let _ = write!(s, "\n  \"version\": {},", self.version);
+        let _ = write!(s, "\n  \"store_name\": {},", self.store_name);
+        let _ = write!(s, "\n  \"metadata\": {},", self.metadata);
+        let _ = write!(s, "\n  \"checksum\": {},", self.checksum);
+        s.push_str("\n  \"fields\": [");
+        for (idx, field) in self.fields.iter().enumerate() {
+            s.push_str(if idx == 0 { "\n" } else { ",\n" });
+            s.push_str("    { \"name\": ");
+            write_json_string(&mut s, field.name);
+            let _ = write!(
+                s,
+                ", \"hash\": {}, \"data_format\": \"{}\", \"kind\": \"{}\"",
+                field.hash,
+                field.data_format,
+                field.kind.as_str()
+            );
+            if let Some(repr) = field.enum_repr() {
+                let _ = write!(s, ", \"enum_repr\": \"{}\"", repr);
+            }
+            if let Some(enum_hash) = field.enum_hash {
+                let _ = write!(s, ", \"enum_hash\": {}", enum_hash);
+            }
+            if !field.enum_variants.is_empty() {
+                s.push_str(", \"enum_variants\": [");
+                for (idx, (variant, value)) in field.enum_variants.iter().enumerate() {
+                    s.push_str(if idx == 0 { "[" } else { ", [" });
+                    write_json_string(&mut s, variant);
+                    let _ = write!(s, ", {}]", value);
+                }
+                s.push(']');
+            }
+            let _ = write!(
+                s,
+                ", \"compressed\": {}, \"since\": {}, \"until\": {} }}",
+                field.compressed, field.since, field.until
+            );
+        }
+        if !self.fields.is_empty() {
+            s.push_str("\n  ");
+        }
+        s.push_str("]\n}");
+        s
+    }
+}
+
+fn write_json_string(s: &mut String, value: &str) {
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(s, "\\u{:04x}", c as u32);
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+}
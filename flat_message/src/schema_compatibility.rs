@@ -0,0 +1,202 @@
+use crate::{DataFormat, FieldKind, FieldSchema, Schema};
+use std::fmt::{Display, Formatter};
+
+/// A change between two versions of a structure that breaks the wire compatibility
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakingChange {
+    /// The name of the structure changed (the name hash stored in the message is different)
+    NameChanged {
+        old: String,
+        new: String,
+    },
+    VersionDecreased {
+        old: u8,
+        new: u8,
+    },
+    /// The fields changed but the version is the same
+    VersionNotIncremented {
+        version: u8,
+    },
+    /// A field that older messages require is no longer serialized
+    FieldRemoved {
+        field: String,
+    },
+    /// A new field that is required when reading older messages
+    FieldAdded {
+        field: String,
+    },
+    /// A field was removed and another one with the same type was added (the hash is different)
+    FieldRenamed {
+        old: String,
+        new: String,
+    },
+    DataFormatChanged {
+        field: String,
+        old: DataFormat,
+        new: DataFormat,
+    },
+    KindChanged {
+        field: String,
+        old: FieldKind,
+        new: FieldKind,
+    },
+    /// The enum representation, name or (for sealed enums) the list of variants changed
+    EnumHashChanged {
+        field: String,
+    },
+    CompressionChanged {
+        field: String,
+    },
+}
+
+impl Display for BreakingChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakingChange::NameChanged { old, new } => {
+                write!(f, "Structure was renamed from '{}' to '{}' !", old, new)
+            }
+            BreakingChange::VersionDecreased { old, new } => {
+                write!(f, "Version decreased from {} to {} !", old, new)
+            }
+            BreakingChange::VersionNotIncremented { version } => {
+                write!(
+                    f,
+                    "Fields were changed but the version ({}) was not incremented !",
+                    version
+                )
+            }
+            BreakingChange::FieldRemoved { field } => {
+                write!(f, "Required field '{}' was removed !", field)
+            }
+            BreakingChange::FieldAdded { field } => write!(
+                f,
+                "Field '{}' was added but it is required for older versions (use 'since') !",
+                field
+            ),
+            BreakingChange::FieldRenamed { old, new } => {
+                write!(f, "Field '{}' was renamed to '{}' !", old, new)
+            }
+            BreakingChange::DataFormatChanged { field, old, new } => write!(
+                f,
+                "The data format of field '{}' changed from {} to {} !",
+                field, old, new
+            ),
+            BreakingChange::KindChanged { field, old, new } => {
+                write!(f, "Field '{}' changed from {:?} to {:?} !", field, old, new)
+            }
+            BreakingChange::EnumHashChanged { field } => write!(
+                f,
+                "The enum used by field '{}' changed (name, representation or variants) !",
+                field
+            ),
+            BreakingChange::CompressionChanged { field } => {
+                write!(f, "The compression of field '{}' changed !", field)
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Lists the changes from `self` (the old version of a structure) to `new` that break the
+    /// wire compatibility (messages of one version can not be read by the other one)
+    pub fn breaking_changes(&self, new: &Schema) -> Vec<BreakingChange> {
+        let mut changes = Vec::new();
+        if self.name_hash != new.name_hash && self.store_name && new.store_name {
+            changes.push(BreakingChange::NameChanged {
+                old: self.name.to_string(),
+                new: new.name.to_string(),
+            });
+        }
+        // fields that exist in both versions
+        let mut fields_changed = false;
+        for old_field in self.fields.iter() {
+            let Some(new_field) = new.field(old_field.name) else {
+                continue;
+            };
+            let field = || old_field.name.to_string();
+            if old_field.kind != new_field.kind {
+                changes.push(BreakingChange::KindChanged {
+                    field: field(),
+                    old: old_field.kind,
+                    new: new_field.kind,
+                });
+            }
+            if old_field.data_format != new_field.data_format {
+                changes.push(BreakingChange::DataFormatChanged {
+                    field: field(),
+                    old: old_field.data_format,
+                    new: new_field.data_format,
+                });
+            } else if old_field.enum_hash != new_field.enum_hash {
+                changes.push(BreakingChange::EnumHashChanged { field: field() });
+            }
+            if old_field.compressed != new_field.compressed {
+                changes.push(BreakingChange::CompressionChanged { field: field() });
+            }
+            fields_changed |= old_field != new_field;
+        }
+        // removed and added fields (a removed field and an added one with the same type are
+        // reported as a rename)
+        let removed: Vec<_> = self
+            .fields
+            .iter()
+            .filter(|f| new.field(f.name).is_none())
+            .collect();
+        let mut added: Vec<_> = new
+            .fields
+            .iter()
+            .filter(|f| self.field(f.name).is_none())
+            .collect();
+        fields_changed |= !removed.is_empty() || !added.is_empty();
+        for old_field in removed {
+            let removal_breaks = is_required(old_field, new.version);
+            // prefer a field that also kept the same versions
+            let rename = added
+                .iter()
+                .position(|f| {
+                    same_type(old_field, f)
+                        && (old_field.since, old_field.until) == (f.since, f.until)
+                })
+                .or_else(|| added.iter().position(|f| same_type(old_field, f)))
+                .filter(|index| removal_breaks || is_required(added[*index], self.version));
+            if let Some(index) = rename {
+                changes.push(BreakingChange::FieldRenamed {
+                    old: old_field.name.to_string(),
+                    new: added.remove(index).name.to_string(),
+                });
+            } else if removal_breaks {
+                changes.push(BreakingChange::FieldRemoved {
+                    field: old_field.name.to_string(),
+                });
+            }
+        }
+        for new_field in added {
+            if is_required(new_field, self.version) {
+                changes.push(BreakingChange::FieldAdded {
+                    field: new_field.name.to_string(),
+                });
+            }
+        }
+        // version rules
+        if new.version < self.version {
+            changes.push(BreakingChange::VersionDecreased {
+                old: self.version,
+                new: new.version,
+            });
+        } else if fields_changed && new.version == self.version && new.version != 0 {
+            changes.push(BreakingChange::VersionNotIncremented {
+                version: new.version,
+            });
+        }
+        changes
+    }
+}
+
+// the field has to exist in messages with the provided version
+fn is_required(field: &FieldSchema, version: u8) -> bool {
+    (field.since..=field.until).contains(&version.max(1))
+}
+
+fn same_type(a: &FieldSchema, b: &FieldSchema) -> bool {
+    a.hash & 0xFF == b.hash & 0xFF && a.enum_hash == b.enum_hash
+}
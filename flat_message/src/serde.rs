@@ -1,14 +1,27 @@
 use common::data_format::DataFormat;
 
+mod arrays;
 mod basic_types;
 mod bool;
 mod buffers;
 mod buffers_8bits;
+mod bytes;
+mod compressed;
+mod maps;
+mod size_types;
 mod string;
 mod string_lists;
+mod validated_types;
+
+pub use self::maps::MapView;
+pub use self::maps::SerDeMapItem;
 
 pub unsafe trait SerDe<'a> {
     const DATA_FORMAT: DataFormat;
+    /// The hash stored by enums (derived with `FlatMessageEnum`) to validate their values
+    const ENUM_HASH: Option<u32> = None;
+    /// The variants (name and value) of enums derived with `FlatMessageEnum`
+    const ENUM_VARIANTS: &'static [(&'static str, i128)] = &[];
     unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self
     where
         Self: Sized;
@@ -50,3 +63,11 @@ pub unsafe trait SerDeVec<'a> {
     where
         Self: Sized;
 }
+
+/// Fields marked with `#[flat_message(compress = "lz4")]` (always deserialized into an owned value)
+pub trait SerDeCompressed: Sized {
+    // the low byte of the field hash (data format, vector and compressed bits)
+    const TYPE_HASH: u32;
+    fn compress(obj: &Self) -> Vec<u8>;
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self>;
+}
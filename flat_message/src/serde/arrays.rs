@@ -0,0 +1,47 @@
+use super::{SerDe, SerDeSlice};
+use common::data_format::DataFormat;
+
+/// Fixed size arrays are stored as slices (the same format as `&[T]` and `Vec<T>`). The length
+/// is validated when the array is deserialized.
+unsafe impl<'a, T: SerDeSlice<'a> + Copy + 'a, const N: usize> SerDe<'a> for [T; N] {
+    const DATA_FORMAT: DataFormat = T::DATA_FORMAT;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        let slice = T::from_buffer_unchecked(buf, pos);
+        unsafe { std::ptr::read_unaligned(slice.as_ptr() as *const [T; N]) }
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        T::from_buffer(buf, pos)?.try_into().ok()
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &[T; N], p: *mut u8, pos: usize) -> usize {
+        T::write(obj.as_slice(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &[T; N]) -> usize {
+        T::size(obj.as_slice())
+    }
+}
+
+/// Borrowed arrays are read in place (slices are aligned in the serialized message)
+unsafe impl<'a, T: SerDeSlice<'a> + 'a, const N: usize> SerDe<'a> for &'a [T; N] {
+    const DATA_FORMAT: DataFormat = T::DATA_FORMAT;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        let slice = T::from_buffer_unchecked(buf, pos);
+        unsafe { &*(slice.as_ptr() as *const [T; N]) }
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        T::from_buffer(buf, pos)?.try_into().ok()
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &&'a [T; N], p: *mut u8, pos: usize) -> usize {
+        T::write(obj.as_slice(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &&'a [T; N]) -> usize {
+        T::size(obj.as_slice())
+    }
+}
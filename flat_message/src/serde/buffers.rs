@@ -18,7 +18,14 @@ macro_rules! IMPLEMENT_SERDE_FOR_SLICE {
                 let (count, size_len) =
                     size::read(buf.as_ptr(), pos, buf.len(), size::Format::$align_method)?;
                 let end = pos + size_len + count * std::mem::size_of::<$t>();
-                if end > buf.len() {
+                // the buffer might not be aligned (if it is not a Storage object)
+                if end > buf.len()
+                    || !buf
+                        .as_ptr()
+                        .wrapping_add(pos + size_len)
+                        .cast::<$t>()
+                        .is_aligned()
+                {
                     None
                 } else {
                     Some(unsafe {
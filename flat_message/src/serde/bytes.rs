@@ -0,0 +1,107 @@
+use super::SerDe;
+use crate::size;
+use common::data_format::DataFormat;
+use std::borrow::Cow;
+
+/// Bytes fields (`DataFormat::Bytes`) are stored as a `u32` length followed by the data. The
+/// length always has 4 bytes: a field aligned with `#[flat_message(align = N)]` starts 4 bytes
+/// before a multiple of `N` (so that the data is aligned).
+const LENGTH_FORMAT: size::Format = size::Format::U32;
+const BYTES_LENGTH_SIZE: usize = 4;
+
+/// Implementation for &[u8] (read in place)
+unsafe impl<'a> SerDe<'a> for &'a [u8] {
+    const DATA_FORMAT: DataFormat = DataFormat::Bytes;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        let p = buf.as_ptr();
+        let (len, slen) = size::read_unchecked(p, pos, LENGTH_FORMAT);
+        std::slice::from_raw_parts(p.add(pos + slen), len)
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        let (len, slen) = size::read(buf.as_ptr(), pos, buf.len(), LENGTH_FORMAT)?;
+        buf.get(pos + slen..(pos + slen).checked_add(len)?)
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &&'a [u8], p: *mut u8, pos: usize) -> usize {
+        unsafe {
+            let slen = size::write(p, pos, obj.len() as u32, LENGTH_FORMAT);
+            std::ptr::copy_nonoverlapping(obj.as_ptr(), p.add(pos + slen), obj.len());
+            pos + slen + obj.len()
+        }
+    }
+    #[inline(always)]
+    fn size(obj: &&'a [u8]) -> usize {
+        BYTES_LENGTH_SIZE + obj.len()
+    }
+}
+
+/// Implementation for Vec<u8> (only for fields marked with `#[flat_message(kind = bytes)]`,
+/// otherwise `Vec<u8>` is a list of `u8` values)
+unsafe impl SerDe<'_> for Vec<u8> {
+    const DATA_FORMAT: DataFormat = DataFormat::Bytes;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Self {
+        let v: &[u8] = SerDe::from_buffer_unchecked(buf, pos);
+        v.to_vec()
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+        let v: &[u8] = SerDe::from_buffer(buf, pos)?;
+        Some(v.to_vec())
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &Vec<u8>, p: *mut u8, pos: usize) -> usize {
+        SerDe::write(&obj.as_slice(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &Vec<u8>) -> usize {
+        BYTES_LENGTH_SIZE + obj.len()
+    }
+}
+
+/// Implementation for Cow<[u8]> (deserialized as a borrowed slice)
+unsafe impl<'a> SerDe<'a> for Cow<'a, [u8]> {
+    const DATA_FORMAT: DataFormat = DataFormat::Bytes;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        Cow::Borrowed(SerDe::from_buffer_unchecked(buf, pos))
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        Some(Cow::Borrowed(SerDe::from_buffer(buf, pos)?))
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &Cow<'a, [u8]>, p: *mut u8, pos: usize) -> usize {
+        SerDe::write(&obj.as_ref(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &Cow<'a, [u8]>) -> usize {
+        BYTES_LENGTH_SIZE + obj.len()
+    }
+}
+
+/// Implementation for bytes::Bytes (the data is copied when the field is deserialized)
+#[cfg(feature = "bytes")]
+unsafe impl SerDe<'_> for bytes::Bytes {
+    const DATA_FORMAT: DataFormat = DataFormat::Bytes;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Self {
+        let v: &[u8] = SerDe::from_buffer_unchecked(buf, pos);
+        bytes::Bytes::copy_from_slice(v)
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+        let v: &[u8] = SerDe::from_buffer(buf, pos)?;
+        Some(bytes::Bytes::copy_from_slice(v))
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &bytes::Bytes, p: *mut u8, pos: usize) -> usize {
+        SerDe::write(&obj.as_ref(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &bytes::Bytes) -> usize {
+        BYTES_LENGTH_SIZE + obj.len()
+    }
+}
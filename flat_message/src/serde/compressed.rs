@@ -0,0 +1,77 @@
+use super::{SerDeCompressed, SerDeVec};
+use crate::size;
+use common::data_format::DataFormat;
+
+const SIZE_FORMAT: size::Format = size::Format::U8withExtension;
+const COMPRESSED: u32 = 0x40;
+const VECTOR: u32 = 0x80;
+// LZ4 can not expand a block more than 255 times
+const MAX_RATIO: usize = 255;
+
+// | uncompressed size | compressed size | LZ4 block |
+fn compress(data: &[u8]) -> Vec<u8> {
+    let compressed = lz4_flex::block::compress(data);
+    let mut v = vec![0u8; 10 + compressed.len()];
+    let p = v.as_mut_ptr();
+    let mut pos = unsafe { size::write(p, 0, data.len() as u32, SIZE_FORMAT) };
+    pos += unsafe { size::write(p, pos, compressed.len() as u32, SIZE_FORMAT) };
+    v[pos..pos + compressed.len()].copy_from_slice(&compressed);
+    v.truncate(pos + compressed.len());
+    v
+}
+
+fn decompress(buf: &[u8], pos: usize) -> Option<Vec<u8>> {
+    let (len, slen) = size::read(buf.as_ptr(), pos, buf.len(), SIZE_FORMAT)?;
+    let pos = pos + slen;
+    let (compressed_len, slen) = size::read(buf.as_ptr(), pos, buf.len(), SIZE_FORMAT)?;
+    let pos = pos + slen;
+    let end = pos + compressed_len;
+    if end > buf.len() || len > compressed_len * MAX_RATIO {
+        return None;
+    }
+    let mut output = vec![0u8; len];
+    let decompressed_len = lz4_flex::block::decompress_into(&buf[pos..end], &mut output).ok()?;
+    if decompressed_len != len {
+        return None;
+    }
+    Some(output)
+}
+
+impl SerDeCompressed for String {
+    const TYPE_HASH: u32 = DataFormat::String as u32 | COMPRESSED;
+    #[inline(always)]
+    fn compress(obj: &String) -> Vec<u8> {
+        compress(obj.as_bytes())
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+        String::from_utf8(decompress(buf, pos)?).ok()
+    }
+}
+
+impl SerDeCompressed for Vec<u8> {
+    const TYPE_HASH: u32 = DataFormat::U8 as u32 | VECTOR | COMPRESSED;
+    #[inline(always)]
+    fn compress(obj: &Vec<u8>) -> Vec<u8> {
+        compress(obj.as_slice())
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+        decompress(buf, pos)
+    }
+}
+
+impl SerDeCompressed for Vec<String> {
+    const TYPE_HASH: u32 = DataFormat::String as u32 | VECTOR | COMPRESSED;
+    fn compress(obj: &Vec<String>) -> Vec<u8> {
+        // compress the regular (uncompressed) representation of the list
+        let mut raw = vec![0u8; SerDeVec::size(obj)];
+        unsafe { SerDeVec::write(obj, raw.as_mut_ptr(), 0) };
+        compress(&raw)
+    }
+    fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+        let raw = decompress(buf, pos)?;
+        <String as SerDeVec>::from_buffer(&raw, 0)
+    }
+}
+
@@ -0,0 +1,584 @@
+use super::{SerDe, SerDeSlice, SerDeVec};
+use crate::{headers, size, FlatMessageBuffer};
+use common::data_format::DataFormat;
+use common::hashes;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16,
+    NonZeroU32, NonZeroU64, NonZeroU8,
+};
+
+/// Maps are stored as a nested message (`DataFormat::GenericObject`) with two lists: `keys` and
+/// `values` (the same layout used by `serde_wire` for maps):
+/// | Offset | Type | Observation                                     |
+/// |--------|------|-------------------------------------------------|
+/// | +0     | u32  | Size of the message                             |
+/// | +16    | ?    | The message (with the `keys` and `values` list) |
+///
+/// The field is aligned to 16 bytes (so that the nested message can be read in place).
+const MESSAGE_OFFSET: usize = 16;
+const KEYS: &str = "keys";
+const VALUES: &str = "values";
+const STRINGS_SIZE_FORMAT: size::Format = size::Format::U8withExtension;
+
+/// Types that can be used as keys or values of `HashMap` and `BTreeMap` fields (they are stored
+/// as lists). Implemented for numbers, `bool`, `char`, the `NonZero` types, `String`, `&str` and
+/// enums derived with `FlatMessageEnum`.
+///
+/// # Safety
+/// `write_list` must write exactly `list_size` bytes (the buffer is allocated based on it).
+pub unsafe trait SerDeMapItem<'a>: Sized {
+    const DATA_FORMAT: DataFormat;
+    fn list_size<'b>(items: impl ExactSizeIterator<Item = &'b Self>) -> usize
+    where
+        Self: 'b;
+    /// # Safety
+    /// `p` must have at least `pos + list_size(items)` bytes.
+    unsafe fn write_list<'b>(
+        items: impl ExactSizeIterator<Item = &'b Self>,
+        p: *mut u8,
+        pos: usize,
+    ) -> usize
+    where
+        Self: 'b;
+    fn read_list(buf: &'a [u8], pos: usize) -> Option<Vec<Self>>;
+    /// # Safety
+    /// The list must be valid (it was written by `write_list` or validated by `read_list`).
+    unsafe fn read_list_unchecked(buf: &'a [u8], pos: usize) -> Vec<Self>;
+}
+
+// values stored in place (the same layout as `SerDeSlice`)
+macro_rules! IMPLEMENT_SERDE_MAP_ITEM {
+    ($t:ty, $size_format:ident) => {
+        unsafe impl<'a> SerDeMapItem<'a> for $t {
+            const DATA_FORMAT: DataFormat = <$t as SerDeSlice<'a>>::DATA_FORMAT;
+            #[inline(always)]
+            fn list_size<'b>(items: impl ExactSizeIterator<Item = &'b Self>) -> usize {
+                size::list_len::<$t>(items.len(), size::Format::$size_format)
+            }
+            #[inline(always)]
+            unsafe fn write_list<'b>(
+                items: impl ExactSizeIterator<Item = &'b Self>,
+                p: *mut u8,
+                pos: usize,
+            ) -> usize {
+                size::write_list(items, p, pos, size::Format::$size_format)
+            }
+            #[inline(always)]
+            fn read_list(buf: &'a [u8], pos: usize) -> Option<Vec<Self>> {
+                <$t as SerDeSlice>::from_buffer(buf, pos).map(|items| items.to_vec())
+            }
+            #[inline(always)]
+            unsafe fn read_list_unchecked(buf: &'a [u8], pos: usize) -> Vec<Self> {
+                <$t as SerDeSlice>::from_buffer_unchecked(buf, pos).to_vec()
+            }
+        }
+    };
+}
+
+IMPLEMENT_SERDE_MAP_ITEM!(u8, U8withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(i8, U8withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(bool, U8withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroU8, U8withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroI8, U8withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(u16, U16withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(i16, U16withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroU16, U16withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroI16, U16withExtension);
+IMPLEMENT_SERDE_MAP_ITEM!(u32, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(i32, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(f32, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(char, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroU32, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroI32, U32);
+IMPLEMENT_SERDE_MAP_ITEM!(u64, U32on64bits);
+IMPLEMENT_SERDE_MAP_ITEM!(i64, U32on64bits);
+IMPLEMENT_SERDE_MAP_ITEM!(f64, U32on64bits);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroU64, U32on64bits);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroI64, U32on64bits);
+IMPLEMENT_SERDE_MAP_ITEM!(u128, U32on128bits);
+IMPLEMENT_SERDE_MAP_ITEM!(i128, U32on128bits);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroU128, U32on128bits);
+IMPLEMENT_SERDE_MAP_ITEM!(NonZeroI128, U32on128bits);
+
+// the same layout as `SerDeVec` for `&str`
+fn strings_size<'b>(items: impl ExactSizeIterator<Item = &'b str>) -> usize {
+    let count = items.len() as u32;
+    items.fold(size::len(count, STRINGS_SIZE_FORMAT), |total, s| {
+        total + size::len(s.len() as u32, STRINGS_SIZE_FORMAT) + s.len()
+    })
+}
+
+unsafe fn write_strings<'b>(
+    items: impl ExactSizeIterator<Item = &'b str>,
+    p: *mut u8,
+    pos: usize,
+) -> usize {
+    let mut pos = pos + size::write(p, pos, items.len() as u32, STRINGS_SIZE_FORMAT);
+    for s in items {
+        pos += size::write(p, pos, s.len() as u32, STRINGS_SIZE_FORMAT);
+        std::ptr::copy_nonoverlapping(s.as_ptr(), p.add(pos), s.len());
+        pos += s.len();
+    }
+    pos
+}
+
+unsafe impl<'a> SerDeMapItem<'a> for &'a str {
+    const DATA_FORMAT: DataFormat = DataFormat::String;
+    #[inline(always)]
+    fn list_size<'b>(items: impl ExactSizeIterator<Item = &'b Self>) -> usize
+    where
+        Self: 'b,
+    {
+        strings_size(items.copied())
+    }
+    #[inline(always)]
+    unsafe fn write_list<'b>(
+        items: impl ExactSizeIterator<Item = &'b Self>,
+        p: *mut u8,
+        pos: usize,
+    ) -> usize
+    where
+        Self: 'b,
+    {
+        write_strings(items.copied(), p, pos)
+    }
+    #[inline(always)]
+    fn read_list(buf: &'a [u8], pos: usize) -> Option<Vec<Self>> {
+        <&str as SerDeVec>::from_buffer(buf, pos)
+    }
+    #[inline(always)]
+    unsafe fn read_list_unchecked(buf: &'a [u8], pos: usize) -> Vec<Self> {
+        <&str as SerDeVec>::from_buffer_unchecked(buf, pos)
+    }
+}
+
+unsafe impl<'a> SerDeMapItem<'a> for String {
+    const DATA_FORMAT: DataFormat = DataFormat::String;
+    #[inline(always)]
+    fn list_size<'b>(items: impl ExactSizeIterator<Item = &'b Self>) -> usize
+    where
+        Self: 'b,
+    {
+        strings_size(items.map(String::as_str))
+    }
+    #[inline(always)]
+    unsafe fn write_list<'b>(
+        items: impl ExactSizeIterator<Item = &'b Self>,
+        p: *mut u8,
+        pos: usize,
+    ) -> usize
+    where
+        Self: 'b,
+    {
+        write_strings(items.map(String::as_str), p, pos)
+    }
+    #[inline(always)]
+    fn read_list(buf: &'a [u8], pos: usize) -> Option<Vec<Self>> {
+        <String as SerDeVec>::from_buffer(buf, pos)
+    }
+    #[inline(always)]
+    unsafe fn read_list_unchecked(buf: &'a [u8], pos: usize) -> Vec<Self> {
+        <String as SerDeVec>::from_buffer_unchecked(buf, pos)
+    }
+}
+
+#[inline(always)]
+fn align_to(pos: usize, alignment: usize) -> usize {
+    (pos + alignment - 1) & !(alignment - 1)
+}
+
+/// The layout of the nested message (the position of the two lists and its size)
+struct Layout {
+    keys: usize,
+    values: usize,
+    data_size: usize,
+    offset_size: usize,
+    size: usize,
+}
+
+impl Layout {
+    fn new(
+        keys_format: DataFormat,
+        keys_size: usize,
+        values_format: DataFormat,
+        values_size: usize,
+    ) -> Self {
+        let keys = align_to(8, keys_format.alignament() as usize);
+        let values = align_to(keys + keys_size, values_format.alignament() as usize);
+        let data_size = values + values_size;
+        let offset_size = if data_size < 0x100 {
+            1
+        } else if data_size < 0x10000 {
+            2
+        } else {
+            4
+        };
+        Layout {
+            keys,
+            values,
+            data_size,
+            offset_size,
+            size: align_to(data_size, 4) + 2 * (4 + offset_size),
+        }
+    }
+}
+
+fn map_size<'a, 'b, K, V>(entries: impl ExactSizeIterator<Item = (&'b K, &'b V)> + Clone) -> usize
+where
+    K: SerDeMapItem<'a> + 'b,
+    V: SerDeMapItem<'a> + 'b,
+{
+    let layout = Layout::new(
+        K::DATA_FORMAT,
+        K::list_size(entries.clone().map(|(k, _)| k)),
+        V::DATA_FORMAT,
+        V::list_size(entries.map(|(_, v)| v)),
+    );
+    MESSAGE_OFFSET + layout.size
+}
+
+unsafe fn write_map<'a, 'b, K, V>(
+    entries: impl ExactSizeIterator<Item = (&'b K, &'b V)> + Clone,
+    p: *mut u8,
+    pos: usize,
+) -> usize
+where
+    K: SerDeMapItem<'a> + 'b,
+    V: SerDeMapItem<'a> + 'b,
+{
+    let layout = Layout::new(
+        K::DATA_FORMAT,
+        K::list_size(entries.clone().map(|(k, _)| k)),
+        V::DATA_FORMAT,
+        V::list_size(entries.clone().map(|(_, v)| v)),
+    );
+    let message = p.add(pos + MESSAGE_OFFSET);
+    std::ptr::write_bytes(p.add(pos), 0, MESSAGE_OFFSET + layout.size);
+    (p.add(pos) as *mut u32).write_unaligned(layout.size as u32);
+    K::write_list(entries.clone().map(|(k, _)| k), message, layout.keys);
+    V::write_list(entries.map(|(_, v)| v), message, layout.values);
+    let mut table = [
+        (
+            hashes::field_hash(KEYS, K::DATA_FORMAT, true, false),
+            layout.keys,
+        ),
+        (
+            hashes::field_hash(VALUES, V::DATA_FORMAT, true, false),
+            layout.values,
+        ),
+    ];
+    table.sort_by_key(|(hash, _)| *hash);
+    let hash_table = align_to(layout.data_size, 4);
+    let ref_table = hash_table + 8;
+    for (index, (hash, offset)) in table.iter().enumerate() {
+        (message.add(hash_table + index * 4) as *mut u32).write_unaligned(*hash);
+        let offset_pos = message.add(ref_table + index * layout.offset_size);
+        match layout.offset_size {
+            1 => offset_pos.write(*offset as u8),
+            2 => (offset_pos as *mut u16).write_unaligned(*offset as u16),
+            _ => (offset_pos as *mut u32).write_unaligned(*offset as u32),
+        }
+    }
+    (message as *mut u32).write_unaligned(common::constants::MAGIC_V1);
+    (message.add(4) as *mut u16).write_unaligned(2);
+    *message.add(7) = (layout.offset_size / 2) as u8;
+    pos + MESSAGE_OFFSET + layout.size
+}
+
+// the nested message of a map field
+fn read_message(buf: &[u8], pos: usize) -> Option<FlatMessageBuffer<'_>> {
+    let size = <u32 as SerDe>::from_buffer(buf, pos)? as usize;
+    let start = pos.checked_add(MESSAGE_OFFSET)?;
+    let end = start.checked_add(size)?;
+    if end > buf.len() {
+        return None;
+    }
+    FlatMessageBuffer::try_from(&buf[start..end]).ok()
+}
+
+// the offset of a list from the nested message
+fn find_list(message: &FlatMessageBuffer, name: &str, data_format: DataFormat) -> Option<usize> {
+    message.find(hashes::field_hash(name, data_format, true, false))
+}
+
+fn read_list<'a, T: SerDeMapItem<'a>>(
+    message: &FlatMessageBuffer<'a>,
+    name: &str,
+) -> Option<Vec<T>> {
+    T::read_list(message.buffer(), find_list(message, name, T::DATA_FORMAT)?)
+}
+
+fn read_entries<'a, K, V>(buf: &'a [u8], pos: usize) -> Option<impl Iterator<Item = (K, V)>>
+where
+    K: SerDeMapItem<'a>,
+    V: SerDeMapItem<'a>,
+{
+    let message = read_message(buf, pos)?;
+    let keys: Vec<K> = read_list(&message, KEYS)?;
+    let values: Vec<V> = read_list(&message, VALUES)?;
+    if keys.len() != values.len() {
+        return None;
+    }
+    Some(keys.into_iter().zip(values))
+}
+
+// the unchecked version of `read_message` and `find_list`: the nested message and the offsets of
+// the `keys` and `values` lists
+unsafe fn find_lists_unchecked(
+    buf: &[u8],
+    pos: usize,
+    keys_format: DataFormat,
+    values_format: DataFormat,
+) -> (&[u8], usize, usize) {
+    let size = <u32 as SerDe>::from_buffer_unchecked(buf, pos) as usize;
+    let start = pos + MESSAGE_OFFSET;
+    let message = buf.get_unchecked(start..start + size);
+    let layout = headers::Layout::new(message).unwrap_unchecked();
+    let keys_hash = hashes::field_hash(KEYS, keys_format, true, false);
+    let values_hash = hashes::field_hash(VALUES, values_format, true, false);
+    let mut keys = None;
+    let mut values = None;
+    for index in 0..layout.header.fields_count as usize {
+        match layout.field(message, index).unwrap_unchecked() {
+            (hash, offset) if hash == keys_hash => keys = Some(offset),
+            (hash, offset) if hash == values_hash => values = Some(offset),
+            _ => {}
+        }
+    }
+    (message, keys.unwrap_unchecked(), values.unwrap_unchecked())
+}
+
+unsafe fn read_entries_unchecked<'a, K, V>(
+    buf: &'a [u8],
+    pos: usize,
+) -> impl Iterator<Item = (K, V)>
+where
+    K: SerDeMapItem<'a>,
+    V: SerDeMapItem<'a>,
+{
+    let (message, keys, values) = find_lists_unchecked(buf, pos, K::DATA_FORMAT, V::DATA_FORMAT);
+    let keys = K::read_list_unchecked(message, keys);
+    let values = V::read_list_unchecked(message, values);
+    keys.into_iter().zip(values)
+}
+
+unsafe impl<'a, K, V, S> SerDe<'a> for HashMap<K, V, S>
+where
+    K: SerDeMapItem<'a> + Eq + Hash,
+    V: SerDeMapItem<'a>,
+    S: BuildHasher + Default,
+{
+    const DATA_FORMAT: DataFormat = DataFormat::GenericObject;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        read_entries_unchecked(buf, pos).collect()
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        Some(read_entries(buf, pos)?.collect())
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &Self, p: *mut u8, pos: usize) -> usize {
+        write_map(obj.iter(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &Self) -> usize {
+        map_size(obj.iter())
+    }
+}
+
+unsafe impl<'a, K, V> SerDe<'a> for BTreeMap<K, V>
+where
+    K: SerDeMapItem<'a> + Ord,
+    V: SerDeMapItem<'a>,
+{
+    const DATA_FORMAT: DataFormat = DataFormat::GenericObject;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        read_entries_unchecked(buf, pos).collect()
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        Some(read_entries(buf, pos)?.collect())
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &Self, p: *mut u8, pos: usize) -> usize {
+        write_map(obj.iter(), p, pos)
+    }
+    #[inline(always)]
+    fn size(obj: &Self) -> usize {
+        map_size(obj.iter())
+    }
+}
+
+/// A list of strings read in place (validated when the map view is created)
+#[derive(Debug, Clone, Copy)]
+struct StrList<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    count: usize,
+}
+
+impl<'a> StrList<'a> {
+    const EMPTY: StrList<'static> = StrList {
+        buf: &[],
+        pos: 0,
+        count: 0,
+    };
+    fn new(buf: &'a [u8], pos: usize) -> Option<Self> {
+        let (count, size_len) = size::read(buf.as_ptr(), pos, buf.len(), STRINGS_SIZE_FORMAT)?;
+        let mut offset = pos + size_len;
+        for _ in 0..count {
+            let (len, size_len) = size::read(buf.as_ptr(), offset, buf.len(), STRINGS_SIZE_FORMAT)?;
+            let end = offset.checked_add(size_len + len)?;
+            std::str::from_utf8(buf.get(offset + size_len..end)?).ok()?;
+            offset = end;
+        }
+        Some(StrList {
+            buf,
+            pos: pos + size_len,
+            count,
+        })
+    }
+    unsafe fn new_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        let (count, size_len) = size::read_unchecked(buf.as_ptr(), pos, STRINGS_SIZE_FORMAT);
+        StrList {
+            buf,
+            pos: pos + size_len,
+            count,
+        }
+    }
+}
+
+impl<'a> Iterator for StrList<'a> {
+    type Item = &'a str;
+    fn next(&mut self) -> Option<&'a str> {
+        if self.count == 0 {
+            return None;
+        }
+        let (len, size_len) =
+            unsafe { size::read_unchecked(self.buf.as_ptr(), self.pos, STRINGS_SIZE_FORMAT) };
+        let start = self.pos + size_len;
+        self.pos = start + len;
+        self.count -= 1;
+        Some(unsafe { std::str::from_utf8_unchecked(&self.buf[start..self.pos]) })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.count, Some(self.count))
+    }
+}
+
+impl ExactSizeIterator for StrList<'_> {}
+
+/// A map with `&str` keys and values that is read in place (without any allocation). It has the
+/// same format as a `HashMap<String, String>` or `BTreeMap<String, String>` field, and lookups
+/// are linear (the keys are not sorted or hashed).
+#[derive(Debug, Clone, Copy)]
+pub struct MapView<'a> {
+    message: &'a [u8],
+    keys: StrList<'a>,
+    values: StrList<'a>,
+}
+
+impl<'a> MapView<'a> {
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.keys.count
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.keys.count == 0
+    }
+    /// The value associated with `key` (if a key is present multiple times, the first value is
+    /// returned)
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+    #[inline(always)]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.keys().any(|k| k == key)
+    }
+    #[inline(always)]
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &'a str> {
+        self.keys
+    }
+    #[inline(always)]
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &'a str> {
+        self.values
+    }
+    /// The entries of the map (in the order in which they are stored)
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.keys.zip(self.values)
+    }
+}
+
+impl Default for MapView<'_> {
+    fn default() -> Self {
+        MapView {
+            message: &[],
+            keys: StrList::EMPTY,
+            values: StrList::EMPTY,
+        }
+    }
+}
+
+fn view_list<'a>(message: &FlatMessageBuffer<'a>, name: &str) -> Option<StrList<'a>> {
+    StrList::new(
+        message.buffer(),
+        find_list(message, name, DataFormat::String)?,
+    )
+}
+
+unsafe impl<'a> SerDe<'a> for MapView<'a> {
+    const DATA_FORMAT: DataFormat = DataFormat::GenericObject;
+    #[inline(always)]
+    unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> Self {
+        let (message, keys, values) =
+            find_lists_unchecked(buf, pos, DataFormat::String, DataFormat::String);
+        MapView {
+            message,
+            keys: StrList::new_unchecked(message, keys),
+            values: StrList::new_unchecked(message, values),
+        }
+    }
+    #[inline(always)]
+    fn from_buffer(buf: &'a [u8], pos: usize) -> Option<Self> {
+        let message = read_message(buf, pos)?;
+        let keys = view_list(&message, KEYS)?;
+        let values = view_list(&message, VALUES)?;
+        if keys.count != values.count {
+            return None;
+        }
+        Some(MapView {
+            message: message.buffer(),
+            keys,
+            values,
+        })
+    }
+    #[inline(always)]
+    unsafe fn write(obj: &Self, p: *mut u8, pos: usize) -> usize {
+        if obj.message.is_empty() {
+            return write_map::<&str, &str>(std::iter::empty(), p, pos);
+        }
+        // the nested message is copied as it is
+        std::ptr::write_bytes(p.add(pos), 0, MESSAGE_OFFSET);
+        (p.add(pos) as *mut u32).write_unaligned(obj.message.len() as u32);
+        std::ptr::copy_nonoverlapping(
+            obj.message.as_ptr(),
+            p.add(pos + MESSAGE_OFFSET),
+            obj.message.len(),
+        );
+        pos + MESSAGE_OFFSET + obj.message.len()
+    }
+    #[inline(always)]
+    fn size(obj: &Self) -> usize {
+        if obj.message.is_empty() {
+            map_size::<&str, &str>(std::iter::empty())
+        } else {
+            MESSAGE_OFFSET + obj.message.len()
+        }
+    }
+}
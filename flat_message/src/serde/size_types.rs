@@ -0,0 +1,64 @@
+use super::SerDe;
+use super::SerDeSlice;
+use super::SerDeVec;
+use crate::size;
+use common::data_format::DataFormat;
+
+/// `usize` and `isize` are stored as 64 bit values (so that messages are the same on 32 and 64
+/// bit targets). On 32 bit targets, values that do not fit are rejected when they are
+/// deserialized. Slices (`&[usize]`) are not supported as the layout depends on the target.
+macro_rules! IMPLEMENT_SERDE_FOR_SIZE_TYPE {
+    ($t:ty, $wire:ty, $size_format:ident) => {
+        unsafe impl SerDe<'_> for $t {
+            const DATA_FORMAT: DataFormat = <$wire as SerDe>::DATA_FORMAT;
+            #[inline(always)]
+            unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Self {
+                <$wire as SerDe>::from_buffer_unchecked(buf, pos) as $t
+            }
+            #[inline(always)]
+            fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+                <$t>::try_from(<$wire as SerDe>::from_buffer(buf, pos)?).ok()
+            }
+            #[inline(always)]
+            unsafe fn write(obj: &Self, p: *mut u8, pos: usize) -> usize {
+                <$wire as SerDe>::write(&(*obj as $wire), p, pos)
+            }
+            #[inline(always)]
+            fn size(_: &Self) -> usize {
+                std::mem::size_of::<$wire>()
+            }
+        }
+
+        unsafe impl SerDeVec<'_> for $t {
+            const DATA_FORMAT: DataFormat = <$wire as SerDeVec>::DATA_FORMAT;
+            #[inline(always)]
+            unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Vec<Self> {
+                let res: &[$wire] = SerDeSlice::from_buffer_unchecked(buf, pos);
+                res.iter().map(|v| *v as $t).collect()
+            }
+            #[inline(always)]
+            fn from_buffer(buf: &[u8], pos: usize) -> Option<Vec<Self>> {
+                let res: &[$wire] = SerDeSlice::from_buffer(buf, pos)?;
+                res.iter().map(|v| <$t>::try_from(*v).ok()).collect()
+            }
+            #[inline(always)]
+            unsafe fn write(obj: &Vec<Self>, p: *mut u8, pos: usize) -> usize {
+                // the same layout as a list of `$wire` values (converted one by one)
+                let mut pos =
+                    pos + size::write(p, pos, obj.len() as u32, size::Format::$size_format);
+                for v in obj {
+                    (p.add(pos) as *mut $wire).write_unaligned(*v as $wire);
+                    pos += std::mem::size_of::<$wire>();
+                }
+                pos
+            }
+            #[inline(always)]
+            fn size(obj: &Vec<Self>) -> usize {
+                size::list_len::<$wire>(obj.len(), size::Format::$size_format)
+            }
+        }
+    };
+}
+
+IMPLEMENT_SERDE_FOR_SIZE_TYPE!(usize, u64, U32on64bits);
+IMPLEMENT_SERDE_FOR_SIZE_TYPE!(isize, i64, U32on64bits);
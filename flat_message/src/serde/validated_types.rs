@@ -0,0 +1,166 @@
+use super::SerDe;
+use super::SerDeSlice;
+use super::SerDeVec;
+use common::data_format::DataFormat;
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16,
+    NonZeroU32, NonZeroU64, NonZeroU8,
+};
+
+/// Types that have the same layout as an integer but not all values are valid (`char` is stored
+/// as a `u32` and must be a Unicode scalar value, `NonZero*` types are stored as the integer and
+/// can not be zero). The values are validated when they are deserialized, and slices are read in
+/// place (after validation).
+macro_rules! IMPLEMENT_SERDE_FOR_VALIDATED_TYPE {
+    ($t:ty, $wire:ty, $from_wire:path, $from_wire_unchecked:path, $to_wire:path) => {
+        unsafe impl<'a> SerDe<'a> for $t {
+            const DATA_FORMAT: DataFormat = <$wire as SerDe>::DATA_FORMAT;
+            #[inline(always)]
+            unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Self {
+                $from_wire_unchecked(<$wire as SerDe>::from_buffer_unchecked(buf, pos))
+            }
+            #[inline(always)]
+            fn from_buffer(buf: &[u8], pos: usize) -> Option<Self> {
+                $from_wire(<$wire as SerDe>::from_buffer(buf, pos)?)
+            }
+            #[inline(always)]
+            unsafe fn write(obj: &Self, p: *mut u8, pos: usize) -> usize {
+                <$wire as SerDe>::write(&$to_wire(*obj), p, pos)
+            }
+            #[inline(always)]
+            fn size(_: &Self) -> usize {
+                std::mem::size_of::<$wire>()
+            }
+        }
+
+        unsafe impl<'a> SerDeSlice<'a> for $t {
+            const DATA_FORMAT: DataFormat = <$wire as SerDeSlice>::DATA_FORMAT;
+            #[inline(always)]
+            unsafe fn from_buffer_unchecked(buf: &'a [u8], pos: usize) -> &'a [Self] {
+                let items = <$wire as SerDeSlice>::from_buffer_unchecked(buf, pos);
+                std::slice::from_raw_parts(items.as_ptr() as *const $t, items.len())
+            }
+            #[inline(always)]
+            fn from_buffer(buf: &'a [u8], pos: usize) -> Option<&'a [Self]> {
+                let items = <$wire as SerDeSlice>::from_buffer(buf, pos)?;
+                if items.iter().any(|v| $from_wire(*v).is_none()) {
+                    return None;
+                }
+                Some(unsafe {
+                    std::slice::from_raw_parts(items.as_ptr() as *const $t, items.len())
+                })
+            }
+            #[inline(always)]
+            unsafe fn write(obj: &[Self], p: *mut u8, pos: usize) -> usize {
+                let items = std::slice::from_raw_parts(obj.as_ptr() as *const $wire, obj.len());
+                <$wire as SerDeSlice>::write(items, p, pos)
+            }
+            #[inline(always)]
+            fn size(obj: &[Self]) -> usize {
+                let items =
+                    unsafe { std::slice::from_raw_parts(obj.as_ptr() as *const $wire, obj.len()) };
+                <$wire as SerDeSlice>::size(items)
+            }
+        }
+
+        unsafe impl SerDeVec<'_> for $t {
+            const DATA_FORMAT: DataFormat = <$wire as SerDeVec>::DATA_FORMAT;
+            #[inline(always)]
+            unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Vec<Self> {
+                let res: &[$t] = SerDeSlice::from_buffer_unchecked(buf, pos);
+                res.to_vec()
+            }
+            #[inline(always)]
+            fn from_buffer(buf: &[u8], pos: usize) -> Option<Vec<Self>> {
+                let res: &[$t] = SerDeSlice::from_buffer(buf, pos)?;
+                Some(res.to_vec())
+            }
+            #[inline(always)]
+            unsafe fn write(obj: &Vec<Self>, p: *mut u8, pos: usize) -> usize {
+                SerDeSlice::write(obj.as_slice(), p, pos)
+            }
+            #[inline(always)]
+            fn size(obj: &Vec<Self>) -> usize {
+                SerDeSlice::size(obj.as_slice())
+            }
+        }
+    };
+}
+
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    char,
+    u32,
+    char::from_u32,
+    char::from_u32_unchecked,
+    u32::from
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroU8,
+    u8,
+    NonZeroU8::new,
+    NonZeroU8::new_unchecked,
+    NonZeroU8::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroU16,
+    u16,
+    NonZeroU16::new,
+    NonZeroU16::new_unchecked,
+    NonZeroU16::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroU32,
+    u32,
+    NonZeroU32::new,
+    NonZeroU32::new_unchecked,
+    NonZeroU32::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroU64,
+    u64,
+    NonZeroU64::new,
+    NonZeroU64::new_unchecked,
+    NonZeroU64::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroU128,
+    u128,
+    NonZeroU128::new,
+    NonZeroU128::new_unchecked,
+    NonZeroU128::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroI8,
+    i8,
+    NonZeroI8::new,
+    NonZeroI8::new_unchecked,
+    NonZeroI8::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroI16,
+    i16,
+    NonZeroI16::new,
+    NonZeroI16::new_unchecked,
+    NonZeroI16::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroI32,
+    i32,
+    NonZeroI32::new,
+    NonZeroI32::new_unchecked,
+    NonZeroI32::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroI64,
+    i64,
+    NonZeroI64::new,
+    NonZeroI64::new_unchecked,
+    NonZeroI64::get
+);
+IMPLEMENT_SERDE_FOR_VALIDATED_TYPE!(
+    NonZeroI128,
+    i128,
+    NonZeroI128::new,
+    NonZeroI128::new_unchecked,
+    NonZeroI128::get
+);
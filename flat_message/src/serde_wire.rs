@@ -0,0 +1,64 @@
+//! Serializes types that implement `serde::Serialize` / `serde::Deserialize` in the FlatMessage
+//! format (without `#[flat_message]`).
+//!
+//! Fields are stored with the same hashes and layout as the ones generated by `#[flat_message]`
+//! so a structure can be serialized with serde and deserialized with `#[flat_message]` (and the
+//! other way around) as long as both use the same structure name and field types.
+//! Additionally:
+//! * `Option` fields with `None` values are not stored (missing fields are deserialized as `None`)
+//! * nested structures are stored as nested messages (`DataFormat::GenericObject`)
+//! * maps are stored as a nested message with two lists (`keys` and `values`)
+//! * enum variants without values are stored as strings (the name of the variant)
+//! * serde does not provide the type of the elements of an empty list (or map), so it is taken
+//!   from the type of the field (for numbers, `bool`, `char` and strings); other empty lists
+//!   are stored as lists of `u8` values (that only `serde_wire` can read back)
+use crate::{compression, Config, FlatMessageBuffer, Storage, VecLike};
+use ::serde::{Deserialize, Serialize};
+
+pub(crate) mod de;
+mod error;
+pub(crate) mod ser;
+
+pub use self::error::Error;
+
+// nested messages are aligned to 16 bytes (so they can be read in place)
+const OBJECT_ALIGNMENT: usize = 16;
+const MAP_KEYS: &str = "keys";
+const MAP_VALUES: &str = "values";
+
+/// Serializes `value` (it has to be a structure). The compression and the authentication key
+/// from `config` are applied to the message.
+pub fn serialize_to<T: Serialize + ?Sized, V: VecLike>(
+    value: &T,
+    output: &mut V,
+    config: Config,
+) -> Result<(), Error> {
+    let ser::Value::Object(Some(name), fields) = value.serialize(ser::ValueSerializer)? else {
+        return Err(Error::Unsupported("A value that is not a structure"));
+    };
+    let info = ser::MessageInfo {
+        name: Some(name),
+        authentication_key: config.authentication_key(),
+        ..Default::default()
+    };
+    let message = ser::build_message(&info, &fields)?;
+    if message.len() > config.max_size() as usize {
+        return Err(crate::Error::ExceedMaxSize((message.len() as u32, config.max_size())).into());
+    }
+    output.clear();
+    output.resize_zero(message.len());
+    output.as_mut_slice().copy_from_slice(&message);
+    compression::compress(output, config.compression(), config.compression_threshold())?;
+    Ok(())
+}
+
+pub fn deserialize_from<'de, T: Deserialize<'de>>(input: &'de Storage) -> Result<T, Error> {
+    deserialize_from_slice(input.as_slice())
+}
+
+/// Same as `deserialize_from`, but for a buffer that is not a `Storage` object (the buffer has to
+/// be aligned to 16 bytes)
+pub fn deserialize_from_slice<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    let buffer = FlatMessageBuffer::try_from(input)?;
+    T::deserialize(de::Element::Object(buffer))
+}
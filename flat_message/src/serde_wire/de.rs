@@ -0,0 +1,430 @@
+use super::{Error, MAP_KEYS, MAP_VALUES, OBJECT_ALIGNMENT};
+use crate::{size, FlatMessageBuffer, SerDe, SerDeCompressed, SerDeSlice, SerDeVec};
+use ::serde::de::value::{MapDeserializer, SeqDeserializer};
+use ::serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use ::serde::forward_to_deserialize_any;
+use common::data_format::DataFormat;
+use common::hashes;
+
+const LIST: u32 = 0x80;
+const COMPRESSED: u32 = 0x40;
+
+/// A value read from a message
+pub(crate) enum Element<'de> {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    Str(&'de str),
+    /// A compressed string
+    String(String),
+    Bytes(&'de [u8]),
+    /// A compressed list of bytes
+    ByteBuf(Vec<u8>),
+    Object(FlatMessageBuffer<'de>),
+    List(Vec<Element<'de>>),
+    /// The value of an enum (lists of enums are lists of `Enum` elements)
+    Enum(i128),
+}
+
+const ENUM_FORMATS: [DataFormat; 8] = [
+    DataFormat::EnumI8,
+    DataFormat::EnumI16,
+    DataFormat::EnumI32,
+    DataFormat::EnumI64,
+    DataFormat::EnumU8,
+    DataFormat::EnumU16,
+    DataFormat::EnumU32,
+    DataFormat::EnumU64,
+];
+
+fn enum_value(buf: &[u8], pos: usize, data_format: DataFormat) -> Option<i128> {
+    let bytes = buf.get(pos..pos + data_format.alignament() as usize)?;
+    Some(match data_format {
+        DataFormat::EnumI8 => i8::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumI16 => i16::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumI32 => i32::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumI64 => i64::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumU8 => u8::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumU16 => u16::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        DataFormat::EnumU32 => u32::from_ne_bytes(bytes.try_into().ok()?) as i128,
+        _ => u64::from_ne_bytes(bytes.try_into().ok()?) as i128,
+    })
+}
+
+// enums are stored as the enum hash (u32) followed by the value (or the number of values and
+// the values for lists)
+fn enum_element(
+    buf: &[u8],
+    pos: usize,
+    data_format: DataFormat,
+    list: bool,
+) -> Option<Element<'_>> {
+    let pos = pos.checked_add(4)?;
+    if !list {
+        return enum_value(buf, pos, data_format).map(Element::Enum);
+    }
+    let item_size = data_format.alignament() as usize;
+    let format = match item_size {
+        1 => size::Format::U8withExtension,
+        2 => size::Format::U16withExtension,
+        _ => size::Format::U32,
+    };
+    if pos > buf.len() {
+        return None;
+    }
+    let (count, size_len) = size::read(buf.as_ptr(), pos, buf.len(), format)?;
+    let start = pos + size_len;
+    if start + count.checked_mul(item_size)? > buf.len() {
+        return None;
+    }
+    (0..count)
+        .map(|index| enum_value(buf, start + index * item_size, data_format).map(Element::Enum))
+        .collect::<Option<Vec<_>>>()
+        .map(Element::List)
+}
+
+fn value<'de, T: SerDe<'de>>(buf: &'de [u8], pos: usize, hash: u32) -> Result<T, Error> {
+    T::from_buffer(buf, pos).ok_or(Error::Format(crate::Error::FailToDeserialize(hash)))
+}
+
+fn slice<'de, T: SerDeSlice<'de> + Copy + 'de>(
+    buf: &'de [u8],
+    pos: usize,
+    hash: u32,
+    element: fn(T) -> Element<'de>,
+) -> Result<Element<'de>, Error> {
+    let items =
+        T::from_buffer(buf, pos).ok_or(Error::Format(crate::Error::FailToDeserialize(hash)))?;
+    Ok(Element::List(items.iter().map(|v| element(*v)).collect()))
+}
+
+// a nested message (see `ser::write_nested`), returns the message and the position after it
+fn nested(buf: &[u8], pos: usize, hash: u32) -> Result<(FlatMessageBuffer<'_>, usize), Error> {
+    let invalid = Error::Format(crate::Error::FailToDeserialize(hash));
+    let size = <u32 as SerDe>::from_buffer(buf, pos).ok_or(invalid.clone())? as usize;
+    let start = pos + OBJECT_ALIGNMENT;
+    let end = start.checked_add(size).ok_or(invalid.clone())?;
+    if end > buf.len() {
+        return Err(invalid);
+    }
+    let message = FlatMessageBuffer::try_from(&buf[start..end])?;
+    Ok((
+        message,
+        (end + OBJECT_ALIGNMENT - 1) & !(OBJECT_ALIGNMENT - 1),
+    ))
+}
+
+impl<'de> Element<'de> {
+    /// Reads the field with the provided hash (the low byte of the hash is the type of the field)
+    pub(crate) fn read(buf: &'de [u8], hash: u32, pos: usize) -> Result<Self, Error> {
+        const fn format(data_format: DataFormat, flags: u32) -> u32 {
+            data_format as u32 | flags
+        }
+        const STRING_COMPRESSED: u32 = format(DataFormat::String, COMPRESSED);
+        const BYTES_COMPRESSED: u32 = format(DataFormat::U8, LIST | COMPRESSED);
+        const STRINGS_COMPRESSED: u32 = format(DataFormat::String, LIST | COMPRESSED);
+        const OBJECT_LIST: u32 = format(DataFormat::GenericObject, LIST);
+        const BOOL: u32 = DataFormat::Bool as u32;
+        const U8: u32 = DataFormat::U8 as u32;
+        const U16: u32 = DataFormat::U16 as u32;
+        const U32: u32 = DataFormat::U32 as u32;
+        const U64: u32 = DataFormat::U64 as u32;
+        const U128: u32 = DataFormat::U128 as u32;
+        const I8: u32 = DataFormat::I8 as u32;
+        const I16: u32 = DataFormat::I16 as u32;
+        const I32: u32 = DataFormat::I32 as u32;
+        const I64: u32 = DataFormat::I64 as u32;
+        const I128: u32 = DataFormat::I128 as u32;
+        const F32: u32 = DataFormat::F32 as u32;
+        const F64: u32 = DataFormat::F64 as u32;
+        const STRING: u32 = DataFormat::String as u32;
+        const OBJECT: u32 = DataFormat::GenericObject as u32;
+        const BYTES: u32 = DataFormat::Bytes as u32;
+        let invalid = || Error::Format(crate::Error::FailToDeserialize(hash));
+        Ok(match hash & 0xFF {
+            STRING_COMPRESSED => Element::String(
+                <String as SerDeCompressed>::from_buffer(buf, pos).ok_or_else(invalid)?,
+            ),
+            BYTES_COMPRESSED => Element::ByteBuf(
+                <Vec<u8> as SerDeCompressed>::from_buffer(buf, pos).ok_or_else(invalid)?,
+            ),
+            STRINGS_COMPRESSED => Element::List(
+                <Vec<String> as SerDeCompressed>::from_buffer(buf, pos)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(Element::String)
+                    .collect(),
+            ),
+            BOOL => Element::Bool(value(buf, pos, hash)?),
+            U8 => Element::U8(value(buf, pos, hash)?),
+            U16 => Element::U16(value(buf, pos, hash)?),
+            U32 => Element::U32(value(buf, pos, hash)?),
+            U64 => Element::U64(value(buf, pos, hash)?),
+            U128 => Element::U128(value(buf, pos, hash)?),
+            I8 => Element::I8(value(buf, pos, hash)?),
+            I16 => Element::I16(value(buf, pos, hash)?),
+            I32 => Element::I32(value(buf, pos, hash)?),
+            I64 => Element::I64(value(buf, pos, hash)?),
+            I128 => Element::I128(value(buf, pos, hash)?),
+            F32 => Element::F32(value(buf, pos, hash)?),
+            F64 => Element::F64(value(buf, pos, hash)?),
+            STRING => Element::Str(value(buf, pos, hash)?),
+            OBJECT => Element::Object(nested(buf, pos, hash)?.0),
+            BYTES => Element::Bytes(value(buf, pos, hash)?),
+            t if t == BOOL | LIST => slice(buf, pos, hash, Element::Bool)?,
+            t if t == U8 | LIST => {
+                Element::Bytes(<u8 as SerDeSlice>::from_buffer(buf, pos).ok_or_else(invalid)?)
+            }
+            t if t == U16 | LIST => slice(buf, pos, hash, Element::U16)?,
+            t if t == U32 | LIST => slice(buf, pos, hash, Element::U32)?,
+            t if t == U64 | LIST => slice(buf, pos, hash, Element::U64)?,
+            t if t == U128 | LIST => slice(buf, pos, hash, Element::U128)?,
+            t if t == I8 | LIST => slice(buf, pos, hash, Element::I8)?,
+            t if t == I16 | LIST => slice(buf, pos, hash, Element::I16)?,
+            t if t == I32 | LIST => slice(buf, pos, hash, Element::I32)?,
+            t if t == I64 | LIST => slice(buf, pos, hash, Element::I64)?,
+            t if t == I128 | LIST => slice(buf, pos, hash, Element::I128)?,
+            t if t == F32 | LIST => slice(buf, pos, hash, Element::F32)?,
+            t if t == F64 | LIST => slice(buf, pos, hash, Element::F64)?,
+            t if t == STRING | LIST => Element::List(
+                <&str as SerDeVec>::from_buffer(buf, pos)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(Element::Str)
+                    .collect(),
+            ),
+            OBJECT_LIST => {
+                let count = value::<u32>(buf, pos, hash)? as usize;
+                // each message takes at least 16 bytes
+                if count > buf.len() / OBJECT_ALIGNMENT {
+                    return Err(invalid());
+                }
+                let mut items = Vec::with_capacity(count);
+                let mut pos = pos + OBJECT_ALIGNMENT;
+                for _ in 0..count {
+                    let (message, next) = nested(buf, pos, hash)?;
+                    items.push(Element::Object(message));
+                    pos = next;
+                }
+                Element::List(items)
+            }
+            t => {
+                let data_format = ENUM_FORMATS
+                    .into_iter()
+                    .find(|f| *f as u32 == t & !LIST)
+                    .ok_or_else(invalid)?;
+                enum_element(buf, pos, data_format, t & LIST != 0).ok_or_else(invalid)?
+            }
+        })
+    }
+
+    fn into_list(self) -> Result<Vec<Element<'de>>, Error> {
+        match self {
+            Element::List(items) => Ok(items),
+            Element::Bytes(bytes) => Ok(bytes.iter().map(|b| Element::U8(*b)).collect()),
+            Element::ByteBuf(bytes) => Ok(bytes.into_iter().map(Element::U8).collect()),
+            _ => Err(de::Error::custom("expected a list")),
+        }
+    }
+
+    fn field(buffer: &FlatMessageBuffer<'de>, name: &str) -> Result<Option<Self>, Error> {
+        match buffer.find_name(hashes::fnv_32(name)) {
+            Some((hash, pos)) => Ok(Some(Element::read(buffer.buffer(), hash, pos)?)),
+            None => Ok(None),
+        }
+    }
+
+    // maps are stored as a nested message with two lists: `keys` and `values`
+    pub(crate) fn into_map(self) -> Result<Option<Vec<(Self, Self)>>, Error> {
+        let Element::Object(buffer) = self else {
+            return Ok(None);
+        };
+        let (Some(keys), Some(values)) = (
+            Element::field(&buffer, MAP_KEYS)?,
+            Element::field(&buffer, MAP_VALUES)?,
+        ) else {
+            return Ok(None);
+        };
+        let (keys, values) = (keys.into_list()?, values.into_list()?);
+        if keys.len() != values.len() {
+            return Err(de::Error::custom(
+                "a map must have the same number of keys and values",
+            ));
+        }
+        Ok(Some(keys.into_iter().zip(values).collect()))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Element<'de> {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// The fields of a structure (looked up by their name, missing fields are skipped)
+struct StructAccess<'de> {
+    buffer: FlatMessageBuffer<'de>,
+    fields: std::slice::Iter<'static, &'static str>,
+    value: Option<Element<'de>>,
+}
+
+impl<'de> MapAccess<'de> for StructAccess<'de> {
+    type Error = Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        for name in self.fields.by_ref() {
+            if let Some(value) = Element::field(&self.buffer, name)? {
+                self.value = Some(value);
+                return seed.deserialize(name.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Element<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Element::Bool(v) => visitor.visit_bool(v),
+            Element::U8(v) => visitor.visit_u8(v),
+            Element::U16(v) => visitor.visit_u16(v),
+            Element::U32(v) => visitor.visit_u32(v),
+            Element::U64(v) => visitor.visit_u64(v),
+            Element::U128(v) => visitor.visit_u128(v),
+            Element::I8(v) => visitor.visit_i8(v),
+            Element::I16(v) => visitor.visit_i16(v),
+            Element::I32(v) => visitor.visit_i32(v),
+            Element::I64(v) => visitor.visit_i64(v),
+            Element::I128(v) => visitor.visit_i128(v),
+            Element::F32(v) => visitor.visit_f32(v),
+            Element::F64(v) => visitor.visit_f64(v),
+            Element::Enum(v) => match i64::try_from(v) {
+                Ok(v) => visitor.visit_i64(v),
+                Err(_) => visitor.visit_i128(v),
+            },
+            Element::Str(v) => visitor.visit_borrowed_str(v),
+            Element::String(v) => visitor.visit_string(v),
+            Element::Bytes(_) | Element::ByteBuf(_) | Element::List(_) => {
+                let mut seq = SeqDeserializer::new(self.into_list()?.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            // field names are not stored, so only maps can be read without knowing the type
+            Element::Object(_) => self.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // missing fields are `None` (they are skipped by `StructAccess`)
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Element::Bytes(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Element::ByteBuf(bytes) => visitor.visit_byte_buf(bytes),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // chars are stored as `u32` values (strings with one character are also accepted)
+        match self {
+            Element::U32(v) => match char::from_u32(v) {
+                Some(c) => visitor.visit_char(c),
+                None => visitor.visit_u32(v),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.into_map()? {
+            Some(entries) => {
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            None => Err(de::Error::custom("expected a map")),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let Element::Object(buffer) = self else {
+            return Err(de::Error::custom(format!("expected structure '{}'", name)));
+        };
+        if buffer
+            .name()
+            .is_some_and(|n| n.value != hashes::fnv_32(name))
+        {
+            return Err(crate::Error::UnmatchedName.into());
+        }
+        visitor.visit_map(StructAccess {
+            buffer,
+            fields: fields.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Element::Str(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Element::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            _ => Err(Error::Unsupported("An enum variant with values")),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 str string
+        unit unit_struct seq tuple tuple_struct identifier
+    }
+}
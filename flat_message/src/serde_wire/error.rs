@@ -0,0 +1,57 @@
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A message from a `Serialize` / `Deserialize` implementation
+    Message(String),
+    /// The serialized message is not valid (or too large)
+    Format(crate::Error),
+    /// The type can not be represented in the FlatMessage format
+    Unsupported(&'static str),
+    /// A list with values of different types
+    MixedList,
+    /// Two fields of the same structure have the same hash
+    DuplicateField(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Message(message) => write!(f, "{}", message),
+            Error::Format(e) => write!(f, "{}", e),
+            Error::Unsupported(what) => {
+                write!(
+                    f,
+                    "{} can not be represented in the FlatMessage format !",
+                    what
+                )
+            }
+            Error::MixedList => write!(f, "All the values from a list must have the same type !"),
+            Error::DuplicateField(name) => write!(
+                f,
+                "Field '{}' has the same hash as another field of the structure !",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<crate::Error> for Error {
+    fn from(e: crate::Error) -> Self {
+        Error::Format(e)
+    }
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
@@ -0,0 +1,714 @@
+use super::{Error, OBJECT_ALIGNMENT};
+use crate::{size, SerDe, SerDeSlice, SerDeVec};
+use ::serde::ser::{self, Serialize};
+use common::constants;
+use common::data_format::DataFormat;
+use common::hashes;
+
+/// A serialized value (structures are kept as fields until the message is written)
+pub(crate) enum Value {
+    None,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+    String(String),
+    /// Opaque binary data (`DataFormat::Bytes`)
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    /// A structure (with its name) or a map (the `keys` and `values` lists)
+    Object(Option<&'static str>, Vec<(&'static str, Value)>),
+    /// A list without values (of the provided type)
+    EmptyList(DataFormat),
+    /// The value of an enum (data format, enum hash and value)
+    Enum(DataFormat, u32, i128),
+    EnumList(DataFormat, u32, Vec<i128>),
+    /// A field compressed with `SerDeCompressed` (data format, list and the compressed data)
+    Compressed(DataFormat, bool, Vec<u8>),
+}
+
+impl Value {
+    fn data_format(&self) -> Result<DataFormat, Error> {
+        Ok(match self {
+            Value::Bool(_) => DataFormat::Bool,
+            Value::U8(_) => DataFormat::U8,
+            Value::U16(_) => DataFormat::U16,
+            Value::U32(_) => DataFormat::U32,
+            Value::U64(_) => DataFormat::U64,
+            Value::U128(_) => DataFormat::U128,
+            Value::I8(_) => DataFormat::I8,
+            Value::I16(_) => DataFormat::I16,
+            Value::I32(_) => DataFormat::I32,
+            Value::I64(_) => DataFormat::I64,
+            Value::I128(_) => DataFormat::I128,
+            Value::F32(_) => DataFormat::F32,
+            Value::F64(_) => DataFormat::F64,
+            Value::String(_) => DataFormat::String,
+            Value::Bytes(_) => DataFormat::Bytes,
+            Value::Object(..) => DataFormat::GenericObject,
+            Value::Enum(data_format, ..) => *data_format,
+            Value::None => return Err(Error::Unsupported("A list of options")),
+            Value::List(_) | Value::EmptyList(_) | Value::EnumList(..) | Value::Compressed(..) => {
+                return Err(Error::Unsupported("A list of lists"))
+            }
+        })
+    }
+    // the data format of the field, if it is a list and if it is compressed
+    fn field_format(&self) -> Result<(DataFormat, bool, bool), Error> {
+        let items = match self {
+            Value::List(items) => items,
+            Value::EmptyList(data_format) | Value::EnumList(data_format, ..) => {
+                return Ok((*data_format, true, false))
+            }
+            Value::Compressed(data_format, list, _) => return Ok((*data_format, *list, true)),
+            _ => return Ok((self.data_format()?, false, false)),
+        };
+        // empty lists without a known type (see `type_empty_lists`) are written as lists of u8
+        let Some(first) = items.first() else {
+            return Ok((DataFormat::U8, true, false));
+        };
+        let data_format = first.data_format()?;
+        if data_format == DataFormat::Bytes {
+            return Err(Error::Unsupported("A list of bytes fields"));
+        }
+        for item in items.iter().skip(1) {
+            if item.data_format()? != data_format {
+                return Err(Error::MixedList);
+            }
+        }
+        Ok((data_format, true, false))
+    }
+}
+
+fn align_to(pos: usize, alignment: usize) -> usize {
+    (pos + alignment - 1) & !(alignment - 1)
+}
+
+fn align(output: &mut Vec<u8>, alignment: usize) {
+    output.resize(align_to(output.len(), alignment), 0);
+}
+
+fn write_object<'a, T: SerDe<'a>>(output: &mut Vec<u8>, value: &T) {
+    let pos = output.len();
+    output.resize(pos + T::size(value), 0);
+    unsafe { T::write(value, output.as_mut_ptr(), pos) };
+}
+
+fn write_slice<'a, T: SerDeSlice<'a>>(output: &mut Vec<u8>, items: &[T]) {
+    align(output, T::DATA_FORMAT.alignament() as usize);
+    let pos = output.len();
+    output.resize(pos + T::size(items), 0);
+    unsafe { T::write(items, output.as_mut_ptr(), pos) };
+}
+
+fn write_empty_list(output: &mut Vec<u8>, data_format: DataFormat) -> Result<(), Error> {
+    match data_format {
+        DataFormat::U8 => write_slice::<u8>(output, &[]),
+        DataFormat::U16 => write_slice::<u16>(output, &[]),
+        DataFormat::U32 => write_slice::<u32>(output, &[]),
+        DataFormat::U64 => write_slice::<u64>(output, &[]),
+        DataFormat::U128 => write_slice::<u128>(output, &[]),
+        DataFormat::I8 => write_slice::<i8>(output, &[]),
+        DataFormat::I16 => write_slice::<i16>(output, &[]),
+        DataFormat::I32 => write_slice::<i32>(output, &[]),
+        DataFormat::I64 => write_slice::<i64>(output, &[]),
+        DataFormat::I128 => write_slice::<i128>(output, &[]),
+        DataFormat::F32 => write_slice::<f32>(output, &[]),
+        DataFormat::F64 => write_slice::<f64>(output, &[]),
+        DataFormat::Bool => write_slice::<bool>(output, &[]),
+        DataFormat::String => {
+            let pos = output.len();
+            output.resize(pos + <&str as SerDeVec>::size(&Vec::new()), 0);
+            unsafe { <&str as SerDeVec>::write(&Vec::new(), output.as_mut_ptr(), pos) };
+        }
+        _ => return Err(Error::Unsupported("An empty list of enums or structures")),
+    }
+    Ok(())
+}
+
+// the data format of the elements of a list, from the name of their type (`None` for the types
+// that can not be identified by their name, such as structures or enums)
+fn element_format(type_name: &str) -> Option<DataFormat> {
+    Some(match type_name {
+        "u8" => DataFormat::U8,
+        "u16" => DataFormat::U16,
+        "u32" | "char" => DataFormat::U32,
+        "u64" => DataFormat::U64,
+        "u128" => DataFormat::U128,
+        "i8" => DataFormat::I8,
+        "i16" => DataFormat::I16,
+        "i32" => DataFormat::I32,
+        "i64" => DataFormat::I64,
+        "i128" => DataFormat::I128,
+        "f32" => DataFormat::F32,
+        "f64" => DataFormat::F64,
+        "bool" => DataFormat::Bool,
+        "alloc::string::String" | "&str" => DataFormat::String,
+        _ => return None,
+    })
+}
+
+// the generic arguments of a collection (`Vec<T>`, `HashMap<K, V>`, ...), or the element type of
+// a slice or an array (references and options are removed first)
+fn type_arguments(type_name: &str) -> Vec<&str> {
+    let mut name = type_name.trim();
+    loop {
+        if let Some(inner) = name.strip_prefix('&') {
+            name = inner.strip_prefix("mut ").unwrap_or(inner);
+        } else if let Some(inner) = name
+            .strip_prefix("core::option::Option<")
+            .and_then(|inner| inner.strip_suffix('>'))
+        {
+            name = inner;
+        } else {
+            break;
+        }
+    }
+    if let Some(inner) = name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+        // `[T]` or `[T; N]`
+        let element = match inner.rsplit_once(';') {
+            Some((element, len)) if len.trim().parse::<usize>().is_ok() => element,
+            _ => inner,
+        };
+        return vec![element.trim()];
+    }
+    let Some(args) = name
+        .split_once('<')
+        .and_then(|(_, args)| args.strip_suffix('>'))
+    else {
+        return Vec::new();
+    };
+    let mut result = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (index, c) in args.char_indices() {
+        match c {
+            '<' | '[' | '(' => depth += 1,
+            '>' | ']' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(args[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(args[start..].trim());
+    result
+}
+
+// serde does not provide the type of the elements of an empty list (or map), so it is taken from
+// the type of the field (otherwise the list is written as a list of `u8` values, which can only
+// be read by `serde_wire`)
+fn type_empty_lists(value: &mut Value, type_name: &str) {
+    let set_type = |list: &mut Value, element: Option<&&str>| {
+        if matches!(list, Value::List(items) if items.is_empty()) {
+            if let Some(data_format) = element.and_then(|e| element_format(e)) {
+                *list = Value::EmptyList(data_format);
+            }
+        }
+    };
+    let arguments = type_arguments(type_name);
+    match value {
+        Value::List(_) => set_type(value, arguments.first()),
+        // maps (the `keys` and `values` lists)
+        Value::Object(None, lists) if arguments.len() >= 2 => {
+            for ((_, list), element) in lists.iter_mut().zip(arguments.iter()) {
+                set_type(list, Some(element));
+            }
+        }
+        _ => {}
+    }
+}
+
+// the bytes of an enum value (the size of the value is the alignment of the data format)
+fn enum_bytes(data_format: DataFormat, value: i128) -> Vec<u8> {
+    match data_format.alignament() {
+        1 => (value as u8).to_ne_bytes().to_vec(),
+        2 => (value as u16).to_ne_bytes().to_vec(),
+        4 => (value as u32).to_ne_bytes().to_vec(),
+        _ => (value as u64).to_ne_bytes().to_vec(),
+    }
+}
+
+/// Enums are stored as the enum hash (u32) followed by the value. Lists of enums store the enum
+/// hash followed by the number of values and the values (the same layout as `FlatMessageEnum`).
+fn write_enum_list(output: &mut Vec<u8>, data_format: DataFormat, hash: u32, values: &[i128]) {
+    let format = match data_format.alignament() {
+        1 => size::Format::U8withExtension,
+        2 => size::Format::U16withExtension,
+        _ => size::Format::U32,
+    };
+    align(output, data_format.alignament() as usize);
+    output.extend_from_slice(&hash.to_ne_bytes());
+    let pos = output.len();
+    output.resize(pos + size::len(values.len() as u32, format), 0);
+    unsafe { size::write(output.as_mut_ptr(), pos, values.len() as u32, format) };
+    for value in values {
+        output.extend_from_slice(&enum_bytes(data_format, *value));
+    }
+}
+
+// the values of a list (already checked to have the same type)
+fn items<T>(items: &[Value], get: impl Fn(&Value) -> Option<T>) -> Vec<T> {
+    items.iter().filter_map(get).collect()
+}
+
+/// Nested messages are aligned to 16 bytes and start with their size:
+/// | Offset | Type | Observation                                     |
+/// |--------|------|-------------------------------------------------|
+/// | +0     | u32  | Size of the message                             |
+/// | +16    | ?    | The message (padded to 16 bytes)                |
+///
+/// Lists of nested messages start with the number of messages (u32, padded to 16 bytes),
+/// followed by the messages (in the same format).
+fn write_nested(
+    output: &mut Vec<u8>,
+    name: Option<&str>,
+    fields: &[(&'static str, Value)],
+) -> Result<(), Error> {
+    align(output, OBJECT_ALIGNMENT);
+    let message = build_message(&MessageInfo::with_name(name), fields)?;
+    output.extend_from_slice(&(message.len() as u32).to_ne_bytes());
+    align(output, OBJECT_ALIGNMENT);
+    output.extend_from_slice(&message);
+    align(output, OBJECT_ALIGNMENT);
+    Ok(())
+}
+
+// returns the offset of the value (lists and nested messages are aligned)
+fn write_value(output: &mut Vec<u8>, value: &Value) -> Result<usize, Error> {
+    let start = output.len();
+    match value {
+        Value::Bool(v) => write_object(output, v),
+        Value::U8(v) => write_object(output, v),
+        Value::U16(v) => write_object(output, v),
+        Value::U32(v) => write_object(output, v),
+        Value::U64(v) => write_object(output, v),
+        Value::U128(v) => write_object(output, v),
+        Value::I8(v) => write_object(output, v),
+        Value::I16(v) => write_object(output, v),
+        Value::I32(v) => write_object(output, v),
+        Value::I64(v) => write_object(output, v),
+        Value::I128(v) => write_object(output, v),
+        Value::F32(v) => write_object(output, v),
+        Value::F64(v) => write_object(output, v),
+        Value::String(v) => write_object(output, &v.as_str()),
+        Value::Bytes(v) => write_object(output, &v.as_slice()),
+        Value::Object(name, fields) => {
+            write_nested(output, *name, fields)?;
+            return Ok(align_to(start, OBJECT_ALIGNMENT));
+        }
+        Value::List(list) => return write_list(output, list),
+        Value::EmptyList(data_format) => {
+            write_empty_list(output, *data_format)?;
+            return Ok(align_to(start, data_format.alignament() as usize));
+        }
+        Value::Enum(data_format, hash, value) => {
+            output.extend_from_slice(&hash.to_ne_bytes());
+            output.extend_from_slice(&enum_bytes(*data_format, *value));
+        }
+        Value::EnumList(data_format, hash, values) => {
+            write_enum_list(output, *data_format, *hash, values);
+            return Ok(align_to(start, data_format.alignament() as usize));
+        }
+        Value::Compressed(_, _, data) => output.extend_from_slice(data),
+        Value::None => {}
+    }
+    Ok(start)
+}
+
+fn write_list(output: &mut Vec<u8>, list: &[Value]) -> Result<usize, Error> {
+    let start = output.len();
+    macro_rules! slice {
+        ($variant:ident) => {
+            write_slice(
+                output,
+                &items(list, |v| match v {
+                    Value::$variant(v) => Some(*v),
+                    _ => None,
+                }),
+            )
+        };
+    }
+    let Some(first) = list.first() else {
+        write_slice::<u8>(output, &[]);
+        return Ok(start);
+    };
+    match first {
+        Value::Bool(_) => slice!(Bool),
+        Value::U8(_) => slice!(U8),
+        Value::U16(_) => slice!(U16),
+        Value::U32(_) => slice!(U32),
+        Value::U64(_) => slice!(U64),
+        Value::U128(_) => slice!(U128),
+        Value::I8(_) => slice!(I8),
+        Value::I16(_) => slice!(I16),
+        Value::I32(_) => slice!(I32),
+        Value::I64(_) => slice!(I64),
+        Value::I128(_) => slice!(I128),
+        Value::F32(_) => slice!(F32),
+        Value::F64(_) => slice!(F64),
+        Value::String(_) => {
+            let strings: Vec<&str> = list
+                .iter()
+                .filter_map(|v| match v {
+                    Value::String(v) => Some(v.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let pos = output.len();
+            output.resize(pos + <&str as SerDeVec>::size(&strings), 0);
+            unsafe { <&str as SerDeVec>::write(&strings, output.as_mut_ptr(), pos) };
+        }
+        Value::Object(..) => {
+            align(output, OBJECT_ALIGNMENT);
+            output.extend_from_slice(&(list.len() as u32).to_ne_bytes());
+            for item in list {
+                if let Value::Object(name, fields) = item {
+                    write_nested(output, *name, fields)?;
+                }
+            }
+            return Ok(align_to(start, OBJECT_ALIGNMENT));
+        }
+        Value::Enum(data_format, hash, _) => {
+            let values = items(list, |v| match v {
+                Value::Enum(_, _, value) => Some(*value),
+                _ => None,
+            });
+            write_enum_list(output, *data_format, *hash, &values);
+        }
+        _ => unreachable!("checked by Value::field_format"),
+    }
+    Ok(align_to(start, first.data_format()?.alignament() as usize))
+}
+
+/// The header and the metadata of a message
+#[derive(Default)]
+pub(crate) struct MessageInfo<'a> {
+    pub(crate) name: Option<&'a str>,
+    pub(crate) version: u8,
+    pub(crate) checksum: bool,
+    pub(crate) authentication_key: Option<&'a [u8; constants::MAC_SIZE]>,
+}
+
+impl<'a> MessageInfo<'a> {
+    pub(crate) fn with_name(name: Option<&'a str>) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+/// Writes a message with the same layout as the one generated by `#[flat_message]` (fields with
+/// `None` values are not written)
+pub(crate) fn build_message(
+    info: &MessageInfo,
+    fields: &[(&'static str, Value)],
+) -> Result<Vec<u8>, Error> {
+    let mut output = vec![0u8; 8];
+    let mut table: Vec<(u32, usize)> = Vec::with_capacity(fields.len());
+    for (field_name, value) in fields.iter() {
+        if let Value::None = value {
+            continue;
+        }
+        let (data_format, list, compressed) = value.field_format()?;
+        let hash = hashes::field_hash(field_name, data_format, list, compressed);
+        if table
+            .iter()
+            .any(|(h, _)| *h & 0xFFFFFF00 == hash & 0xFFFFFF00)
+        {
+            return Err(Error::DuplicateField(field_name));
+        }
+        let offset = write_value(&mut output, value)?;
+        table.push((hash, offset));
+    }
+    if table.len() > 0xFFFF {
+        return Err(Error::Unsupported(
+            "A structure with more than 65535 fields",
+        ));
+    }
+    table.sort_by_key(|(hash, _)| *hash);
+    let data_size = output.len();
+    let (offset_size, mut flags) = if data_size < 0x100 {
+        (1, 0)
+    } else if data_size < 0x10000 {
+        (2, 1)
+    } else {
+        (4, 2)
+    };
+    align(&mut output, 4);
+    for (hash, _) in table.iter() {
+        output.extend_from_slice(&hash.to_ne_bytes());
+    }
+    for (_, offset) in table.iter() {
+        match offset_size {
+            1 => output.push(*offset as u8),
+            2 => output.extend_from_slice(&(*offset as u16).to_ne_bytes()),
+            _ => output.extend_from_slice(&(*offset as u32).to_ne_bytes()),
+        }
+    }
+    if let Some(name) = info.name {
+        flags |= constants::FLAG_HAS_NAME_HASH;
+        output.extend_from_slice(&hashes::fnv_32(name).to_ne_bytes());
+    }
+    if info.authentication_key.is_some() {
+        flags |= constants::FLAG_HAS_MAC;
+    }
+    if info.checksum {
+        flags |= constants::FLAG_HAS_CHECKSUM;
+    }
+    output[..4].copy_from_slice(&constants::MAGIC_V1.to_ne_bytes());
+    output[4..6].copy_from_slice(&(table.len() as u16).to_ne_bytes());
+    output[6] = info.version;
+    output[7] = flags;
+    if let Some(key) = info.authentication_key {
+        let mac = crate::authentication::compute_mac(key, &output);
+        output.extend_from_slice(&mac);
+    }
+    if info.checksum {
+        let checksum = hashes::crc32(&output);
+        output.extend_from_slice(&checksum.to_ne_bytes());
+    }
+    Ok(output)
+}
+
+/// Converts a value into a `Value`
+pub(crate) struct ValueSerializer;
+
+pub(crate) struct ListSerializer {
+    items: Vec<Value>,
+}
+
+pub(crate) struct MapSerializer {
+    keys: Vec<Value>,
+    values: Vec<Value>,
+}
+
+pub(crate) struct StructSerializer {
+    name: &'static str,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, Error>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = ser::Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::I8(v))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::I16(v))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::I32(v))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::I64(v))
+    }
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        Ok(Value::I128(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::U8(v))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::U16(v))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::U32(v))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::U64(v))
+    }
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        Ok(Value::U128(v))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::F32(v))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::F64(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        // same encoding as a `char` field of a `#[flat_message]` structure
+        Ok(Value::U32(v as u32))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::String(v.to_string()))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::List(v.iter().map(|b| Value::U8(*b)).collect()))
+    }
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Err(Error::Unsupported("A unit value"))
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+        Err(Error::Unsupported("A unit structure"))
+    }
+    /// Enum variants without values are stored as strings (the name of the variant)
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::String(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Value, Error> {
+        Err(Error::Unsupported("An enum variant with values"))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, Error> {
+        Ok(ListSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<ListSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::Unsupported("An enum variant with values"))
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            keys: Vec::with_capacity(len.unwrap_or(0)),
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer {
+            name,
+            fields: Vec::with_capacity(len),
+        })
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::Unsupported("An enum variant with values"))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::List(self.items))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Value;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Maps are stored as a nested message with two lists (`keys` and `values`)
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.keys.push(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Object(
+            None,
+            vec![
+                (super::MAP_KEYS, Value::List(self.keys)),
+                (super::MAP_VALUES, Value::List(self.values)),
+            ],
+        ))
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let mut value = value.serialize(ValueSerializer)?;
+        type_empty_lists(&mut value, std::any::type_name::<T>());
+        self.fields.push((key, value));
+        Ok(())
+    }
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Object(Some(self.name), self.fields))
+    }
+}
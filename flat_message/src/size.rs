@@ -179,6 +179,35 @@ pub fn len(value: u32, method: Format) -> usize {
     }
 }
 
+/// The size of a list of `count` values of type `T` stored in place (after the number of values)
+#[inline(always)]
+pub fn list_len<T>(count: usize, method: Format) -> usize {
+    len(count as u32, method) + count * std::mem::size_of::<T>()
+}
+
+/// Writes the number of values followed by the values (copied one by one). Returns the position
+/// after the list.
+/// # Safety
+/// `p` must have at least `pos + list_len::<T>(items.len(), method)` bytes.
+#[inline(always)]
+pub unsafe fn write_list<'b, T: 'b>(
+    items: impl ExactSizeIterator<Item = &'b T>,
+    p: *mut u8,
+    pos: usize,
+    method: Format,
+) -> usize {
+    let mut pos = pos + write(p, pos, items.len() as u32, method);
+    for item in items {
+        std::ptr::copy_nonoverlapping(
+            item as *const T as *const u8,
+            p.add(pos),
+            std::mem::size_of::<T>(),
+        );
+        pos += std::mem::size_of::<T>();
+    }
+    pos
+}
+
 // #[inline(always)]
 // pub(crate) unsafe fn write<T: Sized + Copy>(p: *mut u8, pos: usize, value: T) {
 //     unsafe {
@@ -29,15 +29,23 @@ impl TryFrom<&Storage> for StructureInformation {
     type Error = Error;
 
     fn try_from(buf: &Storage) -> Result<Self, Self::Error> {
+        StructureInformation::try_from(buf.as_slice())
+    }
+}
+
+impl TryFrom<&[u8]> for StructureInformation {
+    type Error = Error;
+
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
         // validate buf length - minimum 8 bytes
-        let buf = buf.as_slice();
         let len = buf.len();
         if len < size_of::<headers::HeaderV1>() {
             return Err(Error::InvalidHeaderLength(len));
         }
         let p = buf.as_ptr();
         let header: headers::HeaderV1 = unsafe { buffer::read(p, 0) };
-        if header.magic != constants::MAGIC_V1 {
+        // metadata is not compressed (so it can be read from compressed messages as well)
+        if header.magic != constants::MAGIC_V1 && header.magic != constants::MAGIC_V1_LZ4 {
             return Err(Error::InvalidMagic);
         }
         let mut metadata_size = 0usize;
@@ -53,6 +61,12 @@ impl TryFrom<&Storage> for StructureInformation {
         if header.flags & constants::FLAG_HAS_UNIQUEID != 0 {
             metadata_size += 8;
         }
+        if header.flags & constants::FLAG_HAS_MAC != 0 {
+            metadata_size += constants::MAC_SIZE;
+        }
+        if header.flags & constants::FLAG_IS_ENCRYPTED != 0 {
+            metadata_size += constants::ENCRYPTION_INFO_SIZE;
+        }
         if metadata_size + size_of::<headers::HeaderV1>() > len {
             return Err(Error::InvalidSizeToStoreMetaData((
                 len as u32,
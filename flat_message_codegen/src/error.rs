@@ -0,0 +1,33 @@
+use std::fmt::{Debug, Display, Formatter};
+
+pub enum Error {
+    Io(std::io::Error),
+    /// The schema file is not a valid JSON / TOML (or does not have the expected format)
+    Parse(String),
+    /// The schema can be parsed but it describes something that can not be generated
+    InvalidSchema(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Parse(e) => write!(f, "Invalid schema file: {}", e),
+            Error::InvalidSchema(e) => write!(f, "Invalid schema: {}", e),
+        }
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
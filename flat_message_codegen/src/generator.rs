@@ -0,0 +1,329 @@
+use crate::idl::{EnumDef, FieldDef, Idl, StructDef};
+use crate::Error;
+use common::data_format::DataFormat;
+use common::hashes;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const PRIMITIVES: [&str; 16] = [
+    "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128", "f32", "f64", "bool",
+    "string", "String", "bytes",
+];
+const ENUM_REPRS: [&str; 8] = ["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64"];
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+fn invalid<T>(message: String) -> Result<T, Error> {
+    Err(Error::InvalidSchema(message))
+}
+
+fn validate_identifier(name: &str, what: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !valid_start
+        || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || KEYWORDS.contains(&name)
+        || name == "metadata"
+    {
+        return invalid(format!("'{}' is not a valid name for {} !", name, what));
+    }
+    Ok(())
+}
+
+fn enum_range(repr: &str) -> (i128, i128) {
+    match repr {
+        "u8" => (0, u8::MAX as i128),
+        "u16" => (0, u16::MAX as i128),
+        "u32" => (0, u32::MAX as i128),
+        "u64" => (0, u64::MAX as i128),
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        _ => (i64::MIN as i128, i64::MAX as i128),
+    }
+}
+
+struct Generator<'a> {
+    idl: &'a Idl,
+    enums: HashMap<&'a str, &'a EnumDef>,
+    output: String,
+}
+
+impl<'a> Generator<'a> {
+    fn new(idl: &'a Idl) -> Self {
+        Self {
+            idl,
+            enums: HashMap::new(),
+            output: String::with_capacity(4096),
+        }
+    }
+    fn run(mut self) -> Result<String, Error> {
+        let mut names = HashSet::new();
+        for e in self.idl.enums.iter() {
+            validate_identifier(&e.name, "an enum")?;
+            if PRIMITIVES.contains(&e.name.as_str()) || !names.insert(e.name.as_str()) {
+                return invalid(format!("Duplicate type name '{}' !", e.name));
+            }
+            self.enums.insert(e.name.as_str(), e);
+        }
+        for s in self.idl.structs.iter() {
+            validate_identifier(&s.name, "a structure")?;
+            if PRIMITIVES.contains(&s.name.as_str()) || !names.insert(s.name.as_str()) {
+                return invalid(format!("Duplicate type name '{}' !", s.name));
+            }
+        }
+        self.output
+            .push_str("// Generated by flat_message_codegen. Do not edit.\n");
+        for e in self.idl.enums.iter() {
+            self.generate_enum(e)?;
+        }
+        for s in self.idl.structs.iter() {
+            self.generate_struct(s)?;
+        }
+        Ok(self.output)
+    }
+    fn generate_enum(&mut self, e: &EnumDef) -> Result<(), Error> {
+        if !ENUM_REPRS.contains(&e.repr.as_str()) {
+            return invalid(format!(
+                "Invalid representation '{}' for enum '{}' (allowed values are: {}) !",
+                e.repr,
+                e.name,
+                ENUM_REPRS.join(", ")
+            ));
+        }
+        if e.variants.is_empty() {
+            return invalid(format!("Enum '{}' has no variants !", e.name));
+        }
+        let (min, max) = enum_range(&e.repr);
+        let mut names = HashSet::new();
+        let mut values = HashSet::new();
+        for v in e.variants.iter() {
+            validate_identifier(&v.name, "a variant")?;
+            if !names.insert(v.name.as_str()) || !values.insert(v.value) {
+                return invalid(format!(
+                    "Duplicate variant name or value ('{}' = {}) in enum '{}' !",
+                    v.name, v.value, e.name
+                ));
+            }
+            if (v.value as i128) < min || (v.value as i128) > max {
+                return invalid(format!(
+                    "Value {} of variant '{}::{}' does not fit in '{}' !",
+                    v.value, e.name, v.name, e.repr
+                ));
+            }
+        }
+        if let Some(default) = &e.default {
+            if !names.contains(default.as_str()) {
+                return invalid(format!(
+                    "The default variant '{}' of enum '{}' does not exist !",
+                    default, e.name
+                ));
+            }
+        }
+        let o = &mut self.output;
+        if e.default.is_some() {
+            o.push_str("\n#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, flat_message::FlatMessageEnum)]\n");
+        } else {
+            o.push_str(
+                "\n#[derive(Copy, Clone, Debug, PartialEq, Eq, flat_message::FlatMessageEnum)]\n",
+            );
+        }
+        let _ = writeln!(o, "#[repr({})]", e.repr);
+        if e.sealed {
+            o.push_str("#[sealed]\n");
+        }
+        let _ = writeln!(o, "pub enum {} {{", e.name);
+        for v in e.variants.iter() {
+            if e.default.as_ref() == Some(&v.name) {
+                o.push_str("    #[default]\n");
+            }
+            let _ = writeln!(o, "    {} = {},", v.name, v.value);
+        }
+        o.push_str("}\n");
+        Ok(())
+    }
+    // returns the data format and the rust type of the field
+    fn field_type(&self, s: &StructDef, f: &FieldDef) -> Result<(DataFormat, String), Error> {
+        let borrowed = s.borrowed && f.compress.is_none();
+        let (data_format, ty) = if let Some(e) = self.enums.get(f.ty.as_str()) {
+            let data_format = DataFormat::from(format!("enum_{}", e.repr).as_str());
+            (data_format, e.name.clone())
+        } else if PRIMITIVES.contains(&f.ty.as_str()) {
+            if f.ty == "string" || f.ty == "String" {
+                let ty = if borrowed { "&'a str" } else { "String" };
+                (DataFormat::String, ty.to_string())
+            } else if f.ty == "bytes" {
+                if f.list {
+                    return invalid(format!(
+                        "Field '{}::{}' can not be a list of bytes !",
+                        s.name, f.name
+                    ));
+                }
+                let ty = if borrowed { "&'a [u8]" } else { "Vec<u8>" };
+                (DataFormat::Bytes, ty.to_string())
+            } else {
+                (DataFormat::from(f.ty.as_str()), f.ty.clone())
+            }
+        } else {
+            return invalid(format!(
+                "Unknown type '{}' for field '{}::{}' (it should be a primitive type or an enum from the schema) !",
+                f.ty, s.name, f.name
+            ));
+        };
+        let ty = match (f.list, borrowed) {
+            (false, _) => ty,
+            // strings are stored as a vector of string slices
+            (true, true) if data_format == DataFormat::String => format!("Vec<{}>", ty),
+            (true, true) => format!("&'a [{}]", ty),
+            (true, false) => format!("Vec<{}>", ty),
+        };
+        Ok((data_format, ty))
+    }
+    fn generate_struct(&mut self, s: &StructDef) -> Result<(), Error> {
+        if s.version == Some(0) {
+            return invalid(format!(
+                "Invalid version 0 for structure '{}' (allowed values are between 1 and 255) !",
+                s.name
+            ));
+        }
+        if s.fields.len() > 0xFFFF {
+            return invalid(format!(
+                "Structure '{}' has more than 65535 fields !",
+                s.name
+            ));
+        }
+        let mut attributes = Vec::new();
+        if let Some(version) = s.version {
+            attributes.push(format!("version: {}", version));
+        }
+        if let Some(versions) = &s.compatible_versions {
+            if let Err(e) = common::version_set::VersionSet::try_from(versions.as_str()) {
+                return invalid(format!(
+                    "Invalid compatible_versions for '{}': {}",
+                    s.name, e
+                ));
+            }
+            attributes.push(format!("compatible_versions: \"{}\"", versions));
+        }
+        for (name, value) in [
+            ("store_name", s.store_name),
+            ("metadata", s.metadata),
+            ("checksum", s.checksum),
+        ] {
+            if let Some(value) = value {
+                attributes.push(format!("{}: {}", name, value));
+            }
+        }
+        let mut names = HashSet::new();
+        let mut hashes = HashMap::new();
+        let mut fields = Vec::with_capacity(s.fields.len());
+        let mut lifetime = false;
+        for f in s.fields.iter() {
+            validate_identifier(&f.name, "a field")?;
+            if !names.insert(f.name.as_str()) {
+                return invalid(format!("Duplicate field '{}::{}' !", s.name, f.name));
+            }
+            let (data_format, ty) = self.field_type(s, f)?;
+            lifetime |= ty.contains("'a");
+            let mut field_attributes = Vec::new();
+            if data_format.is_enum() {
+                let repr = &self.enums[f.ty.as_str()].repr;
+                field_attributes.push(format!("repr = {}, kind = enum", repr));
+            }
+            if data_format == DataFormat::Bytes {
+                field_attributes.push("kind = bytes".to_string());
+            }
+            if let Some(compress) = &f.compress {
+                let supported = match data_format {
+                    DataFormat::String => true,
+                    DataFormat::U8 => f.list,
+                    _ => false,
+                };
+                if compress != "lz4" || !supported {
+                    return invalid(format!(
+                        "Field '{}::{}' can not be compressed with '{}' (only 'lz4' is supported, for strings, lists of strings and lists of u8) !",
+                        s.name, f.name, compress
+                    ));
+                }
+                field_attributes.push("compress = \"lz4\"".to_string());
+            }
+            if f.since.is_some() || f.until.is_some() {
+                let since = f.since.unwrap_or(1);
+                let until = f.until.unwrap_or(255);
+                let Some(version) = s.version else {
+                    return invalid(format!(
+                        "Field '{}::{}' uses 'since' or 'until' but the structure has no version !",
+                        s.name, f.name
+                    ));
+                };
+                if since == 0 || until == 0 || since > until || since > version {
+                    return invalid(format!(
+                        "Invalid versions for field '{}::{}' (since = {}, until = {}, structure version = {}) !",
+                        s.name, f.name, since, until, version
+                    ));
+                }
+                // the field is set to its default value when it is missing from a message
+                if let Some(e) = self.enums.get(f.ty.as_str()) {
+                    if !f.list && e.default.is_none() {
+                        return invalid(format!(
+                            "Field '{}::{}' uses 'since' or 'until' but enum '{}' has no default variant !",
+                            s.name, f.name, e.name
+                        ));
+                    }
+                }
+                if let Some(since) = f.since {
+                    field_attributes.push(format!("since = {}", since));
+                }
+                if let Some(until) = f.until {
+                    field_attributes.push(format!("until = {}", until));
+                }
+            }
+            // same hash as the one computed by #[flat_message]
+            let hash = hashes::field_hash(&f.name, data_format, f.list, f.compress.is_some());
+            if let Some(other) = hashes.insert(hash, f.name.as_str()) {
+                return invalid(format!(
+                    "Fields '{}' and '{}' from '{}' have the same hash (0x{:08X}) !",
+                    other, f.name, s.name, hash
+                ));
+            }
+            fields.push((f, ty, field_attributes, hash));
+        }
+        let o = &mut self.output;
+        // the derives are applied by #[flat_message] (after the metadata field is added)
+        if attributes.is_empty() {
+            o.push_str("\n#[flat_message::flat_message]\n");
+        } else {
+            let _ = writeln!(
+                o,
+                "\n#[flat_message::flat_message({})]",
+                attributes.join(", ")
+            );
+        }
+        o.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+        let _ = writeln!(
+            o,
+            "pub struct {}{} {{",
+            s.name,
+            if lifetime { "<'a>" } else { "" }
+        );
+        for (f, ty, field_attributes, hash) in fields {
+            let _ = writeln!(o, "    // hash: 0x{:08X}", hash);
+            if !field_attributes.is_empty() {
+                let _ = writeln!(o, "    #[flat_message({})]", field_attributes.join(", "));
+            }
+            let _ = writeln!(o, "    pub {}: {},", f.name, ty);
+        }
+        o.push_str("}\n");
+        Ok(())
+    }
+}
+
+pub(crate) fn generate(idl: &Idl) -> Result<String, Error> {
+    Generator::new(idl).run()
+}
@@ -0,0 +1,94 @@
+use serde::Deserialize;
+
+/// Schema file
+/// ```json
+/// {
+///     "enums": [
+///         { "name": "Color", "repr": "u8", "sealed": true, "default": "Red",
+///           "variants": [ { "name": "Red", "value": 1 }, { "name": "Green", "value": 2 } ] }
+///     ],
+///     "structs": [
+///         { "name": "Pixel", "version": 2, "borrowed": true,
+///           "fields": [
+///               { "name": "x", "type": "u32" },
+///               { "name": "color", "type": "Color" },
+///               { "name": "tags", "type": "string", "list": true, "since": 2 }
+///           ] }
+///     ]
+/// }
+/// ```
+/// or the same schema in TOML
+/// ```toml
+/// [[enums]]
+/// name = "Color"
+/// repr = "u8"
+/// sealed = true
+/// default = "Red"
+/// variants = [ { name = "Red", value = 1 }, { name = "Green", value = 2 } ]
+///
+/// [[structs]]
+/// name = "Pixel"
+/// version = 2
+/// borrowed = true
+/// fields = [
+///     { name = "x", type = "u32" },
+///     { name = "color", type = "Color" },
+///     { name = "tags", type = "string", list = true, since = 2 },
+/// ]
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Idl {
+    #[serde(default)]
+    pub(crate) enums: Vec<EnumDef>,
+    #[serde(default)]
+    pub(crate) structs: Vec<StructDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EnumDef {
+    pub(crate) name: String,
+    pub(crate) repr: String,
+    #[serde(default)]
+    pub(crate) sealed: bool,
+    /// the variant returned by `Default::default()` (required if the enum is used by a
+    /// non-list field with `since` or `until`, for messages where the field is missing)
+    pub(crate) default: Option<String>,
+    pub(crate) variants: Vec<VariantDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct VariantDef {
+    pub(crate) name: String,
+    pub(crate) value: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StructDef {
+    pub(crate) name: String,
+    pub(crate) version: Option<u8>,
+    pub(crate) compatible_versions: Option<String>,
+    pub(crate) store_name: Option<bool>,
+    pub(crate) metadata: Option<bool>,
+    pub(crate) checksum: Option<bool>,
+    /// strings and lists are borrowed from the serialized message (`&'a str`, `&'a [T]`)
+    #[serde(default)]
+    pub(crate) borrowed: bool,
+    pub(crate) fields: Vec<FieldDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FieldDef {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) ty: String,
+    #[serde(default)]
+    pub(crate) list: bool,
+    pub(crate) compress: Option<String>,
+    pub(crate) since: Option<u8>,
+    pub(crate) until: Option<u8>,
+}
@@ -0,0 +1,45 @@
+//! Generates `#[flat_message]` structures and `FlatMessageEnum` enums from a schema file (JSON or
+//! TOML), so that the schema is the source of truth. Meant to be used from a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("messages.rs");
+//! flat_message_codegen::generate_file("schema/messages.json", out).unwrap();
+//! println!("cargo:rerun-if-changed=schema/messages.json");
+//! ```
+//! and then `include!(concat!(env!("OUT_DIR"), "/messages.rs"));` in a module of the crate.
+mod error;
+mod generator;
+mod idl;
+
+pub use self::error::Error;
+use std::path::Path;
+
+/// Generates the Rust code for the schema (JSON)
+pub fn generate(schema: &str) -> Result<String, Error> {
+    let idl: idl::Idl = serde_json::from_str(schema).map_err(|e| Error::Parse(e.to_string()))?;
+    generator::generate(&idl)
+}
+
+/// Generates the Rust code for the schema (TOML)
+pub fn generate_from_toml(schema: &str) -> Result<String, Error> {
+    let idl: idl::Idl = toml::from_str(schema).map_err(|e| Error::Parse(e.to_string()))?;
+    generator::generate(&idl)
+}
+
+/// Reads the schema file and writes the generated code into the output file. Files with the
+/// `.toml` extension are read as TOML, all other files as JSON.
+pub fn generate_file<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> Result<(), Error> {
+    let input = input.as_ref();
+    let schema = std::fs::read_to_string(input)?;
+    let code = if input
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+    {
+        generate_from_toml(&schema)?
+    } else {
+        generate(&schema)?
+    };
+    std::fs::write(output, code)?;
+    Ok(())
+}
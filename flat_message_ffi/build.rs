@@ -0,0 +1,16 @@
+fn main() {
+    // the C header is generated only on request (`--features header`), into OUT_DIR
+    #[cfg(feature = "header")]
+    {
+        println!("cargo:rerun-if-changed=src");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("flat_message.h");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("invalid cbindgen.toml");
+        cbindgen::generate_with_config(&crate_dir, config)
+            .expect("failed to generate the C header")
+            .write_to_file(&out);
+        println!("cargo:warning=C header generated in {}", out.display());
+    }
+}
@@ -0,0 +1,123 @@
+#ifndef FLAT_MESSAGE_H
+#define FLAT_MESSAGE_H
+
+/* Generated with cbindgen from flat_message_ffi. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define FM_DATA_FORMAT_U8 1
+
+#define FM_DATA_FORMAT_U16 2
+
+#define FM_DATA_FORMAT_U32 3
+
+#define FM_DATA_FORMAT_U64 4
+
+#define FM_DATA_FORMAT_U128 5
+
+#define FM_DATA_FORMAT_I8 6
+
+#define FM_DATA_FORMAT_I16 7
+
+#define FM_DATA_FORMAT_I32 8
+
+#define FM_DATA_FORMAT_I64 9
+
+#define FM_DATA_FORMAT_I128 10
+
+#define FM_DATA_FORMAT_F32 11
+
+#define FM_DATA_FORMAT_F64 12
+
+#define FM_DATA_FORMAT_BOOL 13
+
+typedef enum FmStatus {
+  FM_STATUS_OK = 0,
+  FM_STATUS_NULL_POINTER,
+  FM_STATUS_UNALIGNED_BUFFER,
+  FM_STATUS_INVALID_MESSAGE,
+  FM_STATUS_INVALID_CHECKSUM,
+  FM_STATUS_COMPRESSED_MESSAGE,
+  FM_STATUS_ENCRYPTED_MESSAGE,
+  // The CRC32 stored in the message does not match its content
+  FM_STATUS_INVALID_HASH,
+} FmStatus;
+
+// Opaque reader over a serialized message
+typedef struct FlatMessageReader FlatMessageReader;
+
+// A UTF-8 string from the message (not null terminated)
+typedef struct FmStringView {
+  const uint8_t *data;
+  size_t len;
+} FmStringView;
+
+// A list of values from the message (`data_format` is the `DataFormat` of the elements)
+typedef struct FmSliceView {
+  const uint8_t *data;
+  size_t len;
+  uint8_t data_format;
+} FmSliceView;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Opens a serialized message. Returns NULL on error (the reason is written in `status` if it
+// is not NULL). The reader must be released with `fm_reader_free`.
+struct FlatMessageReader *fm_reader_open(const uint8_t *data, size_t len, enum FmStatus *status);
+
+void fm_reader_free(struct FlatMessageReader *reader);
+
+// The hash of a name (null terminated UTF-8 string) as it is used for structure names and
+// fields. Returns 0 for NULL or invalid UTF-8.
+uint32_t fm_name_hash(const char *name);
+
+// The hash of the structure name (false if the name was not stored)
+bool fm_reader_name(const struct FlatMessageReader *reader, uint32_t *out);
+
+// The version of the structure (false if the structure has no version)
+bool fm_reader_version(const struct FlatMessageReader *reader, uint8_t *out);
+
+bool fm_reader_timestamp(const struct FlatMessageReader *reader, uint64_t *out);
+
+bool fm_reader_unique_id(const struct FlatMessageReader *reader, uint64_t *out);
+
+// The hash of the first field with the provided name (null terminated UTF-8 string). The low
+// byte of the hash is the type of the field; the hash can be passed to all the `fm_get_...`
+// functions. Returns false if the message has no field with this name.
+bool fm_find_by_name(const struct FlatMessageReader *reader, const char *name, uint32_t *out);
+
+// Reads an integer field (i8, i16, i32, i64, u8, u16, u32 or u64). Returns false for a u64
+// value that does not fit in an i64 (use `fm_get_uint` for these fields).
+bool fm_get_int(const struct FlatMessageReader *reader, uint32_t field, int64_t *out);
+
+// Reads an unsigned integer field (u8, u16, u32 or u64)
+bool fm_get_uint(const struct FlatMessageReader *reader, uint32_t field, uint64_t *out);
+
+// Reads a floating point field (f32 or f64)
+bool fm_get_float(const struct FlatMessageReader *reader, uint32_t field, double *out);
+
+bool fm_get_bool(const struct FlatMessageReader *reader, uint32_t field, bool *out);
+
+// Reads a string field. The view points inside the message buffer.
+bool fm_get_string(const struct FlatMessageReader *reader,
+                   uint32_t field,
+                   struct FmStringView *out);
+
+// Reads a list of numbers or booleans. The view points inside the message buffer and
+// `data_format` tells the type of the elements.
+bool fm_get_slice(const struct FlatMessageReader *reader, uint32_t field, struct FmSliceView *out);
+
+// The size (in bytes) of an element from a slice view (0 for an unknown data format)
+size_t fm_data_format_size(uint8_t data_format);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FLAT_MESSAGE_H */
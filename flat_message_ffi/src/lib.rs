@@ -0,0 +1,342 @@
+//! C API for reading FlatMessage buffers (the header is `include/flat_message.h`, it can be
+//! regenerated with `cargo build -p flat_message_ffi --features header`).
+//!
+//! Fields are identified by the hash of their name (see `fm_name_hash` and `fm_find_by_name`). The buffer passed to
+//! `fm_reader_open` is not copied if it is aligned to 16 bytes: in this case it must outlive the
+//! reader and all the string / slice views obtained from it. An unaligned buffer is copied (the
+//! views point inside the copy and are valid until the reader is released).
+#![allow(clippy::missing_safety_doc)]
+
+use flat_message::{
+    DataFormat, Error, FlatMessageBuffer, Name, SerDe, SerDeSlice, Storage, VecLike,
+};
+use std::ffi::{c_char, CStr};
+
+/// Opaque reader over a serialized message
+pub struct FlatMessageReader {
+    buffer: FlatMessageBuffer<'static>,
+    // the aligned copy of the message (if the buffer was not aligned) => `buffer` points inside
+    // it, so it has to be dropped after `buffer`
+    _copy: Option<Storage>,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FmStatus {
+    Ok = 0,
+    NullPointer,
+    UnalignedBuffer,
+    InvalidMessage,
+    InvalidChecksum,
+    CompressedMessage,
+    EncryptedMessage,
+    /// The CRC32 stored in the message does not match its content
+    InvalidHash,
+}
+
+impl From<Error> for FmStatus {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::UnalignedBuffer => FmStatus::UnalignedBuffer,
+            Error::InvalidHash(_) => FmStatus::InvalidHash,
+            Error::InvalidChecksum(_) => FmStatus::InvalidChecksum,
+            Error::CompressedMessage => FmStatus::CompressedMessage,
+            Error::EncryptedMessage => FmStatus::EncryptedMessage,
+            _ => FmStatus::InvalidMessage,
+        }
+    }
+}
+
+/// A UTF-8 string from the message (not null terminated)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FmStringView {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// A list of values from the message (`data_format` is the `DataFormat` of the elements)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FmSliceView {
+    pub data: *const u8,
+    pub len: usize,
+    pub data_format: u8,
+}
+
+unsafe fn write_status(status: *mut FmStatus, value: FmStatus) {
+    if !status.is_null() {
+        *status = value;
+    }
+}
+
+/// Opens a serialized message. Returns NULL on error (the reason is written in `status` if it
+/// is not NULL). The reader must be released with `fm_reader_free`.
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_open(
+    data: *const u8,
+    len: usize,
+    status: *mut FmStatus,
+) -> *mut FlatMessageReader {
+    if data.is_null() {
+        write_status(status, FmStatus::NullPointer);
+        return std::ptr::null_mut();
+    }
+    let mut buf: &'static [u8] = std::slice::from_raw_parts(data, len);
+    let copy = if (data as usize) & 15 != 0 {
+        let copy = Storage::from_buffer(buf);
+        // the content of the storage (on the heap) does not move and is not modified while the
+        // reader lives
+        buf = std::slice::from_raw_parts(copy.as_slice().as_ptr(), len);
+        Some(copy)
+    } else {
+        None
+    };
+    match FlatMessageBuffer::try_from(buf) {
+        Ok(buffer) => {
+            write_status(status, FmStatus::Ok);
+            Box::into_raw(Box::new(FlatMessageReader {
+                buffer,
+                _copy: copy,
+            }))
+        }
+        Err(e) => {
+            write_status(status, e.into());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_free(reader: *mut FlatMessageReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// The hash of a name (null terminated UTF-8 string) as it is used for structure names and
+/// fields. Returns 0 for NULL or invalid UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn fm_name_hash(name: *const c_char) -> u32 {
+    if name.is_null() {
+        return 0;
+    }
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => common::hashes::fnv_32(name),
+        Err(_) => 0,
+    }
+}
+
+unsafe fn reader<'a>(reader: *const FlatMessageReader) -> Option<&'a FlatMessageBuffer<'static>> {
+    reader.as_ref().map(|r| &r.buffer)
+}
+
+unsafe fn write_value<T>(out: *mut T, value: Option<T>) -> bool {
+    match value {
+        Some(value) if !out.is_null() => {
+            *out = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The hash of the structure name (false if the name was not stored)
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_name(reader: *const FlatMessageReader, out: *mut u32) -> bool {
+    let value = self::reader(reader).and_then(|r| r.name()).map(|n| n.value);
+    write_value(out, value)
+}
+
+/// The version of the structure (false if the structure has no version)
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_version(reader: *const FlatMessageReader, out: *mut u8) -> bool {
+    write_value(out, self::reader(reader).and_then(|r| r.version()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_timestamp(
+    reader: *const FlatMessageReader,
+    out: *mut u64,
+) -> bool {
+    let value = self::reader(reader).and_then(|r| r.metadata().timestamp());
+    write_value(out, value)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fm_reader_unique_id(
+    reader: *const FlatMessageReader,
+    out: *mut u64,
+) -> bool {
+    let value = self::reader(reader).and_then(|r| r.metadata().unique_id());
+    write_value(out, value)
+}
+
+fn get<'a, T: SerDe<'a>>(buffer: &'a FlatMessageBuffer<'static>, field: u32) -> Option<T> {
+    buffer.get::<T>(Name::new(field))
+}
+
+/// The hash of the first field with the provided name (null terminated UTF-8 string). The low
+/// byte of the hash is the type of the field; the hash can be passed to all the `fm_get_...`
+/// functions. Returns false if the message has no field with this name.
+#[no_mangle]
+pub unsafe extern "C" fn fm_find_by_name(
+    reader: *const FlatMessageReader,
+    name: *const c_char,
+    out: *mut u32,
+) -> bool {
+    if name.is_null() {
+        return false;
+    }
+    let name = fm_name_hash(name) & 0xFFFFFF00;
+    let value =
+        self::reader(reader).and_then(|r| r.fields().find(|hash| hash & 0xFFFFFF00 == name));
+    write_value(out, value)
+}
+
+/// Reads an integer field (i8, i16, i32, i64, u8, u16, u32 or u64). Returns false for a u64
+/// value that does not fit in an i64 (use `fm_get_uint` for these fields).
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_int(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut i64,
+) -> bool {
+    let value = self::reader(reader).and_then(|r| {
+        get::<i64>(r, field)
+            .or_else(|| get::<i32>(r, field).map(i64::from))
+            .or_else(|| get::<i16>(r, field).map(i64::from))
+            .or_else(|| get::<i8>(r, field).map(i64::from))
+            .or_else(|| get::<u32>(r, field).map(i64::from))
+            .or_else(|| get::<u16>(r, field).map(i64::from))
+            .or_else(|| get::<u8>(r, field).map(i64::from))
+            .or_else(|| get::<u64>(r, field).and_then(|v| i64::try_from(v).ok()))
+    });
+    write_value(out, value)
+}
+
+/// Reads an unsigned integer field (u8, u16, u32 or u64)
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_uint(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut u64,
+) -> bool {
+    let value = self::reader(reader).and_then(|r| {
+        get::<u64>(r, field)
+            .or_else(|| get::<u32>(r, field).map(u64::from))
+            .or_else(|| get::<u16>(r, field).map(u64::from))
+            .or_else(|| get::<u8>(r, field).map(u64::from))
+    });
+    write_value(out, value)
+}
+
+/// Reads a floating point field (f32 or f64)
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_float(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut f64,
+) -> bool {
+    let value = self::reader(reader)
+        .and_then(|r| get::<f64>(r, field).or_else(|| get::<f32>(r, field).map(f64::from)));
+    write_value(out, value)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_bool(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut bool,
+) -> bool {
+    write_value(
+        out,
+        self::reader(reader).and_then(|r| get::<bool>(r, field)),
+    )
+}
+
+/// Reads a string field. The view points inside the message buffer.
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_string(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut FmStringView,
+) -> bool {
+    let value = self::reader(reader)
+        .and_then(|r| get::<&str>(r, field))
+        .map(|s| FmStringView {
+            data: s.as_ptr(),
+            len: s.len(),
+        });
+    write_value(out, value)
+}
+
+fn slice_view<'a, T: SerDeSlice<'a> + 'a>(
+    buffer: &'a FlatMessageBuffer<'static>,
+    field: u32,
+) -> Option<FmSliceView> {
+    buffer
+        .get_slice::<T>(Name::new(field))
+        .map(|s| FmSliceView {
+            data: s.as_ptr() as *const u8,
+            len: s.len(),
+            data_format: T::DATA_FORMAT as u8,
+        })
+}
+
+/// Reads a list of numbers or booleans. The view points inside the message buffer and
+/// `data_format` tells the type of the elements.
+#[no_mangle]
+pub unsafe extern "C" fn fm_get_slice(
+    reader: *const FlatMessageReader,
+    field: u32,
+    out: *mut FmSliceView,
+) -> bool {
+    let value = self::reader(reader).and_then(|r| {
+        slice_view::<u8>(r, field)
+            .or_else(|| slice_view::<u16>(r, field))
+            .or_else(|| slice_view::<u32>(r, field))
+            .or_else(|| slice_view::<u64>(r, field))
+            .or_else(|| slice_view::<u128>(r, field))
+            .or_else(|| slice_view::<i8>(r, field))
+            .or_else(|| slice_view::<i16>(r, field))
+            .or_else(|| slice_view::<i32>(r, field))
+            .or_else(|| slice_view::<i64>(r, field))
+            .or_else(|| slice_view::<i128>(r, field))
+            .or_else(|| slice_view::<f32>(r, field))
+            .or_else(|| slice_view::<f64>(r, field))
+            .or_else(|| slice_view::<bool>(r, field))
+    });
+    write_value(out, value)
+}
+
+// the values of `FmSliceView::data_format` (the same as `DataFormat`)
+pub const FM_DATA_FORMAT_U8: u8 = 1;
+pub const FM_DATA_FORMAT_U16: u8 = 2;
+pub const FM_DATA_FORMAT_U32: u8 = 3;
+pub const FM_DATA_FORMAT_U64: u8 = 4;
+pub const FM_DATA_FORMAT_U128: u8 = 5;
+pub const FM_DATA_FORMAT_I8: u8 = 6;
+pub const FM_DATA_FORMAT_I16: u8 = 7;
+pub const FM_DATA_FORMAT_I32: u8 = 8;
+pub const FM_DATA_FORMAT_I64: u8 = 9;
+pub const FM_DATA_FORMAT_I128: u8 = 10;
+pub const FM_DATA_FORMAT_F32: u8 = 11;
+pub const FM_DATA_FORMAT_F64: u8 = 12;
+pub const FM_DATA_FORMAT_BOOL: u8 = 13;
+const _: () = assert!(
+    FM_DATA_FORMAT_U8 == DataFormat::U8 as u8 && FM_DATA_FORMAT_BOOL == DataFormat::Bool as u8
+);
+
+/// The size (in bytes) of an element from a slice view (0 for an unknown data format)
+#[no_mangle]
+pub extern "C" fn fm_data_format_size(data_format: u8) -> usize {
+    match data_format {
+        FM_DATA_FORMAT_U8 | FM_DATA_FORMAT_I8 | FM_DATA_FORMAT_BOOL => 1,
+        FM_DATA_FORMAT_U16 | FM_DATA_FORMAT_I16 => 2,
+        FM_DATA_FORMAT_U32 | FM_DATA_FORMAT_I32 | FM_DATA_FORMAT_F32 => 4,
+        FM_DATA_FORMAT_U64 | FM_DATA_FORMAT_I64 | FM_DATA_FORMAT_F64 => 8,
+        FM_DATA_FORMAT_U128 | FM_DATA_FORMAT_I128 => 16,
+        _ => 0,
+    }
+}
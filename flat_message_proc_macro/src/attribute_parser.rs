@@ -22,6 +22,11 @@ pub(crate) fn parse(attr: TokenStream) -> HashMap<String, String> {
                         let attr_value = match it.next() {
                             Some(TokenTree::Ident(ident)) => ident.to_string(),
                             Some(TokenTree::Literal(lit)) => lit.to_string(),
+                            Some(TokenTree::Group(group))
+                                if group.delimiter() == Delimiter::Bracket =>
+                            {
+                                group.to_string()
+                            }
                             _ => panic!("Expecting a value for attribute: '{}'", attr_name),
                         };
                         m.insert(attr_name, attr_value);
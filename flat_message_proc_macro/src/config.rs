@@ -13,6 +13,7 @@ pub(crate) struct Config {
     pub(crate) validate_name: bool,
     pub(crate) compatible_versions: Option<VersionValidatorParser>,
     pub(crate) validate_checksum: ValidateChecksum,
+    pub(crate) migrate_from: Vec<syn::Type>,
 }
 
 impl Config {
@@ -24,6 +25,7 @@ impl Config {
         let mut version = 0u8;
         let mut compatible_versions = None;
         let mut validate_checksum = ValidateChecksum::Auto;
+        let mut migrate_from = Vec::new();
 
         let attrs = attribute_parser::parse(args);
         for (attr_name, attr_value) in attrs.iter() {
@@ -40,8 +42,14 @@ impl Config {
                         Err(def) => panic!("Fail to parse compatible_versions: {}", def),
                     }
                 }
+                "migrate_from" => {
+                    match utils::to_type_list(attr_value) {
+                        Ok(types) => migrate_from = types,
+                        Err(def) => panic!("Fail to parse migrate_from: {}", def),
+                    }
+                }
                 _ => {
-                    panic!("Unknown attribute: {}. Supported attributes are: 'store_name', 'metadata', 'checksum', validate_name', 'compatible_versions' and 'version' !", attr_name);
+                    panic!("Unknown attribute: {}. Supported attributes are: 'store_name', 'metadata', 'checksum', validate_name', 'compatible_versions', 'migrate_from' and 'version' !", attr_name);
                 }
             }
         }
@@ -50,6 +58,10 @@ impl Config {
             panic!("You can not use the attribute 'validate_name' with value 'true' unless the attribute 'store_name' is also set to 'true'.  If this was allowed, you will not be able to deserialize a structure of this type !");
         }
 
+        if !migrate_from.is_empty() && version == 0 {
+            panic!("The attribute 'migrate_from' requires the structure to have a version (e.g. 'version: 2') !");
+        }
+
         Self {
             namehash: store_name,
             metadata: add_metadata,
@@ -58,6 +70,7 @@ impl Config {
             validate_name,
             validate_checksum,
             compatible_versions,
+            migrate_from,
         }
     }
 }
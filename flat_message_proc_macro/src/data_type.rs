@@ -7,50 +7,189 @@ pub(crate) enum FieldType {
     Object,
     Slice,
     Vector,
+    /// `[T; N]` or `&[T; N]` (stored as a slice)
+    Array,
+    /// `HashMap<K, V>`, `BTreeMap<K, V>` or `MapView` (stored as a nested message)
+    Map,
 }
 
 impl FieldType {
     pub(crate) fn serde_trait(&self) -> &'static str {
         match self {
-            FieldType::Object => "SerDe",
+            FieldType::Object | FieldType::Array | FieldType::Map => "SerDe",
             FieldType::Slice => "SerDeSlice",
             FieldType::Vector => "SerDeVec",
         }
     }
+    /// The trait implemented by the type of the elements (for enums)
+    pub(crate) fn element_serde_trait(&self) -> &'static str {
+        match self {
+            FieldType::Array => "SerDeSlice",
+            _ => self.serde_trait(),
+        }
+    }
+}
+
+// the type of the elements of `[T; N]` or `&[T; N]`
+fn array_element(ty: &syn::Type) -> Option<String> {
+    let array = match ty {
+        syn::Type::Array(array) => array,
+        syn::Type::Reference(reference) => match reference.elem.as_ref() {
+            syn::Type::Array(array) => array,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let elem = &array.elem;
+    let mut name = quote::quote! {#elem}.to_string();
+    utils::type_name_formatter(&mut name);
+    Some(name)
+}
+
+// `HashMap<K, V>`, `BTreeMap<K, V>` or `MapView` (with or without a path)
+fn is_map(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.path.segments.last().is_some_and(|segment| {
+        matches!(
+            segment.ident.to_string().as_str(),
+            "HashMap" | "BTreeMap" | "MapView"
+        )
+    })
+}
+
+fn path_idents(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.ident.to_string()).collect()
+}
+
+// `Cow<[u8]>` or `Cow<'a, [u8]>` (as `Cow`, `borrow::Cow` or `std::borrow::Cow`)
+fn is_cow_of_bytes(path: &syn::Path) -> bool {
+    let idents = path_idents(path);
+    let idents: Vec<&str> = idents.iter().map(String::as_str).collect();
+    if !matches!(
+        idents.as_slice(),
+        ["Cow"] | ["borrow", "Cow"] | ["std" | "alloc", "borrow", "Cow"]
+    ) {
+        return false;
+    }
+    let Some(syn::PathArguments::AngleBracketed(args)) = path.segments.last().map(|s| &s.arguments)
+    else {
+        return false;
+    };
+    let mut types = args.args.iter().filter_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+    match (types.next(), types.next()) {
+        (Some(syn::Type::Slice(slice)), None) => {
+            matches!(&*slice.elem, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("u8"))
+        }
+        _ => false,
+    }
+}
+
+// `Cow<[u8]>` or `bytes::Bytes` (opaque binary data, stored as `DataFormat::Bytes`). A `Bytes`
+// type without the `bytes::` path has to be marked with `kind = bytes` (it could be any type).
+fn is_bytes(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    if path.qself.is_some() {
+        return false;
+    }
+    is_cow_of_bytes(&path.path)
+        || (path_idents(&path.path) == ["bytes", "Bytes"]
+            && path
+                .path
+                .segments
+                .last()
+                .is_some_and(|s| s.arguments.is_empty()))
+}
+
+// a type named `Bytes` (without generics) that can be marked with `kind = bytes`
+fn is_bytes_name(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.path
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "Bytes" && s.arguments.is_empty())
 }
 
+// nested messages (maps) are aligned to 16 bytes
+const MAP_ALIGNMENT: usize = 16;
+// the data of bytes fields can be aligned up to a page (relative to the start of the message,
+// for direct I/O when the message is written at an aligned file offset)
+const MAX_BYTES_ALIGNMENT: usize = 4096;
+
 pub(crate) struct DataType {
     pub(crate) field_type: FieldType,
     pub(crate) data_format: DataFormat,
     pub(crate) name: String,
     pub(crate) ty: syn::Type,
+    pub(crate) compressed: bool,
+    /// The alignment of the data of a bytes field (`#[flat_message(align = N)]`)
+    pub(crate) bytes_alignment: usize,
 }
 
 impl DataType {
-    pub(crate) fn type_hash(&self) -> u32 {
-        match self.field_type {
-            FieldType::Object => self.data_format as u32,
-            FieldType::Slice | FieldType::Vector => (self.data_format as u32) | 0x80,
-        }
+    pub(crate) fn is_list(&self) -> bool {
+        !matches!(self.field_type, FieldType::Object | FieldType::Map)
     }
     pub(crate) fn new(ty: syn::Type, mut def: String) -> Self {
+        if let Some(name) = array_element(&ty) {
+            return DataType {
+                field_type: FieldType::Array,
+                data_format: DataFormat::from(name.as_str()),
+                name,
+                ty,
+                compressed: false,
+                bytes_alignment: 1,
+            };
+        }
         utils::type_name_formatter(&mut def);
-        if def.starts_with("Vec<") {
-            def = def.replace("Vec<", "").replace(">", "");
+        if is_map(&ty) {
+            return DataType {
+                field_type: FieldType::Map,
+                data_format: DataFormat::GenericObject,
+                name: def,
+                ty,
+                compressed: false,
+                bytes_alignment: 1,
+            };
+        }
+        if is_bytes(&ty) {
+            return DataType {
+                field_type: FieldType::Object,
+                data_format: DataFormat::Bytes,
+                name: def,
+                ty,
+                compressed: false,
+                bytes_alignment: 1,
+            };
+        }
+        if let Some(element) = def.strip_prefix("Vec<").and_then(|d| d.strip_suffix(">")) {
+            def = element.to_string();
             return DataType {
                 field_type: FieldType::Vector,
                 data_format: DataFormat::from(def.as_str()),
                 name: def,
                 ty,
+                compressed: false,
+                bytes_alignment: 1,
             };
         }
-        if def.starts_with("&[") {
-            def = def.replace("&[", "").replace("]", "");
+        if let Some(element) = def.strip_prefix("&[").and_then(|d| d.strip_suffix("]")) {
+            def = element.to_string();
             return DataType {
                 field_type: FieldType::Slice,
                 data_format: DataFormat::from(def.as_str()),
                 name: def,
                 ty,
+                compressed: false,
+                bytes_alignment: 1,
             };
         }
         DataType {
@@ -58,6 +197,8 @@ impl DataType {
             data_format: DataFormat::from(def.as_str()),
             name: def,
             ty,
+            compressed: false,
+            bytes_alignment: 1,
         }
     }
 
@@ -65,6 +206,25 @@ impl DataType {
         &mut self,
         attr: &HashMap<String, String>,
         field_nane: &str,
+    ) -> Result<(), String> {
+        if let Some(compress) = attr.get("compress") {
+            self.update_compression(compress.replace("\"", "").as_str(), field_nane)?;
+        }
+        if attr.get("kind").is_some_and(|kind| kind == "bytes") {
+            self.update_bytes(attr.contains_key("repr"), field_nane)?;
+        } else {
+            self.update_kind(attr, field_nane)?;
+        }
+        if let Some(align) = attr.get("align") {
+            self.update_alignment(align, field_nane)?;
+        }
+        Ok(())
+    }
+
+    fn update_kind(
+        &mut self,
+        attr: &HashMap<String, String>,
+        field_nane: &str,
     ) -> Result<(), String> {
         let has_repr = attr.contains_key("repr");
         let has_kind = attr.contains_key("kind");
@@ -77,6 +237,9 @@ impl DataType {
         if !has_repr && has_kind {
             return Err(format!("If we provided the 'kind' attribute you need to also provide the attribute 'repr' (for field: '{}')",field_nane));
         }
+        if let FieldType::Map = self.field_type {
+            return Err(format!("The 'kind' and 'repr' attributes can not be used for a map field (field: '{}'). Keys and values that are enums are detected automatically !", field_nane));
+        }
         // kind and repr are present
         let kind = attr.get("kind").unwrap();
         let repr = attr.get("repr").unwrap();
@@ -90,15 +253,72 @@ impl DataType {
             return Ok(());
         }
         Err(format!(
-            "Invalid kind: '{}' in field: '{}'. The possible kinds are: 'enum'.",
+            "Invalid kind: '{}' in field: '{}'. The possible kinds are: 'enum' and 'bytes'.",
             kind, field_nane
         ))
     }
 
+    fn update_bytes(&mut self, has_repr: bool, field_nane: &str) -> Result<(), String> {
+        if has_repr {
+            return Err(format!("The 'repr' attribute can not be used for a field with 'kind = bytes' (field: '{}') !", field_nane));
+        }
+        let supported = match self.field_type {
+            FieldType::Vector | FieldType::Slice => self.name == "u8",
+            FieldType::Object => is_bytes_name(&self.ty),
+            _ => false,
+        };
+        if !supported {
+            return Err(format!("Only fields of type Vec<u8>, &[u8] or Bytes (from the bytes crate) can use 'kind = bytes' (field: '{}'). Fields of type Cow<[u8]> or bytes::Bytes are always stored as bytes !", field_nane));
+        }
+        if self.compressed {
+            return Err(format!(
+                "A field with 'kind = bytes' can not be compressed (field: '{}') !",
+                field_nane
+            ));
+        }
+        self.field_type = FieldType::Object;
+        self.data_format = DataFormat::Bytes;
+        Ok(())
+    }
+
+    fn update_alignment(&mut self, align: &str, field_nane: &str) -> Result<(), String> {
+        if self.data_format != DataFormat::Bytes {
+            return Err(format!("The 'align' attribute can only be used for bytes fields (Vec<u8> or &[u8] with 'kind = bytes', Cow<[u8]> or bytes::Bytes) (field: '{}') !", field_nane));
+        }
+        self.bytes_alignment = align
+            .parse::<usize>()
+            .ok()
+            .filter(|value| value.is_power_of_two() && *value <= MAX_BYTES_ALIGNMENT)
+            .ok_or(format!("Invalid alignment: '{}' in field: '{}'. The alignment must be a power of two between 1 and {} !", align, field_nane, MAX_BYTES_ALIGNMENT))?;
+        Ok(())
+    }
+
+    fn update_compression(&mut self, compress: &str, field_nane: &str) -> Result<(), String> {
+        if compress != "lz4" {
+            return Err(format!(
+                "Invalid compression: '{}' in field: '{}'. The possible values are: 'lz4'.",
+                compress, field_nane
+            ));
+        }
+        let supported = match self.field_type {
+            FieldType::Object => self.name == "String",
+            FieldType::Vector => matches!(self.name.as_str(), "u8" | "String"),
+            FieldType::Slice | FieldType::Array | FieldType::Map => false,
+        };
+        if !supported {
+            return Err(format!("Compression is only supported for fields of type String, Vec<u8> or Vec<String> (field: '{}'). Borrowed types (such as &str, &[u8] or Vec<&str>) can not be used as a compressed field is decompressed into an owned value !", field_nane));
+        }
+        self.compressed = true;
+        Ok(())
+    }
+
     pub(crate) fn serialization_alignment(&self) -> usize {
         match self.field_type {
-            FieldType::Object => 1,
-            FieldType::Slice | FieldType::Vector => self.data_format.alignament() as usize,
+            FieldType::Object => self.bytes_alignment,
+            FieldType::Map => MAP_ALIGNMENT,
+            FieldType::Slice | FieldType::Vector | FieldType::Array => {
+                self.data_format.alignament() as usize
+            }
         }
     }
 }
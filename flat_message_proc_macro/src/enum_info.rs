@@ -67,26 +67,26 @@ impl EnumInfo {
         let name = &self.name;
 
         quote! {
-            unsafe impl SerDeVec<'_> for #name {
+            unsafe impl flat_message::SerDeVec<'_> for #name {
                 const DATA_FORMAT: flat_message::DataFormat = #data_format;
 
                 #[inline(always)]
                 unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Vec<Self> {
-                    let res: &[#name] = SerDeSlice::from_buffer_unchecked(buf, pos);
+                    let res: &[#name] = flat_message::SerDeSlice::from_buffer_unchecked(buf, pos);
                     res.to_vec()
                 }
                 #[inline(always)]
                 fn from_buffer(buf: &[u8], pos: usize) -> Option<Vec<Self>> {
-                    let res: &[#name] = SerDeSlice::from_buffer(buf, pos)?;
+                    let res: &[#name] = flat_message::SerDeSlice::from_buffer(buf, pos)?;
                     Some(res.to_vec())
                 }
                 #[inline(always)]
                 unsafe fn write(obj: &Vec<Self>, p: *mut u8, pos: usize) -> usize {
-                    SerDeSlice::write(obj.as_slice(), p, pos)
+                    flat_message::SerDeSlice::write(obj.as_slice(), p, pos)
                 }
                 #[inline(always)]
                 fn size(obj: &Vec<Self>) -> usize {
-                    SerDeSlice::size(obj.as_slice())
+                    flat_message::SerDeSlice::size(obj.as_slice())
                 }
             }
         }
@@ -125,7 +125,7 @@ impl EnumInfo {
         };
 
         quote! {
-            unsafe impl<'a> SerDeSlice<'a> for #name {
+            unsafe impl<'a> flat_message::SerDeSlice<'a> for #name {
                 const DATA_FORMAT: flat_message::DataFormat = #data_format;
                 #[inline(always)]
                 unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> &'a [Self] {
@@ -154,7 +154,8 @@ impl EnumInfo {
                         flat_message::size::Format::#size_format,
                     )?;
                     let end = pos + size_len + count #multiplier;
-                    if end > buf.len() {
+                    // the buffer might not be aligned (if it is not a Storage object)
+                    if end > buf.len() || !buf.as_ptr().wrapping_add(pos + size_len).cast::<#name>().is_aligned() {
                         None
                     } else {
                         let slice = #slice
@@ -201,10 +202,16 @@ impl EnumInfo {
         let repr_type = self.repr.repr_type();
         let name_hash = self.compute_hash();
         let variant_validation = self.generate_variant_validation_match(true);
+        let variants = self.variants.iter().map(|(variant, value)| {
+            let value = proc_macro2::Literal::i128_unsuffixed(*value);
+            quote! { (#variant, #value), }
+        });
 
         quote! {
-            unsafe impl<'a> SerDe<'a> for #name {
+            unsafe impl<'a> flat_message::SerDe<'a> for #name {
                 const DATA_FORMAT: flat_message::DataFormat = #data_format;
+                const ENUM_HASH: Option<u32> = Some(#name_hash);
+                const ENUM_VARIANTS: &'static [(&'static str, i128)] = &[#(#variants)*];
                 #[inline(always)]
                 unsafe fn from_buffer_unchecked(buf: &[u8], pos: usize) -> Self {
                     unsafe {
@@ -242,16 +249,56 @@ impl EnumInfo {
             }
         }
     }
+    fn generate_map_item_implementation(&self) -> TokenStream {
+        let name = &self.name;
+        let data_format = self.repr.data_format();
+        let name_hash = self.compute_hash();
+        // the same layout as for slices
+        let size_format = match self.repr {
+            EnumMemoryRepresentation::U8 | EnumMemoryRepresentation::I8 => {
+                quote! { U8withExtension }
+            }
+            EnumMemoryRepresentation::U16 | EnumMemoryRepresentation::I16 => {
+                quote! { U16withExtension }
+            }
+            _ => quote! { U32 },
+        };
+        quote! {
+            unsafe impl<'a> flat_message::SerDeMapItem<'a> for #name {
+                const DATA_FORMAT: flat_message::DataFormat = #data_format;
+                #[inline(always)]
+                fn list_size<'b>(items: impl ExactSizeIterator<Item = &'b Self>) -> usize {
+                    4 /* name hash */ + flat_message::size::list_len::<#name>(items.len(), flat_message::size::Format::#size_format)
+                }
+                #[inline(always)]
+                unsafe fn write_list<'b>(items: impl ExactSizeIterator<Item = &'b Self>, p: *mut u8, pos: usize) -> usize {
+                    std::ptr::write_unaligned(p.add(pos) as *mut u32, #name_hash);
+                    flat_message::size::write_list(items, p, pos + 4, flat_message::size::Format::#size_format)
+                }
+                #[inline(always)]
+                fn read_list(buf: &'a [u8], pos: usize) -> Option<Vec<Self>> {
+                    <#name as flat_message::SerDeSlice>::from_buffer(buf, pos).map(|items| items.to_vec())
+                }
+                #[inline(always)]
+                unsafe fn read_list_unchecked(buf: &'a [u8], pos: usize) -> Vec<Self> {
+                    <#name as flat_message::SerDeSlice>::from_buffer_unchecked(buf, pos).to_vec()
+                }
+            }
+        }
+    }
     pub fn generate_code(&self) -> TokenStream {
         let serde_code = self.generate_serde_implementation();
         let slice_code = self.generate_slice_serde_implementation();
         let vec_code = self.generate_vector_serde_implementation();
+        let map_item_code = self.generate_map_item_implementation();
         quote! {
             #serde_code
             // for slices
             #slice_code
             // for vectors
             #vec_code
+            // for keys and values of maps
+            #map_item_code
         }
     }
 }
@@ -15,14 +15,14 @@ pub(crate) enum EnumMemoryRepresentation {
 impl EnumMemoryRepresentation {
     pub(crate) fn data_format(&self) -> proc_macro2::TokenStream {
         match self {
-            EnumMemoryRepresentation::U8 => quote! {DataFormat::EnumU8},
-            EnumMemoryRepresentation::U16 => quote! {DataFormat::EnumU16},
-            EnumMemoryRepresentation::U32 => quote! {DataFormat::EnumU32},
-            EnumMemoryRepresentation::U64 => quote! {DataFormat::EnumU64},
-            EnumMemoryRepresentation::I8 => quote! {DataFormat::EnumI8},
-            EnumMemoryRepresentation::I16 => quote! {DataFormat::EnumI16},
-            EnumMemoryRepresentation::I32 => quote! {DataFormat::EnumI32},
-            EnumMemoryRepresentation::I64 => quote! {DataFormat::EnumI64},
+            EnumMemoryRepresentation::U8 => quote! {flat_message::DataFormat::EnumU8},
+            EnumMemoryRepresentation::U16 => quote! {flat_message::DataFormat::EnumU16},
+            EnumMemoryRepresentation::U32 => quote! {flat_message::DataFormat::EnumU32},
+            EnumMemoryRepresentation::U64 => quote! {flat_message::DataFormat::EnumU64},
+            EnumMemoryRepresentation::I8 => quote! {flat_message::DataFormat::EnumI8},
+            EnumMemoryRepresentation::I16 => quote! {flat_message::DataFormat::EnumI16},
+            EnumMemoryRepresentation::I32 => quote! {flat_message::DataFormat::EnumI32},
+            EnumMemoryRepresentation::I64 => quote! {flat_message::DataFormat::EnumI64},
             EnumMemoryRepresentation::NotDefined => quote!(),
         }
     }
@@ -1,4 +1,5 @@
-use super::attribute_parser;
+use super::{attribute_parser, utils};
+use common::data_format::DataFormat;
 use common::hashes;
 use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
@@ -11,8 +12,18 @@ pub(crate) struct FieldInfo {
     pub(crate) hash: u32,
     pub(crate) hash_table_order: u32,
     pub(crate) data_type: DataType,
+    pub(crate) since: u8,
+    pub(crate) until: u8,
 }
 impl FieldInfo {
+    /// The interval of versions where the field must exist (`None` if it must exist in all versions)
+    pub(crate) fn versions(&self) -> Option<(u8, u8)> {
+        if self.since > 1 || self.until < 255 {
+            Some((self.since, self.until))
+        } else {
+            None
+        }
+    }
     pub(crate) fn inner_var(&self) -> syn::Ident {
         syn::Ident::new(
             format!("inner_var_{}_{}", &self.name, self.hash).as_str(),
@@ -25,8 +36,16 @@ impl FieldInfo {
     }
     #[inline(always)]
     pub(crate) fn serialization_trait(&self) -> syn::Ident {
+        let serde_trait = if self.data_type.compressed {
+            "SerDeCompressed"
+        } else {
+            self.data_type.field_type.serde_trait()
+        };
+        syn::Ident::new(serde_trait, proc_macro2::Span::call_site())
+    }
+    pub(crate) fn compressed_var(&self) -> syn::Ident {
         syn::Ident::new(
-            self.data_type.field_type.serde_trait(),
+            format!("compressed_var_{}_{}", &self.name, self.hash).as_str(),
             proc_macro2::Span::call_site(),
         )
     }
@@ -34,6 +53,22 @@ impl FieldInfo {
     pub(crate) fn serialization_alignment(&self) -> usize {
         self.data_type.serialization_alignment()
     }
+    /// Aligns `pos` (the position where the field is written). Bytes fields start with a `u32`
+    /// length, so the field is placed 4 bytes before the alignment (the data is aligned).
+    pub(crate) fn alignment_code(&self, pos: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let alignment = self.serialization_alignment();
+        if alignment <= 1 {
+            quote! {}
+        } else if self.data_type.data_format == DataFormat::Bytes {
+            quote! {
+                #pos = ((#pos + 4 + #alignment - 1) & !(#alignment - 1)) - 4;
+            }
+        } else {
+            quote! {
+                #pos = (#pos + #alignment - 1) & !(#alignment - 1);
+            }
+        }
+    }
 }
 impl TryFrom<&Field> for FieldInfo {
     type Error = String;
@@ -47,6 +82,8 @@ impl TryFrom<&Field> for FieldInfo {
         let name = field.ident.as_ref().unwrap().to_string();
         let ty = &field.ty;
         let mut data_type = DataType::new(ty.clone(), quote! {#ty}.to_string());
+        let mut since = 1u8;
+        let mut until = 255u8;
         for attr in field.attrs.iter() {
             if attr.path().is_ident("flat_message") {
                 let all_tokens = attr.meta.clone().into_token_stream();
@@ -62,6 +99,12 @@ impl TryFrom<&Field> for FieldInfo {
                 }
                 let attr = attribute_parser::parse(tokens);
                 data_type.update(&attr, name.as_str())?;
+                if let Some(value) = attr.get("since") {
+                    since = utils::to_version(value).ok_or(format!("Invalid version value ('{}') for attribute 'since' in field '{}'. Allowed values are between 1 and 255 !", value, name))?;
+                }
+                if let Some(value) = attr.get("until") {
+                    until = utils::to_version(value).ok_or(format!("Invalid version value ('{}') for attribute 'until' in field '{}'. Allowed values are between 1 and 255 !", value, name))?;
+                }
             } else {
                 return Err(format!(
                     "Attribute '{}' is not supported for field '{}'",
@@ -70,13 +113,26 @@ impl TryFrom<&Field> for FieldInfo {
                 ));
             }
         }
+        if since > until {
+            return Err(format!(
+                "Field '{}' has the 'since' version ({}) greater than the 'until' version ({}) !",
+                name, since, until
+            ));
+        }
         // compute the data format
-        let hash = (hashes::fnv_32(&name) & 0xFFFFFF00) | data_type.type_hash();
+        let hash = hashes::field_hash(
+            &name,
+            data_type.data_format,
+            data_type.is_list(),
+            data_type.compressed,
+        );
         Ok(FieldInfo {
             name,
             hash,
             hash_table_order: 0,
             data_type,
+            since,
+            until,
         })
     }
 }
@@ -5,6 +5,7 @@ mod enum_info;
 mod enum_memory_representation;
 mod field_info;
 mod struct_info;
+mod union_info;
 mod utils;
 mod validate_checksum;
 mod version_validator_parser;
@@ -60,6 +61,21 @@ pub fn flat_message_enum(input: TokenStream) -> TokenStream {
     ei.generate_code().into()
 }
 
+#[proc_macro_derive(FlatMessageUnion)]
+pub fn flat_message_union(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    let ui = match union_info::UnionInfo::try_from(input) {
+        Ok(ui) => ui,
+        Err(e) => {
+            return quote::quote! {
+                compile_error!(#e);
+            }
+            .into();
+        }
+    };
+    ui.generate_code().into()
+}
+
 #[proc_macro]
 pub fn name(input: TokenStream) -> TokenStream {
     let value = utils::validate_one_string_parameter(input, "name");
@@ -3,7 +3,7 @@ use crate::data_type::FieldType;
 use crate::field_info::FieldInfo;
 use common::constants;
 use common::hashes;
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::Attribute;
 use syn::{DataStruct, DeriveInput, FieldsNamed};
 
@@ -61,6 +61,13 @@ impl<'a> StructInfo<'a> {
                 metadata_offset+=4;
             });
         }
+        let mac_size = constants::MAC_SIZE;
+        lines.push(quote! {
+            if let Some(key) = config.authentication_key() {
+                let mac = flat_message::authentication::compute_mac(key, &output[..metadata_offset]);
+                ptr::copy_nonoverlapping(mac.as_ptr(), buffer.add(metadata_offset), #mac_size);
+            }
+        });
         lines.push(quote! {
             debug_assert_eq!(size, output.len());
         });
@@ -91,6 +98,14 @@ impl<'a> StructInfo<'a> {
                 metainfo_size += 4;
             });
         }
+        let mac_flag = constants::FLAG_HAS_MAC;
+        let mac_size = constants::MAC_SIZE;
+        lines.push(quote! {
+            if config.authentication_key().is_some() {
+                flags |= #mac_flag;
+                metainfo_size += #mac_size;
+            }
+        });
         if self.config.checksum {
             lines.push(quote! {
                 flags |= #checksum_flag;
@@ -103,14 +118,17 @@ impl<'a> StructInfo<'a> {
         let compute_size_code = self.fields.iter().map(|field| {
             let field_name = field.name_ident();
             let serialization_trait = field.serialization_trait();
-            let serialization_alignment = field.serialization_alignment();
-            if serialization_alignment>1 {
+            if field.data_type.compressed {
+                // compress once (the result is used when the fields are serialized)
+                let compressed_var = field.compressed_var();
                 quote! {
-                    size = (size + #serialization_alignment - 1) & !(#serialization_alignment - 1);
-                    size += ::flat_message::#serialization_trait::size(&self.#field_name);
+                    let #compressed_var = ::flat_message::SerDeCompressed::compress(&self.#field_name);
+                    size += #compressed_var.len();
                 }
             } else {
+                let alignment_code = field.alignment_code(quote! {size});
                 quote! {
+                    #alignment_code
                     size += ::flat_message::#serialization_trait::size(&self.#field_name);
                 }
             }
@@ -163,18 +181,7 @@ impl<'a> StructInfo<'a> {
             let field_name = syn::Ident::new(field.name.as_str(), proc_macro2::Span::call_site());
             let hash_table_order = field.hash_table_order as usize;
             let serde_trait = field.serialization_trait();
-            let serialization_alignment = field.serialization_alignment();
-            let alignament_code = match field.data_type.field_type {
-                FieldType::Object => quote! {},
-                FieldType::Slice | FieldType::Vector => 
-                if serialization_alignment>1 {
-                    quote! {
-                        buf_pos = (buf_pos + #serialization_alignment - 1) & !(#serialization_alignment - 1);
-                    }
-                } else {
-                    quote! {}
-                }
-            };
+            let alignament_code = field.alignment_code(quote! {buf_pos});
             let refcode = 
             match ref_size {
                 1 => {
@@ -197,6 +204,14 @@ impl<'a> StructInfo<'a> {
                 }
                 _ => quote! {}
             };
+            if field.data_type.compressed {
+                let compressed_var = field.compressed_var();
+                return quote! {
+                    #refcode
+                    ptr::copy_nonoverlapping(#compressed_var.as_ptr(), buffer.add(buf_pos), #compressed_var.len());
+                    buf_pos += #compressed_var.len();
+                };
+            }
             quote! {
                 #alignament_code
                 #refcode
@@ -230,9 +245,14 @@ impl<'a> StructInfo<'a> {
         if self.config.validate_name {
             let has_name = constants::FLAG_HAS_NAME_HASH;
             let has_crc = constants::FLAG_HAS_CHECKSUM;
+            let has_mac = constants::FLAG_HAS_MAC;
+            let mac_size = constants::MAC_SIZE;
             let name_hash = hashes::fnv_32(self.name.to_string().as_str());
             quote! {
-                let name_offset = if header.flags & #has_crc != 0 { len - 8 } else { len - 4 };
+                let mut name_offset = if header.flags & #has_crc != 0 { len - 8 } else { len - 4 };
+                if header.flags & #has_mac != 0 {
+                    name_offset -= #mac_size;
+                }
                 if header.flags & #has_name == 0 {
                     return Err(flat_message::Error::NameNotStored);
                 }
@@ -268,12 +288,44 @@ impl<'a> StructInfo<'a> {
             crate::validate_checksum::ValidateChecksum::Ignore => quote! {},
         }
     }
-    fn generate_header_deserialization_code(&self) -> proc_macro2::TokenStream {
+    fn generate_migration_code(&self, runtime_options: bool) -> proc_macro2::TokenStream {
+        let types = &self.config.migrate_from;
+        let migrations = types.iter().enumerate().map(|(idx, ty)| {
+            let message = format!(
+                "Type '{}' (used in migrate_from for '{}') must have a version !",
+                ty.to_token_stream(),
+                self.name
+            );
+            let deserialize = if runtime_options {
+                quote! { deserialize_from_slice_with_options(input, options)? }
+            } else {
+                quote! { deserialize_from_slice_unchecked(input)? }
+            };
+            // each older version is converted into the next one (the last one into Self)
+            let chain = types[idx + 1..].iter().map(|next| {
+                quote! { let message: #next = message.into(); }
+            });
+            quote! {
+                const { assert!(<#ty as flat_message::FlatMessage<'_>>::VERSION.is_some(), #message) };
+                if <#ty as flat_message::FlatMessage<'_>>::VERSION == Some(header.version) {
+                    let message = <#ty as flat_message::FlatMessage<'_>>::#deserialize;
+                    #(#chain)*
+                    return Ok(message.into());
+                }
+            }
+        });
+        quote! { #(#migrations)* }
+    }
+    fn generate_header_deserialization_code(&self, runtime_options: bool) -> proc_macro2::TokenStream {
         let magic = constants::MAGIC_V1;
+        let magic_lz4 = constants::MAGIC_V1_LZ4;
         let has_crc = constants::FLAG_HAS_CHECKSUM;
         let has_name = constants::FLAG_HAS_NAME_HASH;
         let has_timestamp = constants::FLAG_HAS_TIMESTAMP;
         let has_unique_id = constants::FLAG_HAS_UNIQUEID;
+        let has_mac = constants::FLAG_HAS_MAC;
+        let mac_size = constants::MAC_SIZE;
+        let is_encrypted = constants::FLAG_IS_ENCRYPTED;
         let metadata_code = self.generate_metadata_deserialization_code();
         let name_validation = self.generate_name_validation_code();
         let version_compatibility_check =
@@ -282,10 +334,30 @@ impl<'a> StructInfo<'a> {
             } else {
                 quote! {}
             };
+        let migration_code = self.generate_migration_code(runtime_options);
+        // a set of versions provided at runtime replaces the one from the attribute
+        // (older versions that can be migrated do not have to be part of the attribute)
+        let version_compatibility_check = if runtime_options {
+            quote! {
+                if let Some(versions) = options.compatible_versions() {
+                    if !versions.contains(header.version) {
+                        return Err(flat_message::Error::IncompatibleVersion(header.version));
+                    }
+                }
+                #migration_code
+                if options.compatible_versions().is_none() {
+                    #version_compatibility_check
+                }
+            }
+        } else {
+            quote! {
+                #migration_code
+                #version_compatibility_check
+            }
+        };
 
         quote! {
                 use ::std::ptr;
-                let input = input.as_slice();
                 enum RefOffsetSize {
                     U8,
                     U16,
@@ -298,8 +370,14 @@ impl<'a> StructInfo<'a> {
                 let buffer = input.as_ptr();
                 let header: flat_message::headers::HeaderV1 = unsafe { ptr::read_unaligned(buffer as *const flat_message::headers::HeaderV1) };
                 if header.magic != #magic {
+                    if header.magic == #magic_lz4 {
+                        return Err(flat_message::Error::CompressedMessage);
+                    }
                     return Err(flat_message::Error::InvalidMagic);
                 }
+                if header.flags & #is_encrypted != 0 {
+                    return Err(flat_message::Error::EncryptedMessage);
+                }
                 #version_compatibility_check
                 let mut metadata_size = 0usize;
                 if header.flags & #has_crc != 0 {
@@ -314,6 +392,9 @@ impl<'a> StructInfo<'a> {
                 if header.flags & #has_unique_id != 0 {
                     metadata_size += 8;
                 }
+                if header.flags & #has_mac != 0 {
+                    metadata_size += #mac_size;
+                }
                 let ref_offset_size = match header.flags & 0b0000_0011 {
                     0 => RefOffsetSize::U8,
                     1 => RefOffsetSize::U16,
@@ -344,12 +425,14 @@ impl<'a> StructInfo<'a> {
     }
     fn generate_field_deserialize_code(
         &self,
-        serde_trait: &syn::Ident,
-        inner_var: &syn::Ident,
-        ty: &syn::Type,
-        field_name_hash: u32,
+        field: &FieldInfo,
         unchecked_code: bool,
     ) -> proc_macro2::TokenStream {
+        let serde_trait = &field.serialization_trait();
+        let inner_var = &field.inner_var();
+        let ty = &field.data_type.ty;
+        let field_name_hash = field.hash;
+        let compressed = field.data_type.compressed;
         let boundary_check = quote! {
             if offset<8 || offset >= hash_table_offset {
                 return Err(flat_message::Error::InvalidFieldOffset((offset as u32, hash_table_offset as u32)));
@@ -363,7 +446,8 @@ impl<'a> StructInfo<'a> {
                 return Err(flat_message::Error::FailToDeserialize(#field_name_hash));
             };
         };
-        let checks_and_init = if unchecked_code {
+        // compressed fields are always validated (decompression needs a valid LZ4 block)
+        let checks_and_init = if unchecked_code && !compressed {
             quote! {
                 #unsafe_init
             }
@@ -373,6 +457,37 @@ impl<'a> StructInfo<'a> {
                 #safe_init
             }
         };
+        if let Some((since, until)) = field.versions() {
+            // the field might be missing (if the message was created by a version where
+            // the field did not exist) => hashes are sorted, so stop at the first greater one
+            return quote! {
+                let mut field_offset = None;
+                while let Some(value) = it.as_slice().first() {
+                    if *value > #field_name_hash {
+                        break;
+                    }
+                    it.next();
+                    if *value == #field_name_hash {
+                        field_offset = Some(unsafe { ptr::read_unaligned(p_ofs) as usize });
+                        unsafe { p_ofs = p_ofs.add(1); }
+                        break;
+                    }
+                    unsafe { p_ofs = p_ofs.add(1); }
+                }
+                let #inner_var: #ty = match field_offset {
+                    Some(offset) => {
+                        #checks_and_init
+                        #inner_var
+                    }
+                    None => {
+                        if header.version >= #since && header.version <= #until {
+                            return Err(flat_message::Error::UnknownHash(#field_name_hash));
+                        }
+                        Default::default()
+                    }
+                };
+            };
+        }
         quote! {
             loop {
                 if let Some(value) = it.next() {
@@ -394,24 +509,9 @@ impl<'a> StructInfo<'a> {
         ref_size: u8,
         unchecked_code: bool,
     ) -> Vec<proc_macro2::TokenStream> {
-        struct HashAndInnerVar {
-            hash: u32,
-            inner_var: syn::Ident,
-            serde_trait: syn::Ident,
-            ty: syn::Type,
-        }
         let mut v = Vec::with_capacity(4);
-        let mut hashes: Vec<_> = self
-            .fields
-            .iter()
-            .map(|field| HashAndInnerVar {
-                hash: field.hash,
-                inner_var: field.inner_var(),
-                serde_trait: field.serialization_trait(),
-                ty: field.data_type.ty.clone(),
-            })
-            .collect();
-        hashes.sort_by_key(|hash| hash.hash);
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|field| field.hash);
         v.push(match ref_size {
             1 => quote! {
                 let mut p_ofs = unsafe { buffer.add(ref_table_offset) as *const u8 };
@@ -424,14 +524,8 @@ impl<'a> StructInfo<'a> {
             },
             _ => quote! {},
         });
-        for obj in hashes {
-            v.push(self.generate_field_deserialize_code(
-                &obj.serde_trait,
-                &obj.inner_var,
-                &obj.ty,
-                obj.hash,
-                unchecked_code,
-            ));
+        for field in fields {
+            v.push(self.generate_field_deserialize_code(field, unchecked_code));
         }
         v
     }
@@ -464,11 +558,11 @@ impl<'a> StructInfo<'a> {
             if !field.data_type.data_format.is_enum() {
                 continue;
             }
-            let s = format!("const _const_assertion_{}_{}: () = if <{} as {}>::DATA_FORMAT as u8 != flat_message::DataFormat::{} as u8 {{ panic!(\"Incorect representation for field {}::{} in the #[flat_message(...)] attribute ! Please check the #[repr(...)] attribute in the definition of enum '{}' and make sure it is the same in the attribute #[flat_message(...)] for the field {}::{} !\"); }};", 
+            let s = format!("const _const_assertion_{}_{}: () = if <{} as flat_message::{}>::DATA_FORMAT as u8 != flat_message::DataFormat::{} as u8 {{ panic!(\"Incorect representation for field {}::{} in the #[flat_message(...)] attribute ! Please check the #[repr(...)] attribute in the definition of enum '{}' and make sure it is the same in the attribute #[flat_message(...)] for the field {}::{} !\"); }};", 
                             name, 
                             field.name, 
                             field.data_type.name,
-                            field.data_type.field_type.serde_trait(), 
+                            field.data_type.field_type.element_serde_trait(),
                             field.data_type.data_format, 
                             name,
                             field.name,
@@ -545,37 +639,72 @@ impl<'a> StructInfo<'a> {
                 }
                 output.clear();
                 output.resize_zero(size);
-                let output = output.as_mut_slice();
-                // Step 8: write data directly to a raw pointer
-                let buffer: *mut u8 = output.as_mut_ptr();
-                unsafe {
-                    // write header
-                    ptr::write_unaligned(buffer as *mut flat_message::headers::HeaderV1, header);
-                    // write serialization code
-                    match offset_size {
-                        RefOffsetSize::U8 => {
-                            #(#serialize_code_u8)*
-                        }
-                        RefOffsetSize::U16 => {
-                            #(#serialize_code_u16)*
-                        }
-                        RefOffsetSize::U32 => {
-                            #(#serialize_code_u32)*
+                {
+                    let output = output.as_mut_slice();
+                    // Step 8: write data directly to a raw pointer
+                    let buffer: *mut u8 = output.as_mut_ptr();
+                    unsafe {
+                        // write header
+                        ptr::write_unaligned(buffer as *mut flat_message::headers::HeaderV1, header);
+                        // write serialization code
+                        match offset_size {
+                            RefOffsetSize::U8 => {
+                                #(#serialize_code_u8)*
+                            }
+                            RefOffsetSize::U16 => {
+                                #(#serialize_code_u16)*
+                            }
+                            RefOffsetSize::U32 => {
+                                #(#serialize_code_u32)*
+                            }
                         }
+                        // hash table
+                        #(#hash_table_code)*
+                        // metadata
+                        #(#metadata_serialization_code)*
+                        // CRC32 if case
+                        #checksum_code
                     }
-                    // hash table
-                    #(#hash_table_code)*
-                    // metadata
-                    #(#metadata_serialization_code)*
-                    // CRC32 if case
-                    #checksum_code
+                }
+                // Step 9: compress the data region (if required)
+                if config.compression() != flat_message::Compression::None {
+                    return flat_message::compression::compress(output, config.compression(), config.compression_threshold());
                 }
                 Ok(())
             }
         }
     }
+    fn generate_alignment_check_code(&self) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        if self.generics.lifetimes().count() == 0 {
+            // nothing is borrowed from the buffer => an unaligned buffer is copied into a Storage
+            let copy = |method: proc_macro2::TokenStream| {
+                quote! {
+                    if (input.as_ptr() as usize) & 15 != 0 {
+                        use ::flat_message::VecLike;
+                        let aligned = flat_message::Storage::from_buffer(input);
+                        return <Self as flat_message::FlatMessage<'_>>::#method;
+                    }
+                }
+            };
+            (
+                copy(quote! { deserialize_from_slice_with_options(aligned.as_slice(), options) }),
+                copy(quote! { deserialize_from_slice_unchecked(aligned.as_slice()) }),
+            )
+        } else {
+            // the hash table and the lists of values are read in place
+            (
+                quote! {
+                    if (input.as_ptr() as usize) & 15 != 0 {
+                        return Err(flat_message::Error::UnalignedBuffer);
+                    }
+                },
+                quote! {},
+            )
+        }
+    }
     fn generate_deserialize_from_methods(&self) -> proc_macro2::TokenStream {
-        let header_deserialization_code = self.generate_header_deserialization_code();
+        let header_deserialization_code = self.generate_header_deserialization_code(true);
+        let header_deserialization_code_unchecked = self.generate_header_deserialization_code(false);
         let deserializaton_code_u8 = self.generate_fields_deserialize_code(1, false);
         let deserializaton_code_u16 = self.generate_fields_deserialize_code(2, false);
         let deserializaton_code_u32 = self.generate_fields_deserialize_code(4, false);
@@ -586,12 +715,35 @@ impl<'a> StructInfo<'a> {
 
         let ctor_code = self.generate_struct_construction_code();
         let lifetimes = &self.generics.params;
+        let (alignment_check, alignment_check_unchecked) = self.generate_alignment_check_code();
         quote! {
             fn deserialize_from(input: & #lifetimes ::flat_message::Storage) -> core::result::Result<Self,flat_message::Error>
             {
                 use ::flat_message::VecLike;
+                Self::deserialize_from_slice(input.as_slice())
+            }
+            unsafe fn deserialize_from_unchecked(input: & #lifetimes ::flat_message::Storage) -> core::result::Result<Self,flat_message::Error>
+            {
+                use ::flat_message::VecLike;
+                Self::deserialize_from_slice_unchecked(input.as_slice())
+            }
+            fn deserialize_from_slice(input: & #lifetimes [u8]) -> core::result::Result<Self,flat_message::Error>
+            {
+                Self::deserialize_from_slice_with_options(input, &flat_message::DeserializeOptions::default())
+            }
+            fn deserialize_from_with_options(input: & #lifetimes ::flat_message::Storage, options: &flat_message::DeserializeOptions) -> core::result::Result<Self,flat_message::Error>
+            {
+                use ::flat_message::VecLike;
+                Self::deserialize_from_slice_with_options(input.as_slice(), options)
+            }
+            fn deserialize_from_slice_with_options(input: & #lifetimes [u8], options: &flat_message::DeserializeOptions) -> core::result::Result<Self,flat_message::Error>
+            {
+                #alignment_check
                 #header_deserialization_code
                 #checksum_check_code
+                if let Some(key) = options.authentication_key() {
+                    flat_message::authentication::verify_mac(key, input)?;
+                }
                 match ref_offset_size {
                     RefOffsetSize::U8 => {
                         #(#deserializaton_code_u8)*
@@ -607,10 +759,10 @@ impl<'a> StructInfo<'a> {
                     }
                 }
             }
-            unsafe fn deserialize_from_unchecked(input: & #lifetimes ::flat_message::Storage) -> core::result::Result<Self,flat_message::Error>
+            unsafe fn deserialize_from_slice_unchecked(input: & #lifetimes [u8]) -> core::result::Result<Self,flat_message::Error>
             {
-                use ::flat_message::VecLike;
-                #header_deserialization_code
+                #alignment_check_unchecked
+                #header_deserialization_code_unchecked
                 match ref_offset_size {
                     RefOffsetSize::U8 => {
                         #(#deserializaton_code_u8_unchecked)*
@@ -628,6 +780,69 @@ impl<'a> StructInfo<'a> {
             }
         }
     }
+    fn generate_schema_code(&self) -> proc_macro2::TokenStream {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|field| field.hash);
+        let fields = fields.iter().map(|field| {
+            let name = field.name.as_str();
+            let hash = field.hash;
+            let data_format = syn::Ident::new(
+                field.data_type.data_format.to_string().as_str(),
+                proc_macro2::Span::call_site(),
+            );
+            let kind = match field.data_type.field_type {
+                FieldType::Object => quote! { flat_message::FieldKind::Object },
+                FieldType::Slice => quote! { flat_message::FieldKind::Slice },
+                FieldType::Vector => quote! { flat_message::FieldKind::Vector },
+                FieldType::Array => quote! { flat_message::FieldKind::Array },
+                FieldType::Map => quote! { flat_message::FieldKind::Map },
+            };
+            let (enum_hash, enum_variants) = if field.data_type.data_format.is_enum() {
+                let ty: syn::Type = syn::parse_str(field.data_type.name.as_str())
+                    .expect("Failed to convert the enum name into a type");
+                (
+                    quote! { <#ty as flat_message::SerDe<'static>>::ENUM_HASH },
+                    quote! { <#ty as flat_message::SerDe<'static>>::ENUM_VARIANTS },
+                )
+            } else {
+                (quote! { None }, quote! { &[] })
+            };
+            let compressed = field.data_type.compressed;
+            let since = field.since;
+            let until = field.until;
+            quote! {
+                flat_message::FieldSchema {
+                    name: #name,
+                    hash: #hash,
+                    data_format: flat_message::DataFormat::#data_format,
+                    kind: #kind,
+                    enum_hash: #enum_hash,
+                    enum_variants: #enum_variants,
+                    compressed: #compressed,
+                    since: #since,
+                    until: #until,
+                },
+            }
+        });
+        let name = self.name.to_string();
+        let name_hash = hashes::fnv_32(name.as_str());
+        let version = self.config.version;
+        let store_name = self.config.namehash;
+        let metadata = self.config.metadata;
+        let checksum = self.config.checksum;
+        quote! {
+            /// Description of the structure (as it is stored in the serialized message)
+            pub const SCHEMA: &'static flat_message::Schema = &flat_message::Schema {
+                name: #name,
+                name_hash: #name_hash,
+                version: #version,
+                store_name: #store_name,
+                metadata: #metadata,
+                checksum: #checksum,
+                fields: &[#(#fields)*],
+            };
+        }
+    }
     pub(crate) fn generate_code(&self) -> proc_macro::TokenStream {
         let name = self.name;
         let visibility = self.visibility;
@@ -655,6 +870,23 @@ impl<'a> StructInfo<'a> {
         let deserialize_from_methods = self.generate_deserialize_from_methods();
         let derives = &self.derives;
         let const_assertion_functions = self.generate_const_assertion_functions();
+        let schema_code = self.generate_schema_code();
+        let name_const = if self.config.namehash {
+            let name_hash = hashes::fnv_32(self.name.to_string().as_str());
+            quote! {
+                const NAME: Option<flat_message::Name> = Some(flat_message::Name { value: #name_hash });
+            }
+        } else {
+            quote! {}
+        };
+        let version_const = if self.config.version != 0 {
+            let version = self.config.version;
+            quote! {
+                const VERSION: Option<u8> = Some(#version);
+            }
+        } else {
+            quote! {}
+        };
 
         let new_code = quote! {
 
@@ -666,7 +898,16 @@ impl<'a> StructInfo<'a> {
 
             #(#const_assertion_functions)*
 
+            impl #generics #name #generics {
+                #schema_code
+            }
+
             impl #generics flat_message::FlatMessage #implicit_lifetime for #name #generics {
+                #name_const
+                #version_const
+                fn schema() -> Option<&'static flat_message::Schema> {
+                    Some(Self::SCHEMA)
+                }
                 #metadata_methods
                 #serialize_to_methods
                 #deserialize_from_methods
@@ -686,6 +927,17 @@ impl<'a> StructInfo<'a> {
             for field in fields.named.iter() {
                 data_members.push(FieldInfo::try_from(field)?);
             }
+            for field in data_members.iter() {
+                if field.versions().is_none() {
+                    continue;
+                }
+                if config.version == 0 {
+                    return Err(format!("Field '{}' uses the 'since' or 'until' attributes, but the structure does not have a version (e.g. '#[flat_message(version: 2)]') !", field.name));
+                }
+                if field.since > config.version {
+                    return Err(format!("Field '{}' can not be added in version {} (the 'since' attribute) as the structure has version {} !", field.name, field.since, config.version));
+                }
+            }
             if data_members.len() > 0xFFFF {
                 return Err(format!("Structs with more than 65535 fields are not supported ! (Current structure has {} fields)", data_members.len()));
             }
@@ -0,0 +1,196 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Data, DeriveInput, Fields, GenericParam};
+
+pub struct UnionInfo {
+    name: syn::Ident,
+    generics: syn::Generics,
+    lifetime: Option<syn::Lifetime>,
+    variants: Vec<(syn::Ident, syn::Type)>,
+}
+
+impl UnionInfo {
+    fn generate_deserialize_code(&self, unchecked: bool) -> TokenStream {
+        let lifetime = self.lifetime();
+        let method = if unchecked {
+            quote! { deserialize_from_slice_unchecked(input) }
+        } else {
+            quote! { deserialize_from_slice_with_options(input, options) }
+        };
+        let checks = self.variants.iter().map(|(variant, ty)| {
+            let message = format!(
+                "Type '{}' (used by {}::{}) must store its name (store_name = true) to be used in a union !",
+                ty.to_token_stream(),
+                self.name,
+                variant
+            );
+            quote! {
+                const { assert!(<#ty as flat_message::FlatMessage<#lifetime>>::NAME.is_some(), #message) };
+                if name == <#ty as flat_message::FlatMessage<#lifetime>>::NAME {
+                    return Ok(Self::#variant(<#ty as flat_message::FlatMessage<#lifetime>>::#method?));
+                }
+            }
+        });
+        let unique_names = if unchecked {
+            quote! {}
+        } else {
+            self.generate_unique_name_checks()
+        };
+        quote! {
+            #unique_names
+            let name = flat_message::StructureInformation::try_from(input)?.name();
+            if name.is_none() {
+                return Err(flat_message::Error::NameNotStored);
+            }
+            #(#checks)*
+            Err(flat_message::Error::UnmatchedName)
+        }
+    }
+    // two variants with the same name hash would make the second one unreachable
+    fn generate_unique_name_checks(&self) -> TokenStream {
+        let lifetime = self.lifetime();
+        let mut checks = Vec::new();
+        for (idx, (first, first_ty)) in self.variants.iter().enumerate() {
+            for (second, second_ty) in self.variants[idx + 1..].iter() {
+                let message = format!(
+                    "Types '{}' (used by {}::{}) and '{}' (used by {}::{}) have the same name hash (the variants of a union must have different names) !",
+                    first_ty.to_token_stream(),
+                    self.name,
+                    first,
+                    second_ty.to_token_stream(),
+                    self.name,
+                    second
+                );
+                checks.push(quote! {
+                    const {
+                        let unique = match (
+                            <#first_ty as flat_message::FlatMessage<#lifetime>>::NAME,
+                            <#second_ty as flat_message::FlatMessage<#lifetime>>::NAME,
+                        ) {
+                            (Some(first), Some(second)) => first.value != second.value,
+                            _ => true,
+                        };
+                        assert!(unique, #message)
+                    };
+                });
+            }
+        }
+        quote! { #(#checks)* }
+    }
+    fn lifetime(&self) -> TokenStream {
+        match &self.lifetime {
+            Some(lifetime) => quote! { #lifetime },
+            None => quote! { 'a },
+        }
+    }
+    pub fn generate_code(&self) -> TokenStream {
+        let name = &self.name;
+        let generics = &self.generics;
+        let lifetime = self.lifetime();
+        let impl_generics = if self.lifetime.is_some() {
+            quote! { #generics }
+        } else {
+            quote! { <'a> }
+        };
+        let variants: Vec<_> = self.variants.iter().map(|(variant, _)| variant).collect();
+        let deserialize_code = self.generate_deserialize_code(false);
+        let deserialize_unchecked_code = self.generate_deserialize_code(true);
+        quote! {
+            impl #impl_generics flat_message::FlatMessage<#lifetime> for #name #generics {
+                fn metadata(&self) -> &flat_message::MetaData {
+                    match self {
+                        #(Self::#variants(message) => message.metadata(),)*
+                    }
+                }
+                fn update_metada(&mut self, new: flat_message::MetaData) {
+                    match self {
+                        #(Self::#variants(message) => message.update_metada(new),)*
+                    }
+                }
+                fn serialize_to<V: flat_message::VecLike>(&self, output: &mut V, config: flat_message::Config) -> core::result::Result<(), flat_message::Error> {
+                    match self {
+                        #(Self::#variants(message) => message.serialize_to(output, config),)*
+                    }
+                }
+                fn deserialize_from(input: & #lifetime flat_message::Storage) -> core::result::Result<Self, flat_message::Error> {
+                    use flat_message::VecLike;
+                    Self::deserialize_from_slice(input.as_slice())
+                }
+                unsafe fn deserialize_from_unchecked(input: & #lifetime flat_message::Storage) -> core::result::Result<Self, flat_message::Error> {
+                    use flat_message::VecLike;
+                    Self::deserialize_from_slice_unchecked(input.as_slice())
+                }
+                fn deserialize_from_slice(input: & #lifetime [u8]) -> core::result::Result<Self, flat_message::Error> {
+                    Self::deserialize_from_slice_with_options(input, &flat_message::DeserializeOptions::default())
+                }
+                fn deserialize_from_with_options(input: & #lifetime flat_message::Storage, options: &flat_message::DeserializeOptions) -> core::result::Result<Self, flat_message::Error> {
+                    use flat_message::VecLike;
+                    Self::deserialize_from_slice_with_options(input.as_slice(), options)
+                }
+                fn deserialize_from_slice_with_options(input: & #lifetime [u8], options: &flat_message::DeserializeOptions) -> core::result::Result<Self, flat_message::Error> {
+                    #deserialize_code
+                }
+                unsafe fn deserialize_from_slice_unchecked(input: & #lifetime [u8]) -> core::result::Result<Self, flat_message::Error> {
+                    #deserialize_unchecked_code
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<DeriveInput> for UnionInfo {
+    type Error = String;
+
+    fn try_from(input: DeriveInput) -> Result<Self, Self::Error> {
+        let name = input.ident.clone();
+        let Data::Enum(data) = &input.data else {
+            return Err(format!("FlatMessageUnion can only be derived for enums ('{}' is not an enum) !", name));
+        };
+        let mut lifetime = None;
+        for param in input.generics.params.iter() {
+            match param {
+                GenericParam::Lifetime(l) if lifetime.is_none() => lifetime = Some(l.lifetime.clone()),
+                _ => {
+                    return Err(format!(
+                        "FlatMessageUnion '{}' can have at most one lifetime parameter (and no other generic parameters) !",
+                        name
+                    ))
+                }
+            }
+        }
+        if data.variants.is_empty() {
+            return Err(format!("FlatMessageUnion '{}' must have at least one variant !", name));
+        }
+        let mut variants = Vec::with_capacity(data.variants.len());
+        for variant in data.variants.iter() {
+            match &variant.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    let ty = fields.unnamed[0].ty.clone();
+                    let type_name = ty.to_token_stream().to_string();
+                    if let Some((other, _)) = variants
+                        .iter()
+                        .find(|(_, t): &&(syn::Ident, syn::Type)| t.to_token_stream().to_string() == type_name)
+                    {
+                        return Err(format!(
+                            "Variants '{}::{}' and '{}::{}' have the same type ({}). Each variant of a union must have a different message type !",
+                            name, other, name, variant.ident, type_name
+                        ));
+                    }
+                    variants.push((variant.ident.clone(), ty));
+                }
+                _ => {
+                    return Err(format!(
+                        "Variant '{}::{}' must have exactly one unnamed field (the message type), for example: {}(MyMessage) !",
+                        name, variant.ident, variant.ident
+                    ))
+                }
+            }
+        }
+        Ok(UnionInfo {
+            name,
+            generics: input.generics,
+            lifetime,
+            variants,
+        })
+    }
+}
@@ -109,6 +109,19 @@ pub(crate) fn to_version(value: &str) -> Option<u8> {
     }
 }
 
+pub(crate) fn to_type_list(value: &str) -> Result<Vec<syn::Type>, String> {
+    use syn::parse::Parser;
+    let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) else {
+        return Err(format!("Expecting a list of types (e.g. '[v1::Foo, v2::Foo]') but got '{}' !", value));
+    };
+    let parser = syn::punctuated::Punctuated::<syn::Type, syn::Token![,]>::parse_terminated;
+    match parser.parse_str(inner) {
+        Ok(types) if types.is_empty() => Err("The list of types can not be empty !".to_string()),
+        Ok(types) => Ok(types.into_iter().collect()),
+        Err(e) => Err(format!("Invalid list of types '{}' ({}) !", value, e)),
+    }
+}
+
 fn find_lifetime(input: &str) -> Option<(usize, usize)> {
     let mut start = 0;
     let mut found = false;
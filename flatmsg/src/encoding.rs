@@ -0,0 +1,39 @@
+// decoders for messages passed as text (whitespace is ignored)
+
+pub fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("Invalid hex string (odd number of digits) !".to_string());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| "Invalid hex string !")?;
+            u8::from_str_radix(pair, 16).map_err(|_| format!("Invalid hex digits '{}' !", pair))
+        })
+        .collect()
+}
+
+pub fn from_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return Err(format!("Invalid base64 character '{}' !", c as char)),
+        };
+        accumulator = (accumulator << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((accumulator >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,476 @@
+use common::constants::*;
+use common::hashes;
+use flat_message::headers::{HeaderV1, Layout};
+use flat_message::size::{self, Format};
+use flat_message::{
+    compression, Config, DataFormat, Name, NameRegistry, SerDe, SerDeCompressed, SerDeSlice,
+    SerDeVec, Storage, VecLike,
+};
+use serde_json::{json, Value};
+use std::mem::size_of;
+
+const FLAG_NAMES: [(u8, &str); 6] = [
+    (FLAG_HAS_CHECKSUM, "checksum"),
+    (FLAG_HAS_NAME_HASH, "name"),
+    (FLAG_HAS_TIMESTAMP, "timestamp"),
+    (FLAG_HAS_UNIQUEID, "unique_id"),
+    (FLAG_HAS_MAC, "mac"),
+    (FLAG_IS_ENCRYPTED, "encrypted"),
+];
+
+struct Checksum {
+    stored: u32,
+    computed: u32,
+}
+
+struct Field {
+    hash: u32,
+    name: Option<String>,
+    data_format: Option<DataFormat>,
+    list: bool,
+    compressed: bool,
+    offset: usize,
+    value: Option<Value>,
+    // false for the values that are not decoded by the inspector (lists of nested messages and
+    // lists of bytes), which is not an error
+    decoded: bool,
+}
+
+/// Everything that could be read from a serialized message
+pub struct Report {
+    size: usize,
+    magic: u32,
+    fields_count: u16,
+    version: u8,
+    flags: u8,
+    offset_size: usize,
+    timestamp: Option<u64>,
+    unique_id: Option<u64>,
+    name_hash: Option<u32>,
+    name: Option<String>,
+    key_id: Option<u32>,
+    checksum: Option<Checksum>,
+    fields: Vec<Field>,
+    // problems found while decoding (the message is still reported)
+    errors: Vec<String>,
+}
+
+fn read<const N: usize>(buf: &[u8], pos: usize) -> [u8; N] {
+    buf[pos..pos + N].try_into().unwrap()
+}
+
+/// Decodes a message. Only the header, the metadata and the field table need to be valid,
+/// anything else (checksum, field values) is reported in `errors`.
+pub fn inspect(buf: &[u8], names: &NameRegistry) -> Result<Report, String> {
+    let layout = Layout::new(buf).map_err(|e| format!("{} !", e))?;
+    let flags = layout.header.flags;
+    let mut report = Report {
+        size: buf.len(),
+        magic: layout.header.magic,
+        fields_count: layout.header.fields_count,
+        version: layout.header.version,
+        flags,
+        offset_size: layout.offset_size,
+        timestamp: None,
+        unique_id: None,
+        name_hash: None,
+        name: None,
+        key_id: None,
+        checksum: None,
+        fields: Vec::new(),
+        errors: Vec::new(),
+    };
+    if let Some(offset) = layout.trailer_entry(FLAG_HAS_TIMESTAMP) {
+        report.timestamp = Some(u64::from_ne_bytes(read(buf, offset)));
+    }
+    if let Some(offset) = layout.trailer_entry(FLAG_HAS_UNIQUEID) {
+        report.unique_id = Some(u64::from_ne_bytes(read(buf, offset)));
+    }
+    if let Some(offset) = layout.trailer_entry(FLAG_HAS_NAME_HASH) {
+        let hash = u32::from_ne_bytes(read(buf, offset));
+        report.name_hash = Some(hash);
+        report.name = names.name(Name::new(hash)).map(str::to_string);
+    }
+    if let Some(offset) = layout.trailer_entry(FLAG_IS_ENCRYPTED) {
+        // key id, nonce and tag
+        report.key_id = Some(u32::from_ne_bytes(read(buf, offset)));
+    }
+    if let Some(offset) = layout.trailer_entry(FLAG_HAS_CHECKSUM) {
+        let checksum = Checksum {
+            stored: u32::from_ne_bytes(read(buf, offset)),
+            computed: hashes::crc32(&buf[..offset]),
+        };
+        if checksum.stored != checksum.computed {
+            report.errors.push("Invalid checksum !".to_string());
+        }
+        report.checksum = Some(checksum);
+    }
+    if layout.has_flag(FLAG_IS_ENCRYPTED) {
+        // the fields can not be decoded
+        return Ok(report);
+    }
+    // fields are decoded from an aligned (and decompressed) copy
+    let mut storage = Storage::from_buffer(buf);
+    if layout.header.magic == MAGIC_V1_LZ4 {
+        if let Err(e) = compression::decompress(&mut storage, Config::default()) {
+            report.errors.push(format!("Failed to decompress: {}", e));
+            return Ok(report);
+        }
+    }
+    let buf = storage.as_slice();
+    let layout = Layout::new(buf).map_err(|e| format!("{} !", e))?;
+    for index in 0..layout.header.fields_count as usize {
+        let Some((hash, offset)) = layout.field(buf, index) else {
+            break;
+        };
+        let data_format = DataFormat::try_from((hash & 0x3F) as u8).ok();
+        let mut field = Field {
+            hash,
+            name: names.field(hash).map(str::to_string),
+            data_format,
+            list: hash & 0x80 != 0,
+            compressed: hash & 0x40 != 0,
+            offset,
+            value: None,
+            decoded: data_format.is_some_and(|f| is_decoded(f, hash & 0x80 != 0)),
+        };
+        if offset < size_of::<HeaderV1>() || offset >= layout.hash_table_offset {
+            report.errors.push(format!(
+                "Invalid offset {} for field 0x{:08X} !",
+                offset, hash
+            ));
+        } else if data_format.is_none() || field.decoded {
+            field.value = data_format
+                .and_then(|f| decode(buf, offset, f, field.list, field.compressed, names));
+            if field.value.is_none() {
+                report
+                    .errors
+                    .push(format!("Failed to decode field 0x{:08X} !", hash));
+            }
+        }
+        report.fields.push(field);
+    }
+    Ok(report)
+}
+
+fn value<'a, T: SerDe<'a> + Into<Value>>(buf: &'a [u8], pos: usize) -> Option<Value> {
+    T::from_buffer(buf, pos).map(Into::into)
+}
+
+fn slice<'a, T: SerDeSlice<'a> + Copy + Into<Value> + 'a>(
+    buf: &'a [u8],
+    pos: usize,
+) -> Option<Value> {
+    let values = T::from_buffer(buf, pos)?;
+    Some(Value::Array(values.iter().map(|v| (*v).into()).collect()))
+}
+
+// 128 bit values do not fit in a JSON number
+fn value_128<'a, T: SerDe<'a> + ToString>(buf: &'a [u8], pos: usize) -> Option<Value> {
+    T::from_buffer(buf, pos).map(|v| Value::String(v.to_string()))
+}
+
+fn slice_128<'a, T: SerDeSlice<'a> + ToString + 'a>(buf: &'a [u8], pos: usize) -> Option<Value> {
+    let values = T::from_buffer(buf, pos)?;
+    Some(Value::Array(
+        values
+            .iter()
+            .map(|v| Value::String(v.to_string()))
+            .collect(),
+    ))
+}
+
+// enums are stored as the enum hash (u32) followed by the value (or the list of values)
+fn enum_values(buf: &[u8], pos: usize, size: usize, signed: bool, list: bool) -> Option<Value> {
+    let to_value = |pos: usize| -> Option<Value> {
+        let bytes = buf.get(pos..pos + size)?;
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(bytes);
+        let value = u64::from_ne_bytes(raw);
+        Some(if signed {
+            // sign extend
+            let shift = 64 - size * 8;
+            Value::from(((value << shift) as i64) >> shift)
+        } else {
+            Value::from(value)
+        })
+    };
+    let enum_hash = u32::from_ne_bytes(buf.get(pos..pos + 4)?.try_into().ok()?);
+    if !list {
+        return Some(json!({ "enum_hash": enum_hash, "value": to_value(pos + 4)? }));
+    }
+    let format = match size {
+        1 => Format::U8withExtension,
+        2 => Format::U16withExtension,
+        _ => Format::U32,
+    };
+    let (count, size_len) = size::read(buf.as_ptr(), pos + 4, buf.len(), format)?;
+    let start = pos + 4 + size_len;
+    let values = (0..count)
+        .map(|index| to_value(start + index * size))
+        .collect::<Option<Vec<_>>>()?;
+    Some(json!({ "enum_hash": enum_hash, "values": values }))
+}
+
+// lists of nested messages and lists of bytes are valid, but they are not decoded
+fn is_decoded(data_format: DataFormat, list: bool) -> bool {
+    !(list && matches!(data_format, DataFormat::GenericObject | DataFormat::Bytes))
+}
+
+// nested messages (maps and structures written by `serde_wire`) are stored as a u32 size
+// followed by the message (aligned to 16 bytes) and are decoded as an object with their fields
+fn nested(buf: &[u8], pos: usize, names: &NameRegistry) -> Option<Value> {
+    let size = <u32 as SerDe>::from_buffer(buf, pos)? as usize;
+    let start = pos.checked_add(16)?;
+    let message = buf.get(start..start.checked_add(size)?)?;
+    let report = inspect(message, names).ok()?;
+    if !report.is_valid() {
+        return None;
+    }
+    // the two lists of a map
+    let name_hash = |name| hashes::field_hash(name, DataFormat::U8, false, false) & 0xFFFFFF00;
+    let map_names = [("keys", name_hash("keys")), ("values", name_hash("values"))];
+    let fields = report.fields.into_iter().map(|f| {
+        let name = f.name.or_else(|| {
+            map_names
+                .iter()
+                .find(|(_, hash)| f.hash & 0xFFFFFF00 == *hash)
+                .map(|(name, _)| name.to_string())
+        });
+        (
+            name.unwrap_or_else(|| format!("0x{:08X}", f.hash)),
+            f.value.unwrap_or(Value::Null),
+        )
+    });
+    Some(Value::Object(fields.collect()))
+}
+
+fn decode(
+    buf: &[u8],
+    pos: usize,
+    data_format: DataFormat,
+    list: bool,
+    compressed: bool,
+    names: &NameRegistry,
+) -> Option<Value> {
+    if compressed {
+        return match (data_format, list) {
+            (DataFormat::String, false) => {
+                <String as SerDeCompressed>::from_buffer(buf, pos).map(Value::from)
+            }
+            (DataFormat::String, true) => {
+                <Vec<String> as SerDeCompressed>::from_buffer(buf, pos).map(Value::from)
+            }
+            (DataFormat::U8, true) => {
+                <Vec<u8> as SerDeCompressed>::from_buffer(buf, pos).map(Value::from)
+            }
+            _ => None,
+        };
+    }
+    match (data_format, list) {
+        (DataFormat::U8, false) => value::<u8>(buf, pos),
+        (DataFormat::U16, false) => value::<u16>(buf, pos),
+        (DataFormat::U32, false) => value::<u32>(buf, pos),
+        (DataFormat::U64, false) => value::<u64>(buf, pos),
+        (DataFormat::U128, false) => value_128::<u128>(buf, pos),
+        (DataFormat::I8, false) => value::<i8>(buf, pos),
+        (DataFormat::I16, false) => value::<i16>(buf, pos),
+        (DataFormat::I32, false) => value::<i32>(buf, pos),
+        (DataFormat::I64, false) => value::<i64>(buf, pos),
+        (DataFormat::I128, false) => value_128::<i128>(buf, pos),
+        (DataFormat::F32, false) => value::<f32>(buf, pos),
+        (DataFormat::F64, false) => value::<f64>(buf, pos),
+        (DataFormat::Bool, false) => value::<bool>(buf, pos),
+        (DataFormat::String, false) => value::<&str>(buf, pos),
+        (DataFormat::U8, true) => slice::<u8>(buf, pos),
+        (DataFormat::U16, true) => slice::<u16>(buf, pos),
+        (DataFormat::U32, true) => slice::<u32>(buf, pos),
+        (DataFormat::U64, true) => slice::<u64>(buf, pos),
+        (DataFormat::U128, true) => slice_128::<u128>(buf, pos),
+        (DataFormat::I8, true) => slice::<i8>(buf, pos),
+        (DataFormat::I16, true) => slice::<i16>(buf, pos),
+        (DataFormat::I32, true) => slice::<i32>(buf, pos),
+        (DataFormat::I64, true) => slice::<i64>(buf, pos),
+        (DataFormat::I128, true) => slice_128::<i128>(buf, pos),
+        (DataFormat::F32, true) => slice::<f32>(buf, pos),
+        (DataFormat::F64, true) => slice::<f64>(buf, pos),
+        (DataFormat::Bool, true) => slice::<bool>(buf, pos),
+        (DataFormat::String, true) => <&str as SerDeVec>::from_buffer(buf, pos).map(Value::from),
+        (DataFormat::EnumU8, _) => enum_values(buf, pos, 1, false, list),
+        (DataFormat::EnumU16, _) => enum_values(buf, pos, 2, false, list),
+        (DataFormat::EnumU32, _) => enum_values(buf, pos, 4, false, list),
+        (DataFormat::EnumU64, _) => enum_values(buf, pos, 8, false, list),
+        (DataFormat::EnumI8, _) => enum_values(buf, pos, 1, true, list),
+        (DataFormat::EnumI16, _) => enum_values(buf, pos, 2, true, list),
+        (DataFormat::EnumI32, _) => enum_values(buf, pos, 4, true, list),
+        (DataFormat::EnumI64, _) => enum_values(buf, pos, 8, true, list),
+        (DataFormat::Bytes, false) => <&[u8] as SerDe>::from_buffer(buf, pos).map(Value::from),
+        (DataFormat::GenericObject, false) => nested(buf, pos, names),
+        (DataFormat::GenericObject, true) | (DataFormat::Bytes, true) => None,
+    }
+}
+
+// milliseconds since the unix epoch as "YYYY-MM-DD HH:MM:SS.mmm UTC"
+fn format_timestamp(timestamp: u64) -> String {
+    let (days, ms) = (timestamp / 86_400_000, timestamp % 86_400_000);
+    // civil date from the number of days (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} UTC",
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+impl Report {
+    fn flag_names(&self) -> Vec<&'static str> {
+        FLAG_NAMES
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let fields: Vec<Value> = self
+            .fields
+            .iter()
+            .map(|f| {
+                json!({
+                    "hash": f.hash,
+                    "name": f.name,
+                    "data_format": f.data_format.map(|d| d.to_string()),
+                    "list": f.list,
+                    "compressed": f.compressed,
+                    "offset": f.offset,
+                    "value": f.value,
+                    "decoded": f.decoded,
+                })
+            })
+            .collect();
+        json!({
+            "size": self.size,
+            "magic": format!("0x{:08X}", self.magic),
+            "compressed": self.magic == MAGIC_V1_LZ4,
+            "version": self.version,
+            "fields_count": self.fields_count,
+            "flags": self.flags,
+            "flag_names": self.flag_names(),
+            "offset_size": self.offset_size,
+            "timestamp": self.timestamp,
+            "date": self.timestamp.map(format_timestamp),
+            "unique_id": self.unique_id,
+            "name_hash": self.name_hash,
+            "name": self.name,
+            "encryption_key_id": self.key_id,
+            "checksum": self.checksum.as_ref().map(|c| json!({
+                "stored": c.stored,
+                "computed": c.computed,
+                "valid": c.stored == c.computed,
+            })),
+            "fields": fields,
+            "errors": self.errors,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        let kind = if self.magic == MAGIC_V1_LZ4 {
+            "LZ4 compressed"
+        } else {
+            "plain"
+        };
+        lines.push(format!("Size        : {} bytes ({})", self.size, kind));
+        lines.push(format!(
+            "Version     : {}",
+            if self.version == 0 {
+                "-".to_string()
+            } else {
+                self.version.to_string()
+            }
+        ));
+        lines.push(format!(
+            "Flags       : 0x{:02X} [{}]",
+            self.flags,
+            self.flag_names().join(", ")
+        ));
+        lines.push(format!("Offset size : {} byte(s)", self.offset_size));
+        if let Some(hash) = self.name_hash {
+            let name = self.name.as_deref().unwrap_or("?");
+            lines.push(format!("Name        : {} (0x{:08X})", name, hash));
+        }
+        if let Some(timestamp) = self.timestamp {
+            lines.push(format!(
+                "Timestamp   : {} ({})",
+                format_timestamp(timestamp),
+                timestamp
+            ));
+        }
+        if let Some(unique_id) = self.unique_id {
+            lines.push(format!("Unique ID   : {}", unique_id));
+        }
+        if let Some(key_id) = self.key_id {
+            lines.push(format!("Key ID      : {}", key_id));
+        }
+        if let Some(c) = &self.checksum {
+            let state = if c.stored == c.computed {
+                "valid".to_string()
+            } else {
+                format!("INVALID, computed 0x{:08X}", c.computed)
+            };
+            lines.push(format!("Checksum    : 0x{:08X} ({})", c.stored, state));
+        }
+        let encrypted = if self.key_id.is_some() {
+            " (encrypted)"
+        } else {
+            ""
+        };
+        lines.push(format!("Fields      : {}{}", self.fields_count, encrypted));
+        for f in self.fields.iter() {
+            let mut data_format = f
+                .data_format
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "?".to_string());
+            if f.list {
+                data_format = format!("[{}]", data_format);
+            }
+            if f.compressed {
+                data_format.push_str(" lz4");
+            }
+            let value = match &f.value {
+                Some(value) => value.to_string(),
+                None if f.data_format.is_some() && !f.decoded => "(not decoded)".to_string(),
+                None => "?".to_string(),
+            };
+            lines.push(format!(
+                "  0x{:08X} {:<16} {:<14} @{:<6} = {}",
+                f.hash,
+                f.name.as_deref().unwrap_or("?"),
+                data_format,
+                f.offset,
+                value
+            ));
+        }
+        for e in self.errors.iter() {
+            lines.push(format!("Error       : {}", e));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,5 @@
+//! The `flatmsg` command line tool. The message inspector and the decoders for messages passed as
+//! text are also available as a library.
+pub mod encoding;
+pub mod inspect;
+pub mod schema_json;
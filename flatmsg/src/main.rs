@@ -0,0 +1,184 @@
+use clap::{Parser, Subcommand};
+use flat_message::{Config, FrameReader, NameRegistry, VecLike};
+use flatmsg::inspect::{self, Report};
+use flatmsg::{encoding, schema_json};
+use std::io::Read;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(about = "Command line tools for FlatMessage")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reports the wire-breaking changes between two schemas (JSON files created with
+    /// `Schema::to_json()`). Exits with code 1 if there are breaking changes.
+    CheckSchema { old: String, new: String },
+    /// Prints the header, the metadata and the decoded fields of a serialized message.
+    /// Exits with code 1 if the message is not valid (for example an invalid checksum).
+    Inspect {
+        /// File with the message (stdin if missing)
+        file: Option<String>,
+        /// The input is a hex string
+        #[arg(long, conflicts_with = "base64")]
+        hex: bool,
+        /// The input is a base64 string
+        #[arg(long)]
+        base64: bool,
+        /// File with structure and field names (one per line) used to resolve hashes
+        #[arg(long)]
+        names: Option<String>,
+        /// Prints the result as JSON
+        #[arg(long)]
+        json: bool,
+        /// The input is a stream of framed messages (written with `FrameWriter`)
+        #[arg(long)]
+        frames: bool,
+    },
+}
+
+fn read_schema(path: &str) -> Result<&'static flat_message::Schema, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    schema_json::parse(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn check_schema(old: &str, new: &str) -> Result<ExitCode, String> {
+    let old = read_schema(old)?;
+    let new = read_schema(new)?;
+    let changes = old.breaking_changes(new);
+    if changes.is_empty() {
+        println!("{}: no breaking changes", new.name);
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!("{}: {} breaking change(s)", new.name, changes.len());
+    for change in changes {
+        println!("  - {}", change);
+    }
+    Ok(ExitCode::FAILURE)
+}
+
+struct InspectOptions<'a> {
+    file: Option<&'a str>,
+    hex: bool,
+    base64: bool,
+    names: Option<&'a str>,
+    json: bool,
+    frames: bool,
+}
+
+fn read_input(options: &InspectOptions) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    match options.file {
+        Some(path) => data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            std::io::stdin()
+                .read_to_end(&mut data)
+                .map_err(|e| format!("stdin: {}", e))?;
+        }
+    }
+    if options.hex || options.base64 {
+        let text = String::from_utf8(data).map_err(|_| "The input is not a text !")?;
+        if options.hex {
+            return encoding::from_hex(&text);
+        }
+        return encoding::from_base64(&text);
+    }
+    Ok(data)
+}
+
+fn inspect(options: &InspectOptions) -> Result<ExitCode, String> {
+    let data = read_input(options)?;
+    let mut names = NameRegistry::new();
+    if let Some(path) = options.names {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for name in text.lines().map(str::trim) {
+            if !name.is_empty() && !name.starts_with('#') {
+                names.register_name(name);
+            }
+        }
+        for collision in names.collisions() {
+            eprintln!("warning: {}", collision);
+        }
+    }
+    let mut reports: Vec<Result<Report, String>> = Vec::new();
+    let mut skipped = 0;
+    if options.frames {
+        let mut reader = FrameReader::new(data.as_slice(), Config::default());
+        while let Some(storage) = reader.read().map_err(|e| e.to_string())? {
+            reports.push(inspect::inspect(storage.as_slice(), &names));
+        }
+        skipped = reader.skipped();
+    } else {
+        reports.push(inspect::inspect(&data, &names));
+    }
+    let valid = skipped == 0
+        && reports
+            .iter()
+            .all(|r| r.as_ref().is_ok_and(Report::is_valid));
+    if options.json {
+        let to_json = |r: &Result<Report, String>| match r {
+            Ok(report) => report.to_json(),
+            Err(e) => serde_json::json!({ "errors": [e] }),
+        };
+        let output = if options.frames {
+            serde_json::json!({
+                "messages": reports.iter().map(to_json).collect::<Vec<_>>(),
+                "skipped": skipped,
+            })
+        } else {
+            to_json(&reports[0])
+        };
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    } else {
+        for (index, report) in reports.iter().enumerate() {
+            if options.frames {
+                println!("--- Message #{} ---", index);
+            }
+            match report {
+                Ok(report) => println!("{}", report.to_text()),
+                Err(e) => println!("Error       : {}", e),
+            }
+        }
+        if options.frames {
+            println!(
+                "--- {} message(s), {} byte(s) skipped ---",
+                reports.len(),
+                skipped
+            );
+        }
+    }
+    Ok(if valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match &args.command {
+        Command::CheckSchema { old, new } => check_schema(old, new),
+        Command::Inspect {
+            file,
+            hex,
+            base64,
+            names,
+            json,
+            frames,
+        } => inspect(&InspectOptions {
+            file: file.as_deref(),
+            hex: *hex,
+            base64: *base64,
+            names: names.as_deref(),
+            json: *json,
+            frames: *frames,
+        }),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        ExitCode::from(2)
+    })
+}
@@ -0,0 +1,87 @@
+use flat_message::{FieldKind, FieldSchema, Schema};
+use serde_json::Value;
+
+/// Reads a schema produced by `Schema::to_json()`. The strings are leaked (`Schema` only holds
+/// static references) which is fine for a short lived process.
+pub fn parse(text: &str) -> Result<&'static Schema, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let fields = value["fields"]
+        .as_array()
+        .ok_or("Missing 'fields' array")?
+        .iter()
+        .map(parse_field)
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Box::leak(Box::new(Schema {
+        name: leak(string(&value, "name")?),
+        name_hash: number(&value, "name_hash")? as u32,
+        version: number(&value, "version")? as u8,
+        store_name: boolean(&value, "store_name")?,
+        metadata: boolean(&value, "metadata")?,
+        checksum: boolean(&value, "checksum")?,
+        fields: fields.leak(),
+    })))
+}
+
+fn parse_field(value: &Value) -> Result<FieldSchema, String> {
+    let data_format = string(value, "data_format")?;
+    let kind = match string(value, "kind")? {
+        "object" => FieldKind::Object,
+        "slice" => FieldKind::Slice,
+        "vector" => FieldKind::Vector,
+        "array" => FieldKind::Array,
+        "map" => FieldKind::Map,
+        other => return Err(format!("Invalid field kind '{}'", other)),
+    };
+    Ok(FieldSchema {
+        name: leak(string(value, "name")?),
+        hash: number(value, "hash")? as u32,
+        data_format: data_format.parse()?,
+        kind,
+        enum_hash: value["enum_hash"].as_u64().map(|h| h as u32),
+        enum_variants: parse_variants(value)?,
+        compressed: boolean(value, "compressed")?,
+        since: number(value, "since")? as u8,
+        until: number(value, "until")? as u8,
+    })
+}
+
+// `[["name", value], ...]` (missing for fields that are not enums)
+fn parse_variants(value: &Value) -> Result<&'static [(&'static str, i128)], String> {
+    let Some(variants) = value["enum_variants"].as_array() else {
+        return Ok(&[]);
+    };
+    let variants = variants
+        .iter()
+        .map(|v| {
+            match (
+                v[0].as_str(),
+                v[1].as_i64()
+                    .map(i128::from)
+                    .or(v[1].as_u64().map(i128::from)),
+            ) {
+                (Some(name), Some(value)) => Ok((leak(name), value)),
+                _ => Err(format!("Invalid enum variant '{}'", v)),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(variants.leak())
+}
+
+fn leak(s: &str) -> &'static str {
+    Box::leak(s.to_string().into_boxed_str())
+}
+fn string<'a>(value: &'a Value, key: &str) -> Result<&'a str, String> {
+    value[key]
+        .as_str()
+        .ok_or(format!("Missing or invalid '{}'", key))
+}
+fn number(value: &Value, key: &str) -> Result<u64, String> {
+    value[key]
+        .as_u64()
+        .ok_or(format!("Missing or invalid '{}'", key))
+}
+fn boolean(value: &Value, key: &str) -> Result<bool, String> {
+    value[key]
+        .as_bool()
+        .ok_or(format!("Missing or invalid '{}'", key))
+}